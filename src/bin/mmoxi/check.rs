@@ -0,0 +1,541 @@
+//! Nagios/Icinga check plugins.
+//!
+//! Checks print the usual plugin output, i.e. a single line with the state, a
+//! message and performance data, and exit with the state's exit code. Errors,
+//! including usage errors, are reported as `UNKNOWN`.
+
+use std::fmt::{self, Display};
+use std::io;
+use std::process::ExitCode;
+
+use anyhow::{Result, anyhow, ensure};
+use clap::ArgMatches;
+
+use mmoxi::disk::Availability;
+use mmoxi::run::Runner;
+
+/// Runs a check and returns its exit code.
+pub fn run(runner: &dyn Runner, args: &ArgMatches) -> ExitCode {
+    let Some((name, args)) = args.subcommand() else {
+        unreachable!("subcommand is required");
+    };
+
+    let mut check = Check::new(name);
+
+    let result = match name {
+        "deadlocks" => deadlocks(runner, &mut check),
+        "disks" => disks(runner, args, &mut check),
+        "fileset-inodes" => fileset_inodes(runner, args, &mut check),
+        "pool" => pool(runner, args, &mut check),
+        "quota" => quota(args, &mut check),
+
+        _ => Err(anyhow!("unknown check: {name}")),
+    };
+
+    let state = match result {
+        Ok(()) => check.report(),
+
+        Err(error) => {
+            println!("{} {} - {error:#}", check.name, State::Unknown);
+            State::Unknown
+        }
+    };
+
+    ExitCode::from(state.code())
+}
+
+// ----------------------------------------------------------------------------
+// checks
+// ----------------------------------------------------------------------------
+
+fn deadlocks(runner: &dyn Runner, check: &mut Check) -> Result<()> {
+    let data = mmoxi::diag::deadlock(runner)?;
+    let nodes = data.nodes();
+
+    if !nodes.is_empty() {
+        check.problem(
+            State::Critical,
+            format!("deadlock on {}", nodes.join(", ")),
+        );
+    }
+
+    check.perfdata("deadlocks", nodes.len(), "", None, None);
+    check.ok("no deadlocks");
+
+    Ok(())
+}
+
+fn disks(
+    runner: &dyn Runner,
+    args: &ArgMatches,
+    check: &mut Check,
+) -> Result<()> {
+    let mut total = 0;
+    let mut not_up = 0;
+
+    for fs in filesystems(runner, args)? {
+        for disk in mmoxi::disk::disks(runner, &fs)? {
+            total += 1;
+
+            let state = match disk.availability() {
+                Availability::Up => continue,
+                Availability::Recovering => State::Warning,
+                Availability::Unknown(_) => State::Unknown,
+                _ => State::Critical,
+            };
+
+            not_up += 1;
+
+            check.problem(
+                state,
+                format!(
+                    "{fs} disk {} is {}",
+                    disk.nsd_name(),
+                    disk.availability()
+                ),
+            );
+        }
+    }
+
+    check.perfdata("disks", total, "", None, None);
+    check.perfdata("not_up", not_up, "", None, None);
+    check.ok(format!("all {total} disks are up"));
+
+    Ok(())
+}
+
+fn fileset_inodes(
+    runner: &dyn Runner,
+    args: &ArgMatches,
+    check: &mut Check,
+) -> Result<()> {
+    let thresholds = Thresholds::from_args(args)?;
+
+    let mut total = 0;
+
+    for fs in filesystems(runner, args)? {
+        let filesets = mmoxi::fileset::filesets(runner, &fs)?;
+
+        for fileset in filesets {
+            if !fileset.is_inode_space_owner() || fileset.max_inodes() == 0 {
+                continue;
+            }
+
+            total += 1;
+
+            let label = format!("{fs}/{}", fileset.name());
+            let used = percent(fileset.alloc_inodes(), fileset.max_inodes());
+
+            check.percent(&label, used, thresholds);
+        }
+    }
+
+    check.ok(format!(
+        "all {total} inode spaces below {}% allocated",
+        thresholds.warn
+    ));
+
+    Ok(())
+}
+
+fn pool(
+    runner: &dyn Runner,
+    args: &ArgMatches,
+    check: &mut Check,
+) -> Result<()> {
+    let thresholds = Thresholds::from_args(args)?;
+
+    let names = filesystems(runner, args)?;
+    let filesystems = mmoxi::pool::run_all(runner, &names)?;
+
+    let mut total = 0;
+
+    for fs in &filesystems {
+        for pool in fs.pools() {
+            for (kind, size) in [("data", pool.data()), ("meta", pool.meta())]
+            {
+                let Some(size) = size.filter(|size| size.total_kb() > 0)
+                else {
+                    continue;
+                };
+
+                total += 1;
+
+                let label = format!("{}/{}/{kind}", fs.name(), pool.name());
+                check.percent(&label, size.used_percent(), thresholds);
+            }
+        }
+    }
+
+    check.ok(format!("all {total} pools below {}% used", thresholds.warn));
+
+    Ok(())
+}
+
+fn quota(args: &ArgMatches, check: &mut Check) -> Result<()> {
+    let thresholds = Thresholds::from_args(args)?;
+
+    let data = mmoxi::quota::Data::from_reader(io::stdin().lock())?;
+
+    let mut total = 0;
+    let mut warning = 0;
+    let mut critical = 0;
+
+    for entry in data.entries() {
+        for (kind, metrics) in
+            [("block", entry.block()), ("files", entry.files())]
+        {
+            if metrics.limit() == 0 {
+                continue;
+            }
+
+            total += 1;
+
+            let usage = u64::try_from(metrics.usage()).unwrap_or_default();
+            let used = percent(usage, metrics.limit());
+
+            let state = thresholds.state(used);
+
+            match state {
+                State::Warning => warning += 1,
+                State::Critical => critical += 1,
+                _ => continue,
+            }
+
+            check.problem(
+                state,
+                format!(
+                    "{} {} {} {kind} {used}%",
+                    entry.fs_name(),
+                    entry.quota_type(),
+                    entry.name()
+                ),
+            );
+        }
+    }
+
+    check.perfdata("limits", total, "", None, None);
+    check.perfdata("warning", warning, "", None, None);
+    check.perfdata("critical", critical, "", None, None);
+    check.ok(format!(
+        "all {total} quotas below {}% of their hard limit",
+        thresholds.warn
+    ));
+
+    Ok(())
+}
+
+// ----------------------------------------------------------------------------
+// plugin output
+// ----------------------------------------------------------------------------
+
+/// Plugin state, ordered by severity.
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Debug)]
+pub enum State {
+    Ok,
+    Unknown,
+    Warning,
+    Critical,
+}
+
+impl State {
+    /// Returns the exit code.
+    pub const fn code(self) -> u8 {
+        match self {
+            Self::Ok => 0,
+            Self::Warning => 1,
+            Self::Critical => 2,
+            Self::Unknown => 3,
+        }
+    }
+}
+
+impl Display for State {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            Self::Ok => "OK",
+            Self::Warning => "WARNING",
+            Self::Critical => "CRITICAL",
+            Self::Unknown => "UNKNOWN",
+        };
+
+        write!(f, "{s}")
+    }
+}
+
+/// Warning and critical thresholds in percent.
+#[derive(Copy, Clone, Debug)]
+struct Thresholds {
+    warn: u64,
+    crit: u64,
+}
+
+impl Thresholds {
+    fn from_args(args: &ArgMatches) -> Result<Self> {
+        let warn = *args
+            .get_one::<u64>("warn")
+            .expect("warn has a default value");
+
+        let crit = *args
+            .get_one::<u64>("crit")
+            .expect("crit has a default value");
+
+        ensure!(
+            warn <= crit,
+            "warning threshold {warn}% is above critical threshold {crit}%"
+        );
+
+        Ok(Self { warn, crit })
+    }
+
+    const fn state(self, value: u64) -> State {
+        if value >= self.crit {
+            State::Critical
+        } else if value >= self.warn {
+            State::Warning
+        } else {
+            State::Ok
+        }
+    }
+}
+
+/// Result of a check.
+struct Check {
+    name: String,
+    state: State,
+    problems: Vec<String>,
+    message: String,
+    perfdata: Vec<String>,
+}
+
+impl Check {
+    fn new(name: &str) -> Self {
+        Self {
+            name: name.to_uppercase(),
+            state: State::Ok,
+            problems: vec![],
+            message: String::new(),
+            perfdata: vec![],
+        }
+    }
+
+    /// Adds a problem, raising the state to at least `state`.
+    fn problem(&mut self, state: State, message: String) {
+        self.state = self.state.max(state);
+        self.problems.push(message);
+    }
+
+    /// Sets the message that is shown if there are no problems.
+    fn ok(&mut self, message: impl Into<String>) {
+        self.message = message.into();
+    }
+
+    /// Checks a percentage against `thresholds` and adds it as perfdata.
+    fn percent(&mut self, label: &str, value: u64, thresholds: Thresholds) {
+        let state = thresholds.state(value);
+
+        if state != State::Ok {
+            self.problem(state, format!("{label} {value}%"));
+        }
+
+        self.perfdata(label, value, "%", Some(thresholds), Some(100));
+    }
+
+    fn perfdata(
+        &mut self,
+        label: &str,
+        value: impl Display,
+        unit: &str,
+        thresholds: Option<Thresholds>,
+        max: Option<u64>,
+    ) {
+        let (warn, crit) = thresholds.map_or_else(
+            || (String::new(), String::new()),
+            |thresholds| {
+                (thresholds.warn.to_string(), thresholds.crit.to_string())
+            },
+        );
+
+        let max = max.map(|max| max.to_string()).unwrap_or_default();
+
+        self.perfdata.push(format!(
+            "'{}'={value}{unit};{warn};{crit};0;{max}",
+            label.replace('\'', "''")
+        ));
+    }
+
+    /// Prints the plugin output and returns the state.
+    fn report(self) -> State {
+        println!("{}", self.output());
+        self.state
+    }
+
+    /// Returns the plugin output.
+    fn output(&self) -> String {
+        let message = if self.problems.is_empty() {
+            self.message.clone()
+        } else {
+            self.problems.join(", ")
+        };
+
+        if self.perfdata.is_empty() {
+            format!("{} {} - {message}", self.name, self.state)
+        } else {
+            format!(
+                "{} {} - {message} | {}",
+                self.name,
+                self.state,
+                self.perfdata.join(" ")
+            )
+        }
+    }
+}
+
+// ----------------------------------------------------------------------------
+// helper
+// ----------------------------------------------------------------------------
+
+/// Returns the given file systems, or all if there are none.
+fn filesystems(runner: &dyn Runner, args: &ArgMatches) -> Result<Vec<String>> {
+    if let Some(names) = args.get_many::<String>("filesystems") {
+        Ok(names.cloned().collect())
+    } else {
+        Ok(mmoxi::fs::names(runner)?)
+    }
+}
+
+/// Returns `value` in percent of `total`, rounded down.
+fn percent(value: u64, total: u64) -> u64 {
+    let percent = u128::from(value) * 100 / u128::from(total);
+    u64::try_from(percent).unwrap_or(u64::MAX)
+}
+
+// ----------------------------------------------------------------------------
+// tests
+// ----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn thresholds(args: &[&str]) -> Result<Thresholds> {
+        let args = crate::cli::build()
+            .try_get_matches_from(
+                ["mmoxi", "check", "pool"].iter().chain(args),
+            )
+            .unwrap();
+
+        let (_, args) = args
+            .subcommand()
+            .and_then(|(_, args)| args.subcommand())
+            .unwrap();
+
+        Thresholds::from_args(args)
+    }
+
+    #[test]
+    fn state() {
+        let thresholds =
+            thresholds(&["--warn", "80", "--crit", "90"]).unwrap();
+
+        assert_eq!(thresholds.state(0), State::Ok);
+        assert_eq!(thresholds.state(79), State::Ok);
+        assert_eq!(thresholds.state(80), State::Warning);
+        assert_eq!(thresholds.state(89), State::Warning);
+        assert_eq!(thresholds.state(90), State::Critical);
+        assert_eq!(thresholds.state(100), State::Critical);
+    }
+
+    #[test]
+    fn state_equal_thresholds() {
+        let thresholds = thresholds(&["-w", "90", "-c", "90"]).unwrap();
+
+        assert_eq!(thresholds.state(89), State::Ok);
+        assert_eq!(thresholds.state(90), State::Critical);
+    }
+
+    #[test]
+    fn state_zero_thresholds() {
+        let thresholds = thresholds(&["-w", "0", "-c", "100"]).unwrap();
+
+        assert_eq!(thresholds.state(0), State::Warning);
+        assert_eq!(thresholds.state(99), State::Warning);
+        assert_eq!(thresholds.state(100), State::Critical);
+    }
+
+    #[test]
+    fn warn_above_crit() {
+        assert!(thresholds(&["-w", "95", "-c", "90"]).is_err());
+    }
+
+    #[test]
+    fn usage_error_is_check() {
+        assert!(crate::cli::is_check([
+            "mmoxi", "check", "pool", "-w", "101"
+        ]));
+        assert!(crate::cli::is_check(["mmoxi", "check", "invalid"]));
+        assert!(crate::cli::is_check(["mmoxi", "check"]));
+        assert!(!crate::cli::is_check(["mmoxi", "list", "-w", "80"]));
+    }
+
+    #[test]
+    fn percent_math() {
+        assert_eq!(percent(0, 100), 0);
+        assert_eq!(percent(100, 100), 100);
+        assert_eq!(percent(1, 3), 33);
+        assert_eq!(percent(2, 3), 66);
+        assert_eq!(percent(u64::MAX, u64::MAX), 100);
+        assert_eq!(percent(u64::MAX / 2, u64::MAX), 49);
+    }
+
+    #[test]
+    fn output_ok() {
+        let thresholds = thresholds(&[]).unwrap();
+
+        let mut check = Check::new("pool");
+        check.percent("gpfs1/data/data", 0, thresholds);
+        check.percent("gpfs1/system/meta", 79, thresholds);
+        check.ok("all 2 pools below 80% used");
+
+        assert_eq!(check.state, State::Ok);
+        assert_eq!(
+            check.output(),
+            "POOL OK - all 2 pools below 80% used | \
+             'gpfs1/data/data'=0%;80;90;0;100 \
+             'gpfs1/system/meta'=79%;80;90;0;100"
+        );
+    }
+
+    #[test]
+    fn output_problems() {
+        let thresholds = thresholds(&[]).unwrap();
+
+        let mut check = Check::new("pool");
+        check.percent("gpfs1/data/data", 80, thresholds);
+        check.percent("gpfs1/o'brien/data", 90, thresholds);
+        check.percent("gpfs1/system/meta", 100, thresholds);
+        check.ok("all 3 pools below 80% used");
+
+        assert_eq!(check.state, State::Critical);
+        assert_eq!(
+            check.output(),
+            "POOL CRITICAL - gpfs1/data/data 80%, gpfs1/o'brien/data 90%, \
+             gpfs1/system/meta 100% | \
+             'gpfs1/data/data'=80%;80;90;0;100 \
+             'gpfs1/o''brien/data'=90%;80;90;0;100 \
+             'gpfs1/system/meta'=100%;80;90;0;100"
+        );
+    }
+
+    #[test]
+    fn output_without_thresholds() {
+        let mut check = Check::new("deadlocks");
+        check.perfdata("deadlocks", 0, "", None, None);
+        check.ok("no deadlocks");
+
+        assert_eq!(
+            check.output(),
+            "DEADLOCKS OK - no deadlocks | 'deadlocks'=0;;;0;"
+        );
+    }
+}
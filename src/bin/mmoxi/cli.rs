@@ -1,12 +1,40 @@
+use std::ffi::OsString;
 use std::fs;
 use std::path::PathBuf;
+use std::process;
+use std::time::Duration;
 
-use clap::builder::PossibleValuesParser;
+use clap::builder::{PossibleValuesParser, TypedValueParser};
 use clap::{Arg, ArgAction, ArgMatches, Command};
 use clap::{crate_name, crate_version};
 
+use mmoxi::prom::Format;
+
+use crate::check::State;
+use crate::collector::Collector;
+
 pub fn args() -> ArgMatches {
-    build().get_matches()
+    build().try_get_matches().unwrap_or_else(|error| {
+        // checks report usage errors as UNKNOWN like all other errors
+        if error.use_stderr() && is_check(std::env::args_os()) {
+            let _ = error.print();
+            process::exit(State::Unknown.code().into());
+        }
+
+        error.exit()
+    })
+}
+
+/// Returns whether the command line invokes a check, even if it is invalid.
+pub fn is_check<I, T>(args: I) -> bool
+where
+    I: IntoIterator<Item = T>,
+    T: Into<OsString> + Clone,
+{
+    build()
+        .ignore_errors(true)
+        .try_get_matches_from(args)
+        .is_ok_and(|args| args.subcommand_name() == Some("check"))
 }
 
 pub fn build() -> Command {
@@ -24,13 +52,44 @@ pub fn build() -> Command {
         .subcommand_required(true)
         .arg_required_else_help(true)
         .infer_subcommands(true)
+        .after_help(
+            "Exits with 75 (EX_TEMPFAIL) if a command failed with a \
+             transient error, e.g. because the daemon is down.",
+        )
+        .args(runner_args())
+        .subcommand(build_analyze())
         .subcommand(build_cache())
+        .subcommand(build_check())
+        .subcommand(build_exporter())
         .subcommand(build_list())
         .subcommand(pool_percent)
         .subcommand(build_prometheus())
         .subcommand(build_show())
 }
 
+fn build_analyze() -> Command {
+    let replication = Command::new("replication")
+        .about("analyze failure group redundancy")
+        .disable_help_flag(true)
+        .disable_version_flag(true)
+        .arg(arg_filesystem())
+        .after_long_help(
+"Reports per pool whether losing any single failure group would make data or \
+ metadata unavailable, based on the default number of replicas of the file \
+ system and the availability of the disks in each failure group. A failure \
+ group with any disk that is not up counts as lost already.",
+        );
+
+    Command::new("analyze")
+        .about("analyze file systems")
+        .disable_help_flag(true)
+        .disable_version_flag(true)
+        .args(arg_json())
+        .subcommand_required(true)
+        .arg_required_else_help(true)
+        .subcommand(replication)
+}
+
 fn build_cache() -> Command {
     let cache_nmon = Command::new("nmon")
         .about("cache local NSD block devices for use with nmon")
@@ -69,100 +128,250 @@ fn build_cache() -> Command {
         .subcommand(cache_nsds)
 }
 
-fn build_list() -> Command {
-    let filesystems = Command::new("filesystems")
-        .about("list file system names")
-        .alias("fs")
-        .disable_help_flag(true)
-        .disable_version_flag(true);
-
-    Command::new("list")
-        .about("list commands")
-        .alias("ls")
+fn build_check() -> Command {
+    let check_deadlocks = Command::new("deadlocks")
+        .about("check for deadlocks")
         .disable_help_flag(true)
         .disable_version_flag(true)
-        .subcommand_required(true)
-        .arg_required_else_help(true)
-        .subcommand(filesystems)
-}
+        .after_long_help("Critical if there are any deadlocks.");
 
-pub fn build_prometheus() -> Command {
-    let prom_deadlocks = Command::new("deadlocks")
-        .about("Gather deadlock metrics.")
+    let check_disks = Command::new("disks")
+        .about("check disk availability")
         .disable_help_flag(true)
         .disable_version_flag(true)
-        .arg(arg_output());
+        .arg(arg_filesystems())
+        .after_long_help(
+"Warning if any disk is recovering, critical if any disk is down or \
+ unrecovered.",
+        );
 
-    let prom_df = Command::new("df")
-        .about("Gather metrics from mmdf.")
+    let check_fileset_inodes = Command::new("fileset-inodes")
+        .about("check allocated fileset inodes")
         .disable_help_flag(true)
         .disable_version_flag(true)
-        .arg(arg_output())
-        .after_long_help("Run on cluster manager only.");
+        .arg(arg_filesystems())
+        .args(threshold_args())
+        .after_long_help(
+"Checks the allocated inodes of all inode spaces in percent of their maximum \
+ inodes.",
+        );
 
-    let prom_disk = Command::new("disk")
-        .about("Gather metrics from mmlsdisk.")
+    let check_pool = Command::new("pool")
+        .about("check pool usage")
         .disable_help_flag(true)
         .disable_version_flag(true)
-        .arg(arg_output())
-        .after_long_help("Run on cluster manager only.");
+        .arg(arg_filesystems())
+        .args(threshold_args())
+        .after_long_help(
+            "Checks the used data and metadata of all pools in percent.",
+        );
 
-    let prom_fileset = Command::new("fileset")
-        .about("Gather fileset metrics.")
+    let check_quota = Command::new("quota")
+        .about("check quota usage")
         .disable_help_flag(true)
         .disable_version_flag(true)
-        .arg(arg_output())
-        .after_long_help("Run on cluster manager only.");
+        .args(threshold_args())
+        .after_long_help(
+"Checks block and file usage in percent of the hard limit. Entries without a \
+ hard limit are ignored. This command expects piped output from one or more \
+ `mmrepquota` commands, e.g. `mmrepquota -Y -j gpfs1 | mmoxi check quota`.",
+        );
 
-    let prom_manager = Command::new("manager")
-        .about("Gather manager state metrics.")
+    Command::new("check")
+        .about("Nagios/Icinga checks")
         .disable_help_flag(true)
         .disable_version_flag(true)
-        .arg(arg_output());
+        .subcommand_required(true)
+        .arg_required_else_help(true)
+        .subcommand(check_deadlocks)
+        .subcommand(check_disks)
+        .subcommand(check_fileset_inodes)
+        .subcommand(check_pool)
+        .subcommand(check_quota)
+        .after_long_help(
+"Checks print a single line of plugin output with performance data and exit \
+ with 0 (OK), 1 (WARNING), 2 (CRITICAL) or 3 (UNKNOWN). Errors, e.g. failing \
+ commands or invalid arguments, are reported as UNKNOWN.",
+        )
+}
 
-    let prom_pool_user_distribution = Command::new("user-distribution")
-        .about("Gather usage per user for a pool.")
-        .alias("udistri")
-        .arg(arg_output())
-        .args(policy_args())
+fn build_exporter() -> Command {
+    Command::new("exporter")
+        .about("serve prometheus metrics via HTTP")
         .disable_help_flag(true)
         .disable_version_flag(true)
+        .arg(
+            Arg::new("listen")
+                .short('l')
+                .long("listen")
+                .default_value(":9303")
+                .help("listen address")
+                .long_help(
+"Listen on this address, either `host:port` or just `:port` for all \
+ interfaces.",
+                )
+                .value_name("address"),
+        )
+        .arg(
+            Arg::new("collector")
+                .short('c')
+                .long("collector")
+                .required(true)
+                .action(ArgAction::Append)
+                .value_parser(arg_collector())
+                .help("enable collector")
+                .long_help(
+"Enable this collector. Can be given multiple times. The `df`, `disk`, \
+ `fileset`, `filesystem`, `mount`, `pool-usage`, `replication` and `state` \
+ collectors should only be enabled on the cluster manager, `iohist` and \
+ `pool-block` on every file server, `diag-memory`, `diag-network`, \
+ `diag-stats`, `health`, `mmpmon` and `waiters` on every node.",
+                )
+                .value_name("collector"),
+        )
+        .arg(
+            Arg::new("interval")
+                .short('i')
+                .long("interval")
+                .action(ArgAction::Append)
+                .value_parser(collector_interval)
+                .help("collector refresh interval")
+                .long_help(
+"Refresh the collector every this many seconds, e.g. `df=600`. Can be given \
+ multiple times. Defaults to 15 seconds for `diag-memory`, `diag-network`, \
+ `mmpmon`, `pool-block` and `waiters`, 60 seconds for `deadlocks`, `diag-stats`, \
+ `disk`, `health`, `iohist`, `manager`, `mount`, `replication` and `state`, \
+ and 300 seconds for `df`, `fileset`, `filesystem` and `pool-usage`.",
+                )
+                .value_name("collector=seconds"),
+        )
+        .arg(arg_device_cache())
         .after_long_help(
-"This is useful to figure out which users are heavily using expensive storage \
- pools like NVME storage. Run on cluster manager only."
-        );
+"Serves the metrics of all enabled collectors at `/metrics`. Collectors are \
+ refreshed in the background, scrapes only return the output of the last \
+ successful refresh. Metrics of a collector whose last refresh failed are \
+ omitted, but its `gpfs_mmoxi_collector_*` metrics are always served. \
+ Clients that accept `application/openmetrics-text` get the OpenMetrics text \
+ format.",
+        )
+}
 
-    let prom_pool_block = Command::new("block")
-        .about("Gather block device metrics grouped by pool.")
+fn build_list() -> Command {
+    let filesystems = Command::new("filesystems")
+        .about("list file system names")
+        .alias("fs")
         .disable_help_flag(true)
-        .disable_version_flag(true)
-        .arg(arg_device_cache())
-        .arg(arg_force())
-        .arg(arg_output())
-        .after_long_help("Run locally on every file server.");
+        .disable_version_flag(true);
 
-    let prom_pool_usage = Command::new("usage")
-        .about("Gather pool usage metrics.")
+    let nodes = Command::new("nodes")
+        .about("list cluster nodes")
         .disable_help_flag(true)
         .disable_version_flag(true)
-        .arg(arg_output())
-        .after_long_help("Run on cluster manager only.");
+        .arg(
+            Arg::new("role")
+                .long("role")
+                .action(ArgAction::Set)
+                .help("only list nodes with this role")
+                .value_name("role")
+                .value_parser(PossibleValuesParser::new([
+                    "quorum", "manager", "perfmon", "gateway",
+                ])),
+        );
 
-    let prom_pool = Command::new("pool")
-        .about("Pool metrics.")
+    let mounts = Command::new("mounts")
+        .about("list nodes that have file systems mounted")
+        .disable_help_flag(true)
+        .disable_version_flag(true);
+
+    Command::new("list")
+        .about("list commands")
+        .alias("ls")
         .disable_help_flag(true)
         .disable_version_flag(true)
+        .args(arg_json())
         .subcommand_required(true)
         .arg_required_else_help(true)
-        .subcommand(prom_pool_block)
-        .subcommand(prom_pool_usage)
-        .subcommand(prom_pool_user_distribution);
+        .subcommand(filesystems)
+        .subcommand(mounts)
+        .subcommand(nodes)
+}
 
-    let prom_quota = Command::new("quota")
-        .about("Gather quota metrics.")
-        .disable_help_flag(true)
-        .disable_version_flag(true)
-        .arg(arg_output())
+pub fn build_prometheus() -> Command {
+    let prom_deadlocks =
+        prom_subcommand("deadlocks", "Gather deadlock metrics.");
+
+    let prom_df = prom_subcommand("df", "Gather metrics from mmdf.")
+        .after_long_help("Run on cluster manager only.");
+
+    let prom_disk = prom_subcommand("disk", "Gather metrics from mmlsdisk.")
+        .after_long_help("Run on cluster manager only.");
+
+    let prom_fileset = prom_subcommand("fileset", "Gather fileset metrics.")
+        .after_long_help("Run on cluster manager only.");
+
+    let prom_filesystem =
+        prom_subcommand("filesystem", "Gather file system attribute metrics.")
+            .alias("fs")
+            .after_long_help("Run on cluster manager only.");
+
+    let prom_diag_memory = prom_subcommand(
+        "diag-memory",
+        "Gather pagepool and daemon memory metrics from mmdiag.",
+    )
+    .after_long_help("Run locally on every node.");
+
+    let prom_diag_network = prom_subcommand(
+        "diag-network",
+        "Gather pending message and connection metrics from mmdiag.",
+    )
+    .after_long_help("Run locally on every node.");
+
+    let prom_diag_stats =
+        prom_subcommand("diag-stats", "Gather daemon statistics from mmdiag.")
+            .after_long_help("Run locally on every node.");
+
+    let prom_health =
+        prom_subcommand("health", "Gather metrics from mmhealth.")
+            .after_long_help("Run locally on every node.");
+
+    let prom_iohist = prom_subcommand(
+        "iohist",
+        "Gather NSD I/O latency histograms from mmdiag.",
+    )
+    .after_long_help(
+"Run locally on every file server. The histograms only cover the recent I/Os \
+ of the I/O history, so they are exported as gauges. NSD pools are cached for \
+ 5 minutes or until an unknown NSD shows up in the I/O history.",
+    );
+
+    let prom_manager =
+        prom_subcommand("manager", "Gather manager state metrics.");
+
+    let prom_mmpmon = prom_subcommand(
+        "mmpmon",
+        "Gather file system and node I/O counters from mmpmon.",
+    )
+    .after_long_help("Run locally on every node.");
+
+    let prom_mount =
+        prom_subcommand("mount", "Gather metrics from mmlsmount.")
+            .after_long_help("Run on cluster manager only.");
+
+    let prom_replication = prom_subcommand(
+        "replication",
+        "Gather failure group redundancy metrics.",
+    )
+    .after_long_help("Run on cluster manager only.");
+
+    let prom_state =
+        prom_subcommand("state", "Gather node state and quorum metrics.")
+            .after_long_help("Run on cluster manager only.");
+
+    let prom_waiters =
+        prom_subcommand("waiters", "Gather waiter metrics from mmdiag.")
+            .after_long_help("Run locally on every node.");
+
+    let prom_quota = prom_subcommand("quota", "Gather quota metrics.")
         .after_long_help(
 "Run every hour on cluster manager only. This command expects piped output \
  from one or more `mmrepquota` commands, e.g. `{ mmrepquota -Y -j gpfs1; \
@@ -173,18 +382,90 @@ pub fn build_prometheus() -> Command {
         .alias("prom")
         .disable_help_flag(true)
         .disable_version_flag(true)
+        .arg(arg_format())
         .subcommand_required(true)
         .arg_required_else_help(true)
         .subcommand(prom_deadlocks)
         .subcommand(prom_df)
+        .subcommand(prom_diag_memory)
+        .subcommand(prom_diag_network)
+        .subcommand(prom_diag_stats)
         .subcommand(prom_disk)
         .subcommand(prom_fileset)
+        .subcommand(prom_filesystem)
+        .subcommand(prom_health)
+        .subcommand(prom_iohist)
         .subcommand(prom_manager)
-        .subcommand(prom_pool)
+        .subcommand(prom_mmpmon)
+        .subcommand(prom_mount)
+        .subcommand(build_prometheus_pool())
         .subcommand(prom_quota)
+        .subcommand(prom_replication)
+        .subcommand(prom_state)
+        .subcommand(prom_waiters)
+        .after_long_help(
+"Output files are replaced atomically, so the node exporter textfile \
+ collector never reads partial output.
+
+All collectors add `gpfs_mmoxi_collector_success`, \
+ `gpfs_mmoxi_collector_duration_seconds` and \
+ `gpfs_mmoxi_collector_last_success_timestamp_seconds` metrics. With an output \
+ file, these are written to a separate file with `.stats` before the \
+ extension, e.g. `df.stats.prom` for `df.prom`. If a command fails, the output \
+ file is left untouched and only the stats file is updated. If only some file \
+ systems fail, the metrics of the others are written anyway.",
+        )
+}
+
+fn prom_subcommand(name: &'static str, about: &'static str) -> Command {
+    Command::new(name)
+        .about(about)
+        .disable_help_flag(true)
+        .disable_version_flag(true)
+        .arg(arg_output())
+}
+
+fn build_prometheus_pool() -> Command {
+    let prom_pool_user_distribution = prom_subcommand(
+        "user-distribution",
+        "Gather usage per user for a pool.",
+    )
+    .alias("udistri")
+    .args(policy_args())
+    .after_long_help(
+"This is useful to figure out which users are heavily using expensive storage \
+ pools like NVME storage. Run on cluster manager only.",
+    );
+
+    let prom_pool_block = prom_subcommand(
+        "block",
+        "Gather block device metrics grouped by pool.",
+    )
+    .arg(arg_device_cache())
+    .arg(arg_force())
+    .after_long_help("Run locally on every file server.");
+
+    let prom_pool_usage =
+        prom_subcommand("usage", "Gather pool usage metrics.")
+            .after_long_help("Run on cluster manager only.");
+
+    Command::new("pool")
+        .about("Pool metrics.")
+        .disable_help_flag(true)
+        .disable_version_flag(true)
+        .subcommand_required(true)
+        .arg_required_else_help(true)
+        .subcommand(prom_pool_block)
+        .subcommand(prom_pool_usage)
+        .subcommand(prom_pool_user_distribution)
 }
 
 fn build_show() -> Command {
+    let cluster = Command::new("cluster")
+        .about("show cluster configuration")
+        .disable_help_flag(true)
+        .disable_version_flag(true);
+
     let cluster_manager = Command::new("cluster")
         .about("show cluster manager")
         .disable_help_flag(true)
@@ -207,6 +488,24 @@ fn build_show() -> Command {
         .subcommand(cluster_manager)
         .subcommand(filesystem_manager);
 
+    let filesystem = Command::new("filesystem")
+        .about("show file system attributes")
+        .arg(arg_filesystem())
+        .alias("fs")
+        .disable_help_flag(true)
+        .disable_version_flag(true);
+
+    let health = Command::new("health")
+        .about("show node or cluster health")
+        .disable_help_flag(true)
+        .disable_version_flag(true)
+        .arg(
+            Arg::new("cluster")
+                .long("cluster")
+                .action(ArgAction::SetTrue)
+                .help("show health of all nodes per component"),
+        );
+
     let node = Command::new("node")
         .about("show node name")
         .disable_help_flag(true)
@@ -216,8 +515,12 @@ fn build_show() -> Command {
         .about("show commands")
         .disable_help_flag(true)
         .disable_version_flag(true)
+        .args(arg_json())
         .subcommand_required(true)
         .arg_required_else_help(true)
+        .subcommand(cluster)
+        .subcommand(filesystem)
+        .subcommand(health)
         .subcommand(manager)
         .subcommand(node)
 }
@@ -226,6 +529,11 @@ fn build_show() -> Command {
 // arguments
 // ----------------------------------------------------------------------------
 
+fn arg_collector() -> impl TypedValueParser<Value = Collector> {
+    PossibleValuesParser::new(Collector::ALL.map(Collector::name))
+        .map(|name| Collector::from_name(&name).expect("possible value"))
+}
+
 fn arg_device_cache() -> Arg {
     Arg::new("device-cache")
         .long("device-cache")
@@ -244,6 +552,14 @@ fn arg_filesystem() -> Arg {
         .value_name("filesystem")
 }
 
+fn arg_filesystems() -> Arg {
+    Arg::new("filesystems")
+        .action(ArgAction::Append)
+        .help("file systems")
+        .long_help("File system names. Defaults to all file systems.")
+        .value_name("filesystem")
+}
+
 fn arg_force() -> Arg {
     Arg::new("force")
         .short('f')
@@ -253,6 +569,18 @@ fn arg_force() -> Arg {
         .long_help("Force recreating the cache.")
 }
 
+/// Returns the `--json` argument, which requires the `serde` feature.
+fn arg_json() -> Option<Arg> {
+    cfg!(feature = "serde").then(|| {
+        Arg::new("json")
+            .long("json")
+            .global(true)
+            .action(ArgAction::SetTrue)
+            .help("print JSON")
+            .long_help("Print JSON instead of plain text.")
+    })
+}
+
 fn arg_output() -> Arg {
     Arg::new("output")
         .short('o')
@@ -262,6 +590,122 @@ fn arg_output() -> Arg {
         .long_help("Output file.")
 }
 
+fn arg_format() -> Arg {
+    Arg::new("format")
+        .long("format")
+        .global(true)
+        .value_parser(
+            PossibleValuesParser::new(["text", "openmetrics", "influx"]).map(
+                |format| format.parse::<Format>().expect("possible value"),
+            ),
+        )
+        .default_value("text")
+        .help("output format")
+        .long_help(
+            "Output format: `text` for the classic Prometheus text format, \
+ `openmetrics` for the OpenMetrics text format or `influx` for InfluxDB line \
+ protocol with the same tags as the Prometheus labels.",
+        )
+        .value_name("format")
+}
+
+fn threshold_args() -> Vec<Arg> {
+    vec![
+        Arg::new("warn")
+            .short('w')
+            .long("warn")
+            .value_parser(clap::value_parser!(u64).range(0..=100))
+            .default_value("80")
+            .help("warning threshold in percent")
+            .long_help(
+                "Warning threshold in percent, must not be above the critical \
+                 threshold.",
+            )
+            .value_name("percent"),
+        Arg::new("crit")
+            .short('c')
+            .long("crit")
+            .value_parser(clap::value_parser!(u64).range(0..=100))
+            .default_value("90")
+            .help("critical threshold in percent")
+            .long_help("Critical threshold in percent.")
+            .value_name("percent"),
+    ]
+}
+
+fn runner_args() -> Vec<Arg> {
+    vec![
+        Arg::new("bin-dir")
+            .long("bin-dir")
+            .global(true)
+            .value_parser(clap::value_parser!(PathBuf))
+            .help("run mm* commands from this directory")
+            .long_help(
+"Run `mm*` commands from this directory, e.g. `/usr/lpp/mmfs/bin`, instead \
+ of looking them up in `PATH`.",
+            )
+            .value_name("dir"),
+
+        Arg::new("sudo")
+            .long("sudo")
+            .global(true)
+            .action(ArgAction::SetTrue)
+            .help("run mm* commands with sudo")
+            .long_help(
+"Run `mm*` commands with non-interactive `sudo -n`.",
+            ),
+
+        Arg::new("record")
+            .long("record")
+            .global(true)
+            .value_parser(clap::value_parser!(PathBuf))
+            .conflicts_with("replay")
+            .help("record mm* command output to this directory")
+            .long_help(
+"Record the output of all `mm*` commands to this directory. The recorded \
+ output can be used later with `--replay`.",
+            )
+            .value_name("dir"),
+
+        Arg::new("replay")
+            .long("replay")
+            .global(true)
+            .value_parser(clap::value_parser!(PathBuf))
+            .help("replay mm* command output from this directory")
+            .long_help(
+"Do not run any `mm*` commands. Instead, replay their output from this \
+ directory, as recorded with `--record`.",
+            )
+            .value_name("dir"),
+
+        Arg::new("timeout")
+            .long("timeout")
+            .global(true)
+            .value_parser(seconds)
+            .help("kill mm* commands after this many seconds")
+            .long_help(
+"Kill `mm*` commands, including all their child processes, if they run \
+ longer than this many seconds. This prevents hanging when e.g. a node is \
+ expelled or a file system is in recovery. By default, there is no timeout, \
+ except for the exporter, which kills commands after 600 seconds.",
+            )
+            .value_name("seconds"),
+
+        Arg::new("program-timeout")
+            .long("program-timeout")
+            .global(true)
+            .action(ArgAction::Append)
+            .value_parser(program_timeout)
+            .help("kill a specific mm* command after this many seconds")
+            .long_help(
+"Kill a specific `mm*` command if it runs longer than this many seconds, \
+ overriding `--timeout` for this command, e.g. `mmapplypolicy=3600`. Can be \
+ given multiple times.",
+            )
+            .value_name("program=seconds"),
+    ]
+}
+
 fn arg_pool() -> Arg {
     Arg::new("pool")
         .required(true)
@@ -347,6 +791,41 @@ fn policy_args() -> Vec<Arg> {
 // value parser
 // ----------------------------------------------------------------------------
 
+/// Parses a positive number of seconds.
+///
+/// Zero is rejected, because a timeout of zero would kill every command
+/// right away and an interval of zero would refresh in a tight loop.
+fn seconds(s: &str) -> Result<Duration, String> {
+    let seconds = s
+        .parse::<u64>()
+        .map_err(|error| format!("not a number of seconds: {s}: {error}"))?;
+
+    if seconds == 0 {
+        return Err("must be at least 1 second".into());
+    }
+
+    Ok(Duration::from_secs(seconds))
+}
+
+fn collector_interval(s: &str) -> Result<(Collector, Duration), String> {
+    let Some((collector, interval)) = s.split_once('=') else {
+        return Err(format!("expected collector=seconds: {s}"));
+    };
+
+    let collector = Collector::from_name(collector)
+        .ok_or_else(|| format!("unknown collector: {collector}"))?;
+
+    Ok((collector, seconds(interval)?))
+}
+
+fn program_timeout(s: &str) -> Result<(String, Duration), String> {
+    let Some((program, timeout)) = s.split_once('=') else {
+        return Err(format!("expected program=seconds: {s}"));
+    };
+
+    Ok((program.into(), seconds(timeout)?))
+}
+
 fn is_dir(s: &str) -> Result<PathBuf, String> {
     let path = PathBuf::from(s);
 
@@ -360,3 +839,49 @@ fn is_dir(s: &str) -> Result<PathBuf, String> {
         Ok(path)
     }
 }
+
+// ----------------------------------------------------------------------------
+// tests
+// ----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timeout() {
+        assert_eq!(seconds("600"), Ok(Duration::from_secs(600)));
+        assert!(seconds("0").is_err());
+        assert!(seconds("-1").is_err());
+
+        assert_eq!(
+            program_timeout("mmlsfs=30"),
+            Ok(("mmlsfs".into(), Duration::from_secs(30)))
+        );
+        assert!(program_timeout("mmlsfs=0").is_err());
+
+        let args = |timeout| {
+            build().try_get_matches_from([
+                "mmoxi",
+                "--timeout",
+                timeout,
+                "list",
+                "filesystems",
+            ])
+        };
+
+        assert!(args("1").is_ok());
+        assert!(args("0").is_err());
+    }
+
+    #[test]
+    fn interval() {
+        assert_eq!(
+            collector_interval("df=600"),
+            Ok((Collector::Df, Duration::from_secs(600)))
+        );
+        assert!(collector_interval("df=0").is_err());
+        assert!(collector_interval("df").is_err());
+        assert!(collector_interval("invalid=60").is_err());
+    }
+}
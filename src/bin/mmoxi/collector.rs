@@ -0,0 +1,538 @@
+//! Prometheus metric collectors shared by `prometheus` and `exporter`.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::path::Path;
+use std::sync::{Mutex, OnceLock, PoisonError};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use anyhow::Result;
+
+use mmoxi::mount::ClusterMounts;
+use mmoxi::nsd::FsPoolId;
+use mmoxi::prom::{Family, ToMetrics};
+use mmoxi::run::Runner;
+
+/// Collectors available in the exporter.
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+pub enum Collector {
+    Deadlocks,
+    Df,
+    DiagMemory,
+    DiagNetwork,
+    DiagStats,
+    Disk,
+    Fileset,
+    Filesystem,
+    Health,
+    Iohist,
+    Manager,
+    Mmpmon,
+    Mount,
+    PoolBlock,
+    PoolUsage,
+    Replication,
+    State,
+    Waiters,
+}
+
+impl Collector {
+    pub const ALL: [Self; 18] = [
+        Self::Deadlocks,
+        Self::Df,
+        Self::DiagMemory,
+        Self::DiagNetwork,
+        Self::DiagStats,
+        Self::Disk,
+        Self::Fileset,
+        Self::Filesystem,
+        Self::Health,
+        Self::Iohist,
+        Self::Manager,
+        Self::Mmpmon,
+        Self::Mount,
+        Self::PoolBlock,
+        Self::PoolUsage,
+        Self::Replication,
+        Self::State,
+        Self::Waiters,
+    ];
+
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Deadlocks => "deadlocks",
+            Self::Df => "df",
+            Self::DiagMemory => "diag-memory",
+            Self::DiagNetwork => "diag-network",
+            Self::DiagStats => "diag-stats",
+            Self::Disk => "disk",
+            Self::Fileset => "fileset",
+            Self::Filesystem => "filesystem",
+            Self::Health => "health",
+            Self::Iohist => "iohist",
+            Self::Manager => "manager",
+            Self::Mmpmon => "mmpmon",
+            Self::Mount => "mount",
+            Self::PoolBlock => "pool-block",
+            Self::PoolUsage => "pool-usage",
+            Self::Replication => "replication",
+            Self::State => "state",
+            Self::Waiters => "waiters",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|collector| collector.name() == name)
+    }
+
+    /// Returns the default refresh interval, longer for expensive commands.
+    pub const fn default_interval(self) -> Duration {
+        match self {
+            Self::DiagMemory
+            | Self::DiagNetwork
+            | Self::Mmpmon
+            | Self::PoolBlock
+            | Self::Waiters => Duration::from_secs(15),
+            Self::Deadlocks
+            | Self::DiagStats
+            | Self::Disk
+            | Self::Health
+            | Self::Iohist
+            | Self::Manager
+            | Self::Mount
+            | Self::Replication
+            | Self::State => Duration::from_secs(60),
+            Self::Df | Self::Fileset | Self::Filesystem | Self::PoolUsage => {
+                Duration::from_secs(300)
+            }
+        }
+    }
+
+    pub fn collect(
+        self,
+        runner: &dyn Runner,
+        device_cache: &Path,
+        metrics: &mut Vec<Family>,
+    ) -> Result<()> {
+        match self {
+            Self::Deadlocks => deadlocks(runner, metrics),
+            Self::Df => df(runner, metrics),
+            Self::DiagMemory => diag_memory(runner, metrics),
+            Self::DiagNetwork => diag_network(runner, metrics),
+            Self::DiagStats => diag_stats(runner, metrics),
+            Self::Disk => disk(runner, metrics),
+            Self::Fileset => fileset(runner, metrics),
+            Self::Filesystem => filesystem(runner, metrics),
+            Self::Health => health(runner, metrics),
+            Self::Iohist => iohist(runner, metrics),
+            Self::Manager => manager(runner, metrics),
+            Self::Mmpmon => mmpmon(runner, metrics),
+            Self::Mount => mount(runner, metrics),
+            Self::PoolBlock => {
+                pool_block(runner, device_cache, false, metrics)
+            }
+            Self::PoolUsage => pool_usage(runner, metrics),
+            Self::Replication => replication(runner, metrics),
+            Self::State => state(runner, metrics),
+            Self::Waiters => waiters(runner, metrics),
+        }
+    }
+}
+
+impl fmt::Display for Collector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+// ----------------------------------------------------------------------------
+// collectors
+// ----------------------------------------------------------------------------
+
+pub fn deadlocks(
+    runner: &dyn Runner,
+    metrics: &mut Vec<Family>,
+) -> Result<()> {
+    let data = mmoxi::diag::deadlock(runner)?;
+    metrics.extend(data.to_metrics());
+    Ok(())
+}
+
+pub fn df(runner: &dyn Runner, metrics: &mut Vec<Family>) -> Result<()> {
+    let (data, errors) = mmoxi::df::run_partial(runner)?;
+    metrics.extend(data.to_metrics());
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(PartialFailure(errors).into())
+    }
+}
+
+pub fn diag_memory(
+    runner: &dyn Runner,
+    metrics: &mut Vec<Family>,
+) -> Result<()> {
+    let node = local_node_name(runner)?;
+    let data = mmoxi::diag::memory(runner, node)?;
+    metrics.extend(data.to_metrics());
+    Ok(())
+}
+
+pub fn diag_network(
+    runner: &dyn Runner,
+    metrics: &mut Vec<Family>,
+) -> Result<()> {
+    let node = local_node_name(runner)?;
+    let data = mmoxi::diag::network(runner, node)?;
+    metrics.extend(data.to_metrics());
+    Ok(())
+}
+
+pub fn diag_stats(
+    runner: &dyn Runner,
+    metrics: &mut Vec<Family>,
+) -> Result<()> {
+    let node = local_node_name(runner)?;
+    let data = mmoxi::diag::stats(runner, node)?;
+    metrics.extend(data.to_metrics());
+    Ok(())
+}
+
+pub fn disk(runner: &dyn Runner, metrics: &mut Vec<Family>) -> Result<()> {
+    let mut all_disks = HashMap::new();
+
+    for fs in mmoxi::fs::names(runner)? {
+        let disks = mmoxi::disk::disks(runner, &fs)?;
+        all_disks.insert(fs, disks);
+    }
+
+    metrics.extend(all_disks.to_metrics());
+
+    Ok(())
+}
+
+pub fn fileset(runner: &dyn Runner, metrics: &mut Vec<Family>) -> Result<()> {
+    let mut filesets = vec![];
+
+    for fs in mmoxi::fs::names(runner)? {
+        filesets.extend(mmoxi::fileset::filesets(runner, &fs)?);
+    }
+
+    metrics.extend(filesets.to_metrics());
+
+    Ok(())
+}
+
+pub fn filesystem(
+    runner: &dyn Runner,
+    metrics: &mut Vec<Family>,
+) -> Result<()> {
+    let data = mmoxi::fs::filesystems(runner)?;
+    metrics.extend(data.to_metrics());
+    Ok(())
+}
+
+pub fn health(runner: &dyn Runner, metrics: &mut Vec<Family>) -> Result<()> {
+    let data = mmoxi::health::node(runner)?;
+    metrics.extend(data.to_metrics());
+    Ok(())
+}
+
+pub fn iohist(runner: &dyn Runner, metrics: &mut Vec<Family>) -> Result<()> {
+    let history = mmoxi::diag::iohist(runner)?;
+    let nsds = history.ios().iter().map(mmoxi::diag::Io::nsd);
+
+    match pool_ids(runner, nsds) {
+        Ok(pools) => {
+            metrics.extend(history.latency(&pools).to_metrics());
+            Ok(())
+        }
+        Err(error) => {
+            // keep the NSD metrics, which don't need the pools
+            metrics.extend(history.latency(&HashMap::new()).to_metrics());
+            Err(PartialFailure(vec![error]).into())
+        }
+    }
+}
+
+pub fn manager(runner: &dyn Runner, metrics: &mut Vec<Family>) -> Result<()> {
+    let data = mmoxi::mgr::local_roles(runner)?;
+    metrics.extend(data.to_metrics());
+    Ok(())
+}
+
+pub fn mmpmon(runner: &dyn Runner, metrics: &mut Vec<Family>) -> Result<()> {
+    let data = mmoxi::mmpmon::io(runner, &[])?;
+    metrics.extend(data.to_metrics());
+    Ok(())
+}
+
+pub fn mount(runner: &dyn Runner, metrics: &mut Vec<Family>) -> Result<()> {
+    let mounts = mmoxi::mount::mounts(runner)?;
+    let cluster = mmoxi::cluster::get(runner)?;
+    let states = mmoxi::state::all(runner)?;
+
+    let nodes = states
+        .states()
+        .iter()
+        .map(|state| state.name().to_owned())
+        .collect();
+
+    let data = ClusterMounts::new(cluster.name().to_owned(), nodes, mounts);
+    metrics.extend(data.to_metrics());
+    Ok(())
+}
+
+pub fn pool_block(
+    runner: &dyn Runner,
+    device_cache: &Path,
+    force: bool,
+    metrics: &mut Vec<Family>,
+) -> Result<()> {
+    let data =
+        mmoxi::prom::pool_block_device_metrics(runner, device_cache, force)?;
+    metrics.extend(data.to_metrics());
+    Ok(())
+}
+
+pub fn pool_usage(
+    runner: &dyn Runner,
+    metrics: &mut Vec<Family>,
+) -> Result<()> {
+    let names = mmoxi::fs::names(runner)?;
+
+    let filesystems = mmoxi::pool::run_all(runner, &names)?;
+
+    metrics.extend(filesystems.to_metrics());
+
+    Ok(())
+}
+
+pub fn replication(
+    runner: &dyn Runner,
+    metrics: &mut Vec<Family>,
+) -> Result<()> {
+    let mut pools = vec![];
+
+    for fs in mmoxi::fs::names(runner)? {
+        pools.extend(mmoxi::replication::run(runner, &fs)?);
+    }
+
+    metrics.extend(pools.to_metrics());
+
+    Ok(())
+}
+
+pub fn state(runner: &dyn Runner, metrics: &mut Vec<Family>) -> Result<()> {
+    let data = mmoxi::state::all(runner)?;
+    metrics.extend(data.to_metrics());
+    Ok(())
+}
+
+pub fn waiters(runner: &dyn Runner, metrics: &mut Vec<Family>) -> Result<()> {
+    let node = local_node_name(runner)?;
+    let data = mmoxi::diag::waiters(runner, node)?;
+    metrics.extend(data.to_metrics());
+    Ok(())
+}
+
+/// Returns the local node name.
+///
+/// The name is resolved only once per process, so that e.g. the exporter
+/// doesn't run an additional `mmgetstate` with every collection.
+fn local_node_name(runner: &dyn Runner) -> Result<&'static str> {
+    static NODE: OnceLock<String> = OnceLock::new();
+
+    if let Some(node) = NODE.get() {
+        return Ok(node);
+    }
+
+    let node = mmoxi::state::local_node_name(runner)?;
+
+    Ok(NODE.get_or_init(|| node))
+}
+
+/// How long the NSD pools are cached.
+const POOLS_TTL: Duration = Duration::from_secs(300);
+
+/// NSD pools with the time they were looked up.
+struct Pools {
+    refreshed: Instant,
+    ids: HashMap<String, FsPoolId>,
+
+    /// NSDs that were asked for but don't belong to a file system.
+    free: HashSet<String>,
+}
+
+/// Returns the file system and pool of every NSD.
+///
+/// The mapping is cached, so that e.g. the exporter doesn't run `mmlsfs` and
+/// an `mmlsdisk` per file system with every collection. It is refreshed once
+/// it is older than [`POOLS_TTL`] or if one of `nsds` is unknown, e.g. after
+/// adding disks. A failed refresh is retried with the next collection.
+fn pool_ids<'a>(
+    runner: &dyn Runner,
+    nsds: impl IntoIterator<Item = &'a str>,
+) -> mmoxi::Result<HashMap<String, FsPoolId>> {
+    static POOLS: Mutex<Option<Pools>> = Mutex::new(None);
+
+    let mut pools = POOLS.lock().unwrap_or_else(PoisonError::into_inner);
+    let nsds: HashSet<_> = nsds.into_iter().collect();
+
+    let fresh = pools.as_ref().is_some_and(|pools| {
+        pools.refreshed.elapsed() < POOLS_TTL
+            && nsds.iter().all(|nsd| {
+                pools.ids.contains_key(*nsd) || pools.free.contains(*nsd)
+            })
+    });
+
+    if !fresh {
+        let ids = mmoxi::nsd::pool_ids(runner)?;
+
+        let free = nsds
+            .iter()
+            .filter(|nsd| !ids.contains_key(**nsd))
+            .map(|nsd| (*nsd).to_owned())
+            .collect();
+
+        *pools = Some(Pools {
+            refreshed: Instant::now(),
+            ids,
+            free,
+        });
+    }
+
+    Ok(pools
+        .as_ref()
+        .map(|pools| pools.ids.clone())
+        .unwrap_or_default())
+}
+
+// ----------------------------------------------------------------------------
+// partial failure
+// ----------------------------------------------------------------------------
+
+/// Some commands of a collector failed, but the metrics of the others were
+/// collected anyway.
+#[derive(Debug)]
+pub struct PartialFailure(pub Vec<mmoxi::Error>);
+
+impl PartialFailure {
+    /// Returns `true` if all of the failures are transient.
+    pub fn is_transient(&self) -> bool {
+        self.0.iter().all(mmoxi::Error::is_transient)
+    }
+}
+
+impl fmt::Display for PartialFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "some commands failed")?;
+
+        for error in &self.0 {
+            write!(f, "; {error}")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl std::error::Error for PartialFailure {}
+
+// ----------------------------------------------------------------------------
+// self-metrics
+// ----------------------------------------------------------------------------
+
+/// Prefix of the collector self-metrics, see [`stats_metrics`].
+pub const STATS_PREFIX: &str = "gpfs_mmoxi_collector_";
+
+/// Outcome of a single collector run.
+#[derive(Clone, Debug)]
+pub struct Stats {
+    pub collector: &'static str,
+    pub success: bool,
+    pub duration: Duration,
+
+    /// Unix timestamp of the last successful run, if there ever was one.
+    pub last_success: Option<f64>,
+}
+
+impl Stats {
+    /// Returns the stats of a run that just finished.
+    ///
+    /// The last success timestamp is now if the run succeeded and
+    /// `previous_success` otherwise.
+    pub fn new(
+        collector: &'static str,
+        success: bool,
+        duration: Duration,
+        previous_success: Option<f64>,
+    ) -> Self {
+        let last_success = if success {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .ok()
+                .map(|now| now.as_secs_f64())
+        } else {
+            previous_success
+        };
+
+        Self {
+            collector,
+            success,
+            duration,
+            last_success,
+        }
+    }
+}
+
+/// Returns the success, duration and last success timestamp of collectors.
+pub fn stats_metrics(stats: &[Stats]) -> Vec<Family> {
+    let mut success = Family::gauge(
+        format!("{STATS_PREFIX}success"),
+        "Whether the last run of the mmoxi collector succeeded.",
+    );
+    let mut duration = Family::gauge(
+        format!("{STATS_PREFIX}duration_seconds"),
+        "How long the last run of the mmoxi collector took.",
+    )
+    .with_unit("seconds");
+    let mut last_success = Family::gauge(
+        format!("{STATS_PREFIX}last_success_timestamp_seconds"),
+        "When the mmoxi collector last succeeded.",
+    )
+    .with_unit("seconds");
+
+    for stats in stats {
+        let labels = [("collector", stats.collector)];
+
+        success.sample(&labels, stats.success);
+        duration.sample(&labels, stats.duration.as_secs_f64());
+
+        if let Some(timestamp) = stats.last_success {
+            last_success.sample(&labels, timestamp);
+        }
+    }
+
+    vec![success, duration, last_success]
+}
+
+/// Returns the last success timestamp of `collector` in previous output.
+///
+/// Both the Prometheus and the line protocol output are supported.
+pub fn previous_success(previous: &str, collector: &str) -> Option<f64> {
+    let name = format!("{STATS_PREFIX}last_success_timestamp_seconds");
+    let prom = format!("{name}{{collector=\"{collector}\"}} ");
+    let influx = format!("{name},collector={collector} gauge=");
+
+    previous
+        .lines()
+        .find_map(|line| {
+            line.strip_prefix(&prom)
+                .or_else(|| line.strip_prefix(&influx))
+        })
+        .and_then(|value| value.split_whitespace().next())
+        .and_then(|value| value.parse().ok())
+}
@@ -0,0 +1,245 @@
+//! Prometheus HTTP exporter.
+//!
+//! Every enabled collector is refreshed in its own thread in its own interval.
+//! Scrapes only ever serve the cached output of the last refresh, so they never
+//! wait for expensive commands like `mmdf`. Every connection is handled in its
+//! own thread, so a slow client can't stall other scrapes.
+
+use std::collections::{BTreeMap, HashMap};
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, PoisonError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result};
+use clap::ArgMatches;
+
+use mmoxi::prom::{Family, Format};
+use mmoxi::run::Runner;
+
+use crate::collector::{self, Collector, PartialFailure, Stats};
+
+/// Clients that don't send their request in time are disconnected.
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Commands are killed after this long unless `--timeout` is given, so a hung
+/// command can't stall its collector forever.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(600);
+
+/// Rendered metrics and stats of the last refresh per collector.
+type Cache = Mutex<BTreeMap<Collector, Entry>>;
+
+struct Entry {
+    metrics: Vec<Family>,
+    stats: Stats,
+}
+
+pub fn run(runner: &(dyn Runner + Sync), args: &ArgMatches) -> Result<()> {
+    let listen = args
+        .get_one::<String>("listen")
+        .expect("listen has a default value");
+
+    let device_cache = args
+        .get_one::<PathBuf>("device-cache")
+        .expect("device-cache has a default value");
+
+    let intervals: HashMap<Collector, Duration> = args
+        .get_many::<(Collector, Duration)>("interval")
+        .map(|intervals| intervals.copied().collect())
+        .unwrap_or_default();
+
+    let mut collectors = args
+        .get_many::<Collector>("collector")
+        .expect("collector is a required argument")
+        .copied()
+        .collect::<Vec<_>>();
+
+    collectors.sort_unstable();
+    collectors.dedup();
+
+    let address = if listen.starts_with(':') {
+        format!("0.0.0.0{listen}")
+    } else {
+        listen.clone()
+    };
+
+    let listener = TcpListener::bind(&address)
+        .with_context(|| format!("binding to {address}"))?;
+
+    let cache = Cache::default();
+
+    thread::scope(|scope| {
+        for collector in collectors {
+            let interval = intervals
+                .get(&collector)
+                .copied()
+                .unwrap_or_else(|| collector.default_interval());
+
+            let cache = &cache;
+
+            scope.spawn(move || {
+                refresh(runner, collector, interval, device_cache, cache);
+            });
+        }
+
+        for stream in listener.incoming() {
+            let stream = match stream {
+                Ok(stream) => stream,
+                Err(error) => {
+                    eprintln!("exporter: accepting connection: {error}");
+                    continue;
+                }
+            };
+
+            let cache = &cache;
+
+            scope.spawn(move || {
+                if let Err(error) = handle(&stream, cache) {
+                    eprintln!("exporter: {error:#}");
+                }
+            });
+        }
+
+        Ok(())
+    })
+}
+
+/// Refreshes the cached output of `collector` forever.
+fn refresh(
+    runner: &dyn Runner,
+    collector: Collector,
+    interval: Duration,
+    device_cache: &Path,
+    cache: &Cache,
+) {
+    loop {
+        let start = Instant::now();
+
+        let mut metrics = vec![];
+        let result = collector.collect(runner, device_cache, &mut metrics);
+        let duration = start.elapsed();
+
+        let mut cache = cache.lock().unwrap_or_else(PoisonError::into_inner);
+
+        let previous_success = cache
+            .get(&collector)
+            .and_then(|entry| entry.stats.last_success);
+
+        let success = match result {
+            Ok(()) => true,
+
+            Err(error) => {
+                eprintln!("collector {collector}: {error:#}");
+
+                // do not serve stale metrics, but keep the ones of a partial
+                // failure
+                if !error.is::<PartialFailure>() {
+                    metrics.clear();
+                }
+
+                false
+            }
+        };
+
+        let stats =
+            Stats::new(collector.name(), success, duration, previous_success);
+
+        cache.insert(collector, Entry { metrics, stats });
+        drop(cache);
+
+        thread::sleep(interval.saturating_sub(start.elapsed()));
+    }
+}
+
+/// Handles a single HTTP request.
+fn handle(stream: &TcpStream, cache: &Cache) -> Result<()> {
+    stream.set_read_timeout(Some(CLIENT_TIMEOUT))?;
+    stream.set_write_timeout(Some(CLIENT_TIMEOUT))?;
+
+    let mut reader = BufReader::new(stream);
+
+    let mut request = String::new();
+    reader.read_line(&mut request).context("reading request")?;
+
+    let mut format = Format::Text;
+
+    // the request body is ignored anyway
+    loop {
+        let mut header = String::new();
+        let n = reader.read_line(&mut header).context("reading headers")?;
+
+        if n == 0 || header.trim_end().is_empty() {
+            break;
+        }
+
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("accept")
+                && value.contains("application/openmetrics-text")
+            {
+                format = Format::OpenMetrics;
+            }
+        }
+    }
+
+    let mut tokens = request.split_whitespace();
+    let method = tokens.next().unwrap_or_default();
+    let path = tokens.next().unwrap_or_default();
+    let path = path.split_once('?').map_or(path, |(path, _)| path);
+
+    let (status, content_type, body) = match (method, path) {
+        ("GET", "/metrics") => {
+            let mut metrics = vec![];
+            let mut stats = vec![];
+
+            for entry in cache
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .values()
+            {
+                metrics.extend_from_slice(&entry.metrics);
+                stats.push(entry.stats.clone());
+            }
+
+            metrics.extend(collector::stats_metrics(&stats));
+
+            let mut body = vec![];
+            format.render(&metrics, &mut body)?;
+
+            ("200 OK", format.content_type(), body)
+        }
+
+        ("GET", "/") => (
+            "200 OK",
+            "text/html; charset=utf-8",
+            b"<html><body><a href=\"/metrics\">Metrics</a></body></html>\n"
+                .to_vec(),
+        ),
+
+        ("GET", _) => ("404 Not Found", "text/plain", b"not found\n".to_vec()),
+
+        _ => (
+            "405 Method Not Allowed",
+            "text/plain",
+            b"method not allowed\n".to_vec(),
+        ),
+    };
+
+    let mut stream = stream;
+
+    write!(
+        stream,
+        "HTTP/1.1 {status}\r\n\
+         Content-Type: {content_type}\r\n\
+         Content-Length: {}\r\n\
+         Connection: close\r\n\
+         \r\n",
+        body.len()
+    )?;
+
+    stream.write_all(&body)?;
+    stream.flush()?;
+
+    Ok(())
+}
@@ -1,92 +1,172 @@
 #![deny(clippy::all)]
 #![warn(clippy::pedantic, clippy::nursery, clippy::cargo)]
 
-use std::collections::HashMap;
-use std::fs::File;
-use std::io::{self, BufWriter, Write};
+use std::io;
 use std::path::PathBuf;
+use std::process::ExitCode;
+use std::time::{Duration, Instant};
 
 use anyhow::{Context, Result, anyhow};
 use clap::ArgMatches;
 
-use mmoxi::prom::ToText;
+use mmoxi::cluster::{Node, Role};
+use mmoxi::prom::{Family, Format, ToMetrics};
+use mmoxi::run::{Local, Recorder, Replay, Runner};
 
+use crate::collector::{PartialFailure, Stats};
+use crate::output::Output;
+
+mod check;
 mod cli;
+mod collector;
+mod exporter;
+mod output;
+
+/// Exit code for transient errors, see `sysexits.h`.
+const EX_TEMPFAIL: u8 = 75;
+
+fn main() -> ExitCode {
+    match run() {
+        Ok(code) => code,
+        Err(error) => {
+            eprintln!("Error: {error:?}");
+
+            let transient = error.chain().any(|cause| {
+                cause
+                    .downcast_ref::<mmoxi::Error>()
+                    .map(mmoxi::Error::is_transient)
+                    .or_else(|| {
+                        cause
+                            .downcast_ref::<PartialFailure>()
+                            .map(PartialFailure::is_transient)
+                    })
+                    .unwrap_or_default()
+            });
+
+            if transient {
+                ExitCode::from(EX_TEMPFAIL)
+            } else {
+                ExitCode::FAILURE
+            }
+        }
+    }
+}
 
-fn main() -> Result<()> {
+fn run() -> Result<ExitCode> {
     let args = cli::args();
 
+    let runner = runner(&args);
+    let runner = runner.as_ref();
+
+    // checks report errors themselves and have their own exit codes
+    if let Some(("check", args)) = args.subcommand() {
+        return Ok(check::run(runner, args));
+    }
+
     match args.subcommand() {
-        Some(("cache", args)) => dispatch_cache(args),
-        Some(("list", args)) => dispatch_list(args),
-        Some(("pool-percent", args)) => run_pool_percent(args),
-        Some(("prometheus", args)) => dispatch_prom(args),
-        Some(("show", args)) => dispatch_show(args),
+        Some(("analyze", args)) => dispatch_analyze(runner, args),
+        Some(("cache", args)) => dispatch_cache(runner, args),
+        Some(("exporter", args)) => exporter::run(runner, args),
+        Some(("list", args)) => dispatch_list(runner, args),
+        Some(("pool-percent", args)) => run_pool_percent(runner, args),
+        Some(("prometheus", args)) => dispatch_prom(runner, args),
+        Some(("show", args)) => dispatch_show(runner, args),
 
         _ => Err(anyhow!("subcommand is required")),
-    }
+    }?;
+
+    Ok(ExitCode::SUCCESS)
 }
 
 // ----------------------------------------------------------------------------
 // subcommand dispatcher
 // ----------------------------------------------------------------------------
 
-fn dispatch_cache(args: &ArgMatches) -> Result<()> {
+fn dispatch_analyze(runner: &dyn Runner, args: &ArgMatches) -> Result<()> {
+    match args.subcommand() {
+        Some(("replication", args)) => run_analyze_replication(runner, args),
+
+        _ => Err(anyhow!("subcommand is required")),
+    }
+}
+
+fn dispatch_cache(runner: &dyn Runner, args: &ArgMatches) -> Result<()> {
     match args.subcommand() {
-        Some(("nmon", args)) => run_cache_nmon(args),
-        Some(("nsds", args)) => run_cache_nsds(args),
+        Some(("nmon", args)) => run_cache_nmon(runner, args),
+        Some(("nsds", args)) => run_cache_nsds(runner, args),
 
         _ => Err(anyhow!("subcommand is required")),
     }
 }
 
-fn dispatch_list(args: &ArgMatches) -> Result<()> {
+fn dispatch_list(runner: &dyn Runner, args: &ArgMatches) -> Result<()> {
     match args.subcommand() {
-        Some(("filesystems", _args)) => run_list_filesystems(),
+        Some(("filesystems", args)) => run_list_filesystems(runner, args),
+        Some(("mounts", args)) => run_list_mounts(runner, args),
+        Some(("nodes", args)) => run_list_nodes(runner, args),
 
         _ => Err(anyhow!("subcommand is required")),
     }
 }
 
-fn dispatch_prom(args: &ArgMatches) -> Result<()> {
+fn dispatch_prom(runner: &dyn Runner, args: &ArgMatches) -> Result<()> {
     match args.subcommand() {
-        Some(("deadlocks", args)) => run_prom_deadlocks(args),
-        Some(("df", args)) => run_prom_df(args),
-        Some(("disk", args)) => run_prom_disk(args),
-        Some(("fileset", args)) => run_prom_fileset(args),
-        Some(("manager", args)) => run_prom_manager(args),
-        Some(("pool", args)) => dispatch_prom_pool(args),
+        Some(("deadlocks", args)) => run_prom_deadlocks(runner, args),
+        Some(("df", args)) => run_prom_df(runner, args),
+        Some(("disk", args)) => run_prom_disk(runner, args),
+        Some(("fileset", args)) => run_prom_fileset(runner, args),
+        Some(("filesystem", args)) => run_prom_filesystem(runner, args),
+        Some(("diag-memory", args)) => run_prom_diag_memory(runner, args),
+        Some(("diag-network", args)) => run_prom_diag_network(runner, args),
+        Some(("diag-stats", args)) => run_prom_diag_stats(runner, args),
+        Some(("health", args)) => run_prom_health(runner, args),
+        Some(("iohist", args)) => run_prom_iohist(runner, args),
+        Some(("manager", args)) => run_prom_manager(runner, args),
+        Some(("mmpmon", args)) => run_prom_mmpmon(runner, args),
+        Some(("mount", args)) => run_prom_mount(runner, args),
+        Some(("pool", args)) => dispatch_prom_pool(runner, args),
         Some(("quota", args)) => run_prom_quota(args),
+        Some(("replication", args)) => run_prom_replication(runner, args),
+        Some(("state", args)) => run_prom_state(runner, args),
+        Some(("waiters", args)) => run_prom_waiters(runner, args),
 
         _ => Err(anyhow!("subcommand is required")),
     }
 }
 
-fn dispatch_prom_pool(args: &ArgMatches) -> Result<()> {
+fn dispatch_prom_pool(runner: &dyn Runner, args: &ArgMatches) -> Result<()> {
     match args.subcommand() {
-        Some(("block", args)) => run_prom_pool_block(args),
-        Some(("usage", args)) => run_prom_pool_usage(args),
+        Some(("block", args)) => run_prom_pool_block(runner, args),
+        Some(("usage", args)) => run_prom_pool_usage(runner, args),
         Some(("user-distribution", args)) => {
-            run_prom_pool_user_distribution(args)
+            run_prom_pool_user_distribution(runner, args)
         }
 
         _ => Err(anyhow!("subcommand is required")),
     }
 }
 
-fn dispatch_show(args: &ArgMatches) -> Result<()> {
+fn dispatch_show(runner: &dyn Runner, args: &ArgMatches) -> Result<()> {
     match args.subcommand() {
-        Some(("manager", args)) => dispatch_show_manager(args),
-        Some(("node", _args)) => run_show_node(),
+        Some(("cluster", args)) => run_show_cluster(runner, args),
+        Some(("filesystem", args)) => run_show_filesystem(runner, args),
+        Some(("health", args)) => run_show_health(runner, args),
+        Some(("manager", args)) => dispatch_show_manager(runner, args),
+        Some(("node", args)) => run_show_node(runner, args),
 
         _ => Err(anyhow!("subcommand is required")),
     }
 }
 
-fn dispatch_show_manager(args: &ArgMatches) -> Result<()> {
+fn dispatch_show_manager(
+    runner: &dyn Runner,
+    args: &ArgMatches,
+) -> Result<()> {
     match args.subcommand() {
-        Some(("cluster", _args)) => run_show_cluster_manager(),
-        Some(("filesystem", args)) => run_show_filesystem_manager(args),
+        Some(("cluster", args)) => run_show_cluster_manager(runner, args),
+        Some(("filesystem", args)) => {
+            run_show_filesystem_manager(runner, args)
+        }
 
         _ => Err(anyhow!("subcommand is required")),
     }
@@ -96,7 +176,50 @@ fn dispatch_show_manager(args: &ArgMatches) -> Result<()> {
 // runner
 // ----------------------------------------------------------------------------
 
-fn run_cache_nmon(args: &ArgMatches) -> Result<()> {
+fn run_analyze_replication(
+    runner: &dyn Runner,
+    args: &ArgMatches,
+) -> Result<()> {
+    let filesystem = args
+        .get_one::<String>("filesystem")
+        .expect("filesystem is a required argument");
+
+    let pools = mmoxi::replication::run(runner, filesystem)?;
+
+    #[cfg(feature = "serde")]
+    if args.get_flag("json") {
+        return print_json(&pools);
+    }
+
+    for pool in pools {
+        for (kind, redundancy) in
+            [("data", pool.data()), ("metadata", pool.meta())]
+        {
+            let Some(redundancy) = redundancy else {
+                continue;
+            };
+
+            let verdict = if redundancy.survives_failure_group_loss() {
+                "survives losing any failure group"
+            } else {
+                "does not survive losing a failure group"
+            };
+
+            println!(
+                "{} {kind}: replicas {}, failure groups [{}], degraded [{}], \
+                 {verdict}",
+                pool.name(),
+                redundancy.replicas(),
+                redundancy.failure_groups().join(", "),
+                redundancy.degraded().join(", "),
+            );
+        }
+    }
+
+    Ok(())
+}
+
+fn run_cache_nmon(runner: &dyn Runner, args: &ArgMatches) -> Result<()> {
     let force = args.get_flag("force");
 
     let device_cache = args
@@ -107,29 +230,36 @@ fn run_cache_nmon(args: &ArgMatches) -> Result<()> {
         .get_one::<PathBuf>("output")
         .expect("output has a default value");
 
-    let output = File::create(output).with_context(|| {
-        format!("creating output file: {}", output.display())
-    })?;
+    let mut output = Output::new(Some(output.clone()));
 
-    let mut output = BufWriter::new(output);
+    mmoxi::nmon::by_pool_cached(runner, device_cache, force, &mut output)?;
 
-    mmoxi::nmon::by_pool_cached(device_cache, force, &mut output)
+    output.commit()
 }
 
-fn run_cache_nsds(args: &ArgMatches) -> Result<()> {
+fn run_cache_nsds(runner: &dyn Runner, args: &ArgMatches) -> Result<()> {
     let force = args.get_flag("force");
 
     let output = args
         .get_one::<PathBuf>("output")
         .expect("output has a default value");
 
-    let _nsds = mmoxi::nsd::local_cached(output, force)?;
+    let _nsds = mmoxi::nsd::local_cached(runner, output, force)?;
 
     Ok(())
 }
 
-fn run_list_filesystems() -> Result<()> {
-    let names = mmoxi::fs::names()?;
+fn run_list_filesystems(
+    runner: &dyn Runner,
+    #[cfg_attr(not(feature = "serde"), allow(unused_variables))]
+    args: &ArgMatches,
+) -> Result<()> {
+    let names = mmoxi::fs::names(runner)?;
+
+    #[cfg(feature = "serde")]
+    if args.get_flag("json") {
+        return print_json(&names);
+    }
 
     for name in names {
         println!("{name}");
@@ -138,12 +268,12 @@ fn run_list_filesystems() -> Result<()> {
     Ok(())
 }
 
-fn run_pool_percent(args: &ArgMatches) -> Result<()> {
+fn run_pool_percent(runner: &dyn Runner, args: &ArgMatches) -> Result<()> {
     let filesystem = args
         .get_one::<String>("filesystem")
         .expect("filesystem is a required argument");
 
-    let filesystem = mmoxi::pool::run(filesystem)?;
+    let filesystem = mmoxi::pool::run(runner, filesystem)?;
 
     let pool_arg = args
         .get_one::<String>("pool")
@@ -164,9 +294,10 @@ fn run_pool_percent(args: &ArgMatches) -> Result<()> {
     Ok(())
 }
 
-fn run_prom_pool_user_distribution(args: &ArgMatches) -> Result<()> {
-    let mut output = output_to_bufwriter(args)?;
-
+fn run_prom_pool_user_distribution(
+    runner: &dyn Runner,
+    args: &ArgMatches,
+) -> Result<()> {
     let device_or_dir = args
         .get_one::<String>("device-or-dir")
         .expect("device-or-dir is a required argument");
@@ -175,136 +306,359 @@ fn run_prom_pool_user_distribution(args: &ArgMatches) -> Result<()> {
         .get_one::<String>("pool")
         .expect("pool is a required argument");
 
-    let fileset = args.get_one::<String>("fileset");
-
-    let nodes = args.get_one::<String>("nodes");
+    let options = mmoxi::policy::pool_user_distribution::Options {
+        fileset: args.get_one::<String>("fileset").cloned(),
+        nodes: args.get_one::<String>("nodes").cloned(),
+        local_work_dir: args.get_one::<PathBuf>("local-work-dir").cloned(),
+        global_work_dir: args.get_one::<PathBuf>("global-work-dir").cloned(),
+        scope: args.get_one::<String>("scope").cloned(),
+    };
 
-    let local_work_dir = args.get_one::<PathBuf>("local-work-dir");
-    let global_work_dir = args.get_one::<PathBuf>("global-work-dir");
+    prometheus(args, "pool-user-distribution", |metrics| {
+        let data = mmoxi::policy::pool_user_distribution::run(
+            runner,
+            device_or_dir,
+            pool,
+            &options,
+        )?;
 
-    let scope = args.get_one::<String>("scope");
+        metrics.extend(data.to_metrics());
 
-    let data = mmoxi::policy::pool_user_distribution::run(
-        device_or_dir,
-        pool,
-        fileset,
-        nodes,
-        local_work_dir,
-        global_work_dir,
-        scope,
-    )?;
+        Ok(())
+    })
+}
 
-    data.to_prom(&mut output)?;
+fn run_prom_deadlocks(runner: &dyn Runner, args: &ArgMatches) -> Result<()> {
+    prometheus(args, "deadlocks", |metrics| {
+        collector::deadlocks(runner, metrics)
+    })
+}
 
-    Ok(())
+fn run_prom_df(runner: &dyn Runner, args: &ArgMatches) -> Result<()> {
+    prometheus(args, "df", |metrics| collector::df(runner, metrics))
 }
 
-fn run_prom_deadlocks(args: &ArgMatches) -> Result<()> {
-    let mut output = output_to_bufwriter(args)?;
-    let data = mmoxi::diag::deadlock()?;
-    data.to_prom(&mut output)?;
-    Ok(())
+fn run_prom_disk(runner: &dyn Runner, args: &ArgMatches) -> Result<()> {
+    prometheus(args, "disk", |metrics| collector::disk(runner, metrics))
 }
 
-fn run_prom_df(args: &ArgMatches) -> Result<()> {
-    let mut output = output_to_bufwriter(args)?;
-    let data = mmoxi::df::run()?;
-    data.to_prom(&mut output)?;
+fn run_list_mounts(
+    runner: &dyn Runner,
+    #[cfg_attr(not(feature = "serde"), allow(unused_variables))]
+    args: &ArgMatches,
+) -> Result<()> {
+    let mounts = mmoxi::mount::mounts(runner)?;
+
+    #[cfg(feature = "serde")]
+    if args.get_flag("json") {
+        return print_json(&mounts);
+    }
+
+    for fs in mounts {
+        for node in fs.nodes() {
+            println!("{} {} {}", fs.fs_name(), node.name(), node.cluster());
+        }
+    }
+
     Ok(())
 }
 
-fn run_prom_disk(args: &ArgMatches) -> Result<()> {
-    let mut output = output_to_bufwriter(args)?;
+fn run_list_nodes(runner: &dyn Runner, args: &ArgMatches) -> Result<()> {
+    let cluster = mmoxi::cluster::get(runner)?;
+
+    let role = args
+        .get_one::<String>("role")
+        .map(|role| role.parse::<Role>())
+        .transpose()?;
 
-    let mut all_disks = HashMap::new();
+    let nodes = cluster
+        .nodes()
+        .iter()
+        .filter(|node| role.as_ref().is_none_or(|role| node.has_role(role)))
+        .collect::<Vec<_>>();
 
-    for fs in mmoxi::fs::names()? {
-        let disks = mmoxi::disk::disks(&fs)?;
-        all_disks.insert(fs, disks);
+    #[cfg(feature = "serde")]
+    if args.get_flag("json") {
+        return print_json(&nodes);
     }
 
-    all_disks
-        .to_prom(&mut output)
-        .context("converting internal data to prometheus")?;
+    for node in nodes {
+        println!("{}", node.daemon_name());
+    }
 
     Ok(())
 }
 
-fn run_prom_fileset(args: &ArgMatches) -> Result<()> {
-    let mut output = output_to_bufwriter(args)?;
+fn run_prom_fileset(runner: &dyn Runner, args: &ArgMatches) -> Result<()> {
+    prometheus(args, "fileset", |metrics| {
+        collector::fileset(runner, metrics)
+    })
+}
 
-    let mut filesets = vec![];
+fn run_prom_filesystem(runner: &dyn Runner, args: &ArgMatches) -> Result<()> {
+    prometheus(args, "filesystem", |metrics| {
+        collector::filesystem(runner, metrics)
+    })
+}
 
-    for fs in mmoxi::fs::names()? {
-        filesets.extend(mmoxi::fileset::filesets(&fs)?);
-    }
+fn run_prom_diag_memory(runner: &dyn Runner, args: &ArgMatches) -> Result<()> {
+    prometheus(args, "diag-memory", |metrics| {
+        collector::diag_memory(runner, metrics)
+    })
+}
 
-    filesets.to_prom(&mut output)?;
+fn run_prom_diag_network(
+    runner: &dyn Runner,
+    args: &ArgMatches,
+) -> Result<()> {
+    prometheus(args, "diag-network", |metrics| {
+        collector::diag_network(runner, metrics)
+    })
+}
 
-    Ok(())
+fn run_prom_diag_stats(runner: &dyn Runner, args: &ArgMatches) -> Result<()> {
+    prometheus(args, "diag-stats", |metrics| {
+        collector::diag_stats(runner, metrics)
+    })
+}
+
+fn run_prom_health(runner: &dyn Runner, args: &ArgMatches) -> Result<()> {
+    prometheus(args, "health", |metrics| collector::health(runner, metrics))
 }
 
-fn run_prom_manager(args: &ArgMatches) -> Result<()> {
-    let mut output = output_to_bufwriter(args)?;
+fn run_prom_iohist(runner: &dyn Runner, args: &ArgMatches) -> Result<()> {
+    prometheus(args, "iohist", |metrics| collector::iohist(runner, metrics))
+}
 
-    let data = mmoxi::mgr::get()?;
-    data.to_prom(&mut output)?;
+fn run_prom_manager(runner: &dyn Runner, args: &ArgMatches) -> Result<()> {
+    prometheus(args, "manager", |metrics| {
+        collector::manager(runner, metrics)
+    })
+}
 
-    Ok(())
+fn run_prom_mmpmon(runner: &dyn Runner, args: &ArgMatches) -> Result<()> {
+    prometheus(args, "mmpmon", |metrics| collector::mmpmon(runner, metrics))
 }
 
-fn run_prom_pool_block(args: &ArgMatches) -> Result<()> {
-    let mut output = output_to_bufwriter(args)?;
+fn run_prom_mount(runner: &dyn Runner, args: &ArgMatches) -> Result<()> {
+    prometheus(args, "mount", |metrics| collector::mount(runner, metrics))
+}
 
+fn run_prom_pool_block(runner: &dyn Runner, args: &ArgMatches) -> Result<()> {
     let device_cache = args
         .get_one::<PathBuf>("device-cache")
         .expect("device-cache has a default value");
 
     let force = args.get_flag("force");
 
-    let metrics = mmoxi::prom::pool_block_device_metrics(device_cache, force)?;
-    metrics.to_prom(&mut output)?;
+    prometheus(args, "pool-block", |metrics| {
+        collector::pool_block(runner, device_cache, force, metrics)
+    })
+}
 
-    Ok(())
+fn run_prom_pool_usage(runner: &dyn Runner, args: &ArgMatches) -> Result<()> {
+    prometheus(args, "pool-usage", |metrics| {
+        collector::pool_usage(runner, metrics)
+    })
+}
+
+fn run_prom_quota(args: &ArgMatches) -> Result<()> {
+    prometheus(args, "quota", |metrics| {
+        let data = mmoxi::quota::Data::from_reader(io::stdin().lock())?;
+        metrics.extend(data.to_metrics());
+        Ok(())
+    })
 }
 
-fn run_prom_pool_usage(args: &ArgMatches) -> Result<()> {
-    let mut output = output_to_bufwriter(args)?;
+fn run_prom_replication(runner: &dyn Runner, args: &ArgMatches) -> Result<()> {
+    prometheus(args, "replication", |metrics| {
+        collector::replication(runner, metrics)
+    })
+}
+
+fn run_prom_state(runner: &dyn Runner, args: &ArgMatches) -> Result<()> {
+    prometheus(args, "state", |metrics| collector::state(runner, metrics))
+}
+
+fn run_prom_waiters(runner: &dyn Runner, args: &ArgMatches) -> Result<()> {
+    prometheus(args, "waiters", |metrics| {
+        collector::waiters(runner, metrics)
+    })
+}
 
-    let names = mmoxi::fs::names()?;
+fn run_show_cluster_manager(
+    runner: &dyn Runner,
+    #[cfg_attr(not(feature = "serde"), allow(unused_variables))]
+    args: &ArgMatches,
+) -> Result<()> {
+    let managers = mmoxi::mgr::get(runner)?;
 
-    let filesystems = mmoxi::pool::run_all(&names)?;
+    #[cfg(feature = "serde")]
+    if args.get_flag("json") {
+        return print_json(managers.cluster());
+    }
 
-    filesystems
-        .to_prom(&mut output)
-        .context("converting internal data to prometheus")?;
+    println!("{}", managers.cluster().name());
 
     Ok(())
 }
 
-fn run_prom_quota(args: &ArgMatches) -> Result<()> {
-    let mut output = output_to_bufwriter(args)?;
+fn run_show_cluster(
+    runner: &dyn Runner,
+    #[cfg_attr(not(feature = "serde"), allow(unused_variables))]
+    args: &ArgMatches,
+) -> Result<()> {
+    let cluster = mmoxi::cluster::get(runner)?;
 
-    let data = mmoxi::quota::Data::from_reader(io::stdin().lock())?;
-    data.to_prom(&mut output)?;
+    #[cfg(feature = "serde")]
+    if args.get_flag("json") {
+        return print_json(&cluster);
+    }
+
+    println!("name: {}", cluster.name());
+    println!("id: {}", cluster.id());
+    println!("uid domain: {}", cluster.uid_domain());
+    println!("remote shell: {}", cluster.rsh_path());
+    println!("remote copy: {}", cluster.rcp_path());
+    println!("repository type: {}", cluster.repository_type());
+    println!("nodes: {}", cluster.nodes().len());
+
+    for role in [Role::Quorum, Role::Manager, Role::Perfmon, Role::Gateway] {
+        let nodes = cluster
+            .nodes_with(role.clone())
+            .map(Node::daemon_name)
+            .collect::<Vec<_>>();
+
+        if !nodes.is_empty() {
+            println!("{role} nodes: {}", nodes.join(", "));
+        }
+    }
 
     Ok(())
 }
 
-fn run_show_cluster_manager() -> Result<()> {
-    let managers = mmoxi::mgr::get()?;
+fn run_show_filesystem(runner: &dyn Runner, args: &ArgMatches) -> Result<()> {
+    let filesystem = args
+        .get_one::<String>("filesystem")
+        .expect("filesystem is a required argument");
+
+    let fs = mmoxi::fs::filesystem(runner, filesystem)?;
 
-    println!("{}", managers.cluster().name());
+    #[cfg(feature = "serde")]
+    if args.get_flag("json") {
+        return print_json(&fs);
+    }
+
+    let replication = fs.replication();
+
+    println!("name: {}", fs.name());
+    println!("version: {}", fs.version());
+    println!(
+        "original version: {}",
+        fs.original_version().unwrap_or("unknown")
+    );
+    println!("created: {}", fs.create_time());
+    println!("mount point: {}", fs.mount_point());
+    println!("automount: {}", fs.automount());
+    println!("block size: {}", fs.block_size());
+    println!("min fragment size: {}", fs.min_fragment_size());
+    println!("inode size: {}", fs.inode_size());
+    println!("indirect block size: {}", fs.indirect_block_size());
+    println!("max inodes: {}", fs.max_inodes());
+    println!(
+        "data replicas: {} (max {})",
+        replication.default_data(),
+        replication.max_data()
+    );
+    println!(
+        "metadata replicas: {} (max {})",
+        replication.default_metadata(),
+        replication.max_metadata()
+    );
+    println!("quotas accounting: {}", fs.quotas_accounting());
+    println!("quotas enforced: {}", fs.quotas_enforced());
+    println!("default quotas: {}", fs.default_quotas());
+    println!(
+        "per fileset quotas: {}",
+        fs.per_fileset_quotas()
+            .map_or_else(|| "unknown".into(), |quotas| quotas.to_string())
+    );
+    println!(
+        "fileset df: {}",
+        fs.fileset_df()
+            .map_or_else(|| "unknown".into(), |df| df.to_string())
+    );
+    println!("nodes: {}", fs.num_nodes());
+
+    Ok(())
+}
+
+fn run_show_health(runner: &dyn Runner, args: &ArgMatches) -> Result<()> {
+    if args.get_flag("cluster") {
+        let health = mmoxi::health::cluster(runner)?;
+
+        #[cfg(feature = "serde")]
+        if args.get_flag("json") {
+            return print_json(&health);
+        }
+
+        for summary in health.summaries() {
+            println!(
+                "{}: {} nodes, {} failed, {} degraded, {} healthy, {} other",
+                summary.component(),
+                summary.total(),
+                summary.failed(),
+                summary.degraded(),
+                summary.healthy(),
+                summary.other(),
+            );
+        }
+
+        return Ok(());
+    }
+
+    let health = mmoxi::health::node(runner)?;
+
+    #[cfg(feature = "serde")]
+    if args.get_flag("json") {
+        return print_json(&health);
+    }
+
+    for state in health.states() {
+        println!(
+            "{} {} {}",
+            state.component(),
+            state.entity_name(),
+            state.state()
+        );
+    }
+
+    for event in health.visible_events() {
+        let severity = event
+            .severity()
+            .map(|severity| format!(" ({severity})"))
+            .unwrap_or_default();
+
+        println!(
+            "event: {} {} {}{severity}",
+            event.component(),
+            event.entity_name(),
+            event.name(),
+        );
+    }
 
     Ok(())
 }
 
-fn run_show_filesystem_manager(args: &ArgMatches) -> Result<()> {
+fn run_show_filesystem_manager(
+    runner: &dyn Runner,
+    args: &ArgMatches,
+) -> Result<()> {
     let filesystem_name = args
         .get_one::<String>("filesystem")
         .expect("filesystem is a required argument");
 
-    let managers = mmoxi::mgr::get()?;
+    let managers = mmoxi::mgr::get(runner)?;
 
     let Some(manager) = managers
         .fs()
@@ -314,15 +668,29 @@ fn run_show_filesystem_manager(args: &ArgMatches) -> Result<()> {
         return Err(anyhow!("filesystem not found in manager list"));
     };
 
+    #[cfg(feature = "serde")]
+    if args.get_flag("json") {
+        return print_json(manager);
+    }
+
     println!("{}", manager.manager_name());
 
     Ok(())
 }
 
-fn run_show_node() -> Result<()> {
-    let node = mmoxi::state::local_node_name()
+fn run_show_node(
+    runner: &dyn Runner,
+    #[cfg_attr(not(feature = "serde"), allow(unused_variables))]
+    args: &ArgMatches,
+) -> Result<()> {
+    let node = mmoxi::state::local_node_name(runner)
         .context("determining local node name")?;
 
+    #[cfg(feature = "serde")]
+    if args.get_flag("json") {
+        return print_json(&node);
+    }
+
     println!("{node}");
 
     Ok(())
@@ -332,16 +700,145 @@ fn run_show_node() -> Result<()> {
 // helper
 // ----------------------------------------------------------------------------
 
-fn output_to_bufwriter(
-    args: &ArgMatches,
-) -> Result<BufWriter<Box<dyn Write>>> {
-    let output = args.get_one::<PathBuf>("output");
+/// Prints `value` as pretty JSON to stdout.
+#[cfg(feature = "serde")]
+fn print_json(value: &impl serde::Serialize) -> Result<()> {
+    use std::io::Write;
 
-    let output: Box<dyn Write> = if let Some(ref output) = output {
-        Box::new(File::create(output)?)
+    let mut stdout = io::stdout().lock();
+
+    serde_json::to_writer_pretty(&mut stdout, value)
+        .context("writing JSON to stdout")?;
+
+    writeln!(stdout)?;
+
+    Ok(())
+}
+
+fn runner(args: &ArgMatches) -> Box<dyn Runner + Sync> {
+    if let Some(dir) = args.get_one::<PathBuf>("replay") {
+        return Box::new(Replay::from_dir(dir));
+    }
+
+    let mut local = Local::new();
+
+    if let Some(dir) = args.get_one::<PathBuf>("bin-dir") {
+        local = local.bin_dir(dir);
+    }
+
+    if args.get_flag("sudo") {
+        local = local.wrapper(["sudo", "-n"]);
+    }
+
+    let timeout = args.get_one::<Duration>("timeout").copied().or_else(|| {
+        (args.subcommand_name() == Some("exporter"))
+            .then_some(exporter::DEFAULT_TIMEOUT)
+    });
+
+    if let Some(timeout) = timeout {
+        local = local.timeout(timeout);
+    }
+
+    if let Some(timeouts) =
+        args.get_many::<(String, Duration)>("program-timeout")
+    {
+        for (program, timeout) in timeouts {
+            local = local.program_timeout(program, *timeout);
+        }
+    }
+
+    if let Some(dir) = args.get_one::<PathBuf>("record") {
+        Box::new(Recorder::new(local, dir))
     } else {
-        Box::new(io::stdout())
+        Box::new(local)
+    }
+}
+
+/// Runs a prometheus `collector`, writing its output atomically.
+///
+/// The collector self-metrics are appended to stdout or written to a separate
+/// stats file next to the output file. If `collect` fails, the previous output
+/// file is left untouched, unless the failure is only partial.
+fn prometheus<F>(
+    args: &ArgMatches,
+    collector: &'static str,
+    collect: F,
+) -> Result<()>
+where
+    F: FnOnce(&mut Vec<Family>) -> Result<()>,
+{
+    let format = *args
+        .get_one::<Format>("format")
+        .expect("format has a default value");
+
+    let output = Output::from_args(args);
+
+    let mut metrics = vec![];
+
+    let start = Instant::now();
+    let result = collect(&mut metrics);
+    let duration = start.elapsed();
+
+    let committed = commit_prometheus(
+        output, format, metrics, collector, &result, duration,
+    );
+
+    match result {
+        Ok(()) => committed,
+
+        Err(error) => {
+            if let Err(commit_error) = committed {
+                eprintln!("Error: {commit_error:?}");
+            }
+
+            Err(error)
+        }
+    }
+}
+
+fn commit_prometheus(
+    mut output: Output,
+    format: Format,
+    mut metrics: Vec<Family>,
+    collector: &'static str,
+    result: &Result<()>,
+    duration: Duration,
+) -> Result<()> {
+    let stats_output = output.stats();
+
+    // keep the metrics of the parts that succeeded
+    let write_metrics = match result {
+        Ok(()) => true,
+        Err(error) => error.is::<PartialFailure>(),
     };
 
-    Ok(BufWriter::new(output))
+    let previous_success = match (result, &stats_output) {
+        (Err(_), Some(stats_output)) => {
+            stats_output.previous_success(collector)?
+        }
+        _ => None,
+    };
+
+    let stats =
+        Stats::new(collector, result.is_ok(), duration, previous_success);
+    let stats = collector::stats_metrics(&[stats]);
+
+    if !write_metrics {
+        metrics.clear();
+    }
+
+    let Some(mut stats_output) = stats_output else {
+        metrics.extend(stats);
+        format.render(&metrics, &mut output)?;
+        return output.commit();
+    };
+
+    // the previous output file is left untouched if the collector failed
+    if write_metrics {
+        format.render(&metrics, &mut output)?;
+        output.commit()?;
+    }
+
+    format.render(&stats, &mut stats_output)?;
+    stats_output.commit()
 }
@@ -0,0 +1,131 @@
+//! Atomic output.
+
+use std::fs::{self, Permissions};
+use std::io::{self, Write};
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use clap::ArgMatches;
+
+use crate::collector;
+
+/// Output that is only written to its target on [`Output::commit`].
+///
+/// Everything is buffered in memory. On commit, file targets are written to a
+/// temporary file in the same directory, which is then renamed over the
+/// target. This way, readers like the node exporter textfile collector never
+/// see partial output.
+pub struct Output {
+    target: Option<PathBuf>,
+    buf: Vec<u8>,
+}
+
+impl Output {
+    /// Returns output to the `--output` file, or to stdout if there is none.
+    pub fn from_args(args: &ArgMatches) -> Self {
+        Self::new(args.get_one::<PathBuf>("output").cloned())
+    }
+
+    pub const fn new(target: Option<PathBuf>) -> Self {
+        Self {
+            target,
+            buf: vec![],
+        }
+    }
+
+    /// Writes the buffered output to the target.
+    pub fn commit(self) -> Result<()> {
+        if let Some(path) = self.target {
+            write_atomic(&path, &self.buf)
+        } else {
+            let mut stdout = io::stdout().lock();
+            stdout.write_all(&self.buf)?;
+            stdout.flush()?;
+            Ok(())
+        }
+    }
+
+    /// Returns the output for the collector self-metrics, or [`None`] if
+    /// they go to stdout along with the other metrics.
+    ///
+    /// For a file target, this is a file next to it with `.stats` before
+    /// the extension, e.g. `df.stats.prom` for `df.prom`. This way, the
+    /// target itself can be left untouched if a collector fails.
+    pub fn stats(&self) -> Option<Self> {
+        let path = self.target.as_ref()?;
+
+        let mut name = path.file_stem().unwrap_or_default().to_owned();
+        name.push(".stats");
+
+        if let Some(extension) = path.extension() {
+            name.push(".");
+            name.push(extension);
+        }
+
+        Some(Self::new(Some(path.with_file_name(name))))
+    }
+
+    /// Returns the last success timestamp of `collector` in the previous
+    /// output of the target.
+    pub fn previous_success(&self, collector: &str) -> Result<Option<f64>> {
+        let previous_success = self.previous()?.and_then(|previous| {
+            let previous = String::from_utf8_lossy(&previous);
+            collector::previous_success(&previous, collector)
+        });
+
+        Ok(previous_success)
+    }
+
+    fn previous(&self) -> Result<Option<Vec<u8>>> {
+        let Some(path) = &self.target else {
+            return Ok(None);
+        };
+
+        match fs::read(path) {
+            Ok(content) => Ok(Some(content)),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(error) => Err(error).with_context(|| {
+                format!("reading previous output: {}", path.display())
+            }),
+        }
+    }
+}
+
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buf.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Writes `content` to a temporary file next to `path` and renames it to
+/// `path`.
+fn write_atomic(path: &Path, content: &[u8]) -> Result<()> {
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+
+    let mut file = tempfile::Builder::new()
+        .prefix(".mmoxi")
+        .permissions(Permissions::from_mode(0o644))
+        .tempfile_in(dir)
+        .with_context(|| {
+            format!("creating temporary file in {}", dir.display())
+        })?;
+
+    file.write_all(content)
+        .and_then(|()| file.as_file().sync_all())
+        .with_context(|| {
+            format!("writing temporary file: {}", file.path().display())
+        })?;
+
+    file.persist(path)
+        .with_context(|| format!("renaming to output: {}", path.display()))?;
+
+    Ok(())
+}
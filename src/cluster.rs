@@ -0,0 +1,398 @@
+//! `mmlscluster` parsing.
+
+use std::fmt::Display;
+use std::io::BufRead;
+use std::str::FromStr;
+
+use crate::{Error, Result};
+
+use crate::parse::{FromRecord, Record};
+use crate::run::{Cmd, Runner};
+use crate::util::serde_via_str;
+
+/// Returns the cluster configuration and its nodes.
+///
+/// # Errors
+///
+/// Returns an error if running `mmlscluster` fails or if parsing its output
+/// fails.
+pub fn get(runner: &dyn Runner) -> Result<Cluster> {
+    let cmd = Cmd::new("mmlscluster").arg("-Y");
+
+    let output = runner.run_checked(&cmd)?;
+
+    Cluster::from_reader(output.stdout.as_slice())
+}
+
+/// Cluster configuration.
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Cluster {
+    name: String,
+    id: String,
+    uid_domain: String,
+    rsh_path: String,
+    rcp_path: String,
+    repository_type: String,
+    nodes: Vec<Node>,
+}
+
+impl Cluster {
+    /// Returns the cluster name.
+    #[must_use]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the cluster ID.
+    #[must_use]
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Returns the UID domain.
+    #[must_use]
+    pub fn uid_domain(&self) -> &str {
+        &self.uid_domain
+    }
+
+    /// Returns the remote shell command.
+    #[must_use]
+    pub fn rsh_path(&self) -> &str {
+        &self.rsh_path
+    }
+
+    /// Returns the remote file copy command.
+    #[must_use]
+    pub fn rcp_path(&self) -> &str {
+        &self.rcp_path
+    }
+
+    /// Returns the configuration repository type, e.g. `CCR`.
+    #[must_use]
+    pub fn repository_type(&self) -> &str {
+        &self.repository_type
+    }
+
+    /// Returns the nodes.
+    #[must_use]
+    pub fn nodes(&self) -> &[Node] {
+        &self.nodes
+    }
+
+    /// Returns the node with the given daemon or admin node name.
+    ///
+    /// Other commands, e.g. `mmlsmgr` and `mmgetstate`, usually report short
+    /// node names, so these match as well.
+    #[must_use]
+    pub fn node(&self, name: &str) -> Option<&Node> {
+        self.nodes.iter().find(|node| node.is_named(name))
+    }
+
+    /// Returns the nodes with `role`.
+    pub fn nodes_with(&self, role: Role) -> impl Iterator<Item = &Node> {
+        self.nodes.iter().filter(move |node| node.has_role(&role))
+    }
+
+    fn from_reader<Input: BufRead>(input: Input) -> Result<Self> {
+        let mut summary = None;
+        let mut nodes = vec![];
+
+        for record in crate::parse::records(input) {
+            let record = record?;
+
+            match record.section() {
+                "clusterSummary" => summary = Some(record),
+                "clusterNode" => nodes.push(Node::from_record(&record)?),
+                _ => {}
+            }
+        }
+
+        let summary = summary
+            .ok_or_else(|| Error::NotFound("mmlscluster summary".into()))?;
+
+        Ok(Self {
+            name: summary.get("clusterName")?.into(),
+            id: summary.get("clusterId")?.into(),
+            uid_domain: summary.get("uidDomain")?.into(),
+            rsh_path: summary.get("rshPath")?.into(),
+            rcp_path: summary.get("rcpPath")?.into(),
+            repository_type: summary.get("repositoryType")?.into(),
+            nodes,
+        })
+    }
+}
+
+/// Cluster node.
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Node {
+    number: u64,
+    daemon_name: String,
+    ip: String,
+    admin_name: String,
+    roles: Vec<Role>,
+}
+
+impl Node {
+    /// Returns the node number.
+    #[must_use]
+    pub const fn number(&self) -> u64 {
+        self.number
+    }
+
+    /// Returns the name used for daemon communication.
+    #[must_use]
+    pub fn daemon_name(&self) -> &str {
+        &self.daemon_name
+    }
+
+    /// Returns the IP address used for daemon communication.
+    #[must_use]
+    pub fn ip(&self) -> &str {
+        &self.ip
+    }
+
+    /// Returns the name used for administration commands.
+    #[must_use]
+    pub fn admin_name(&self) -> &str {
+        &self.admin_name
+    }
+
+    /// Returns the roles, i.e. the designation and other node roles.
+    #[must_use]
+    pub fn roles(&self) -> &[Role] {
+        &self.roles
+    }
+
+    /// Returns `true` if the node has `role`.
+    #[must_use]
+    pub fn has_role(&self, role: &Role) -> bool {
+        self.roles.contains(role)
+    }
+
+    /// Returns `true` if the node is a quorum node.
+    #[must_use]
+    pub fn is_quorum(&self) -> bool {
+        self.has_role(&Role::Quorum)
+    }
+
+    /// Returns `true` if the node is a manager node.
+    #[must_use]
+    pub fn is_manager(&self) -> bool {
+        self.has_role(&Role::Manager)
+    }
+
+    /// Returns `true` if the node is a quorum and a manager node, i.e. if it
+    /// can be the cluster manager.
+    #[must_use]
+    pub fn is_quorum_manager(&self) -> bool {
+        self.is_quorum() && self.is_manager()
+    }
+
+    /// Returns `true` if `name` is the daemon or admin node name, or their
+    /// short name.
+    #[must_use]
+    pub fn is_named(&self, name: &str) -> bool {
+        [&self.daemon_name, &self.admin_name]
+            .into_iter()
+            .any(|full| {
+                full == name
+                    || full
+                        .split_once('.')
+                        .is_some_and(|(short, _)| short == name)
+            })
+    }
+}
+
+/// Node designation or other node role.
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+#[non_exhaustive]
+pub enum Role {
+    /// Quorum node.
+    Quorum,
+
+    /// Manager node, i.e. a candidate for cluster or file system manager.
+    Manager,
+
+    /// Performance monitoring collector node.
+    Perfmon,
+
+    /// AFM gateway node.
+    Gateway,
+
+    /// Unknown role.
+    Unknown(String),
+}
+
+impl Role {
+    /// Parses the `designation` field, e.g. `quorumManager`.
+    fn from_designation(s: &str) -> Vec<Self> {
+        let s = s.to_lowercase();
+
+        let mut roles = vec![];
+
+        if s.contains("quorum") {
+            roles.push(Self::Quorum);
+        }
+
+        if s.contains("manager") {
+            roles.push(Self::Manager);
+        }
+
+        roles
+    }
+}
+
+impl Display for Role {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            Self::Quorum => "quorum",
+            Self::Manager => "manager",
+            Self::Perfmon => "perfmon",
+            Self::Gateway => "gateway",
+            Self::Unknown(s) => s.as_str(),
+        };
+
+        write!(f, "{s}")
+    }
+}
+
+/// Parses role names, with or without the `Node` suffix of the
+/// `otherNodeRoles` field, e.g. `perfmonNode`.
+impl FromStr for Role {
+    type Err = crate::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.strip_suffix("Node").unwrap_or(s) {
+            "quorum" => Ok(Self::Quorum),
+            "manager" => Ok(Self::Manager),
+            "perfmon" => Ok(Self::Perfmon),
+            "gateway" => Ok(Self::Gateway),
+            _ => Ok(Self::Unknown(s.into())),
+        }
+    }
+}
+
+serde_via_str!(Role);
+
+// ----------------------------------------------------------------------------
+// boiler-platy parsing
+// ----------------------------------------------------------------------------
+
+impl FromRecord for Node {
+    fn from_record(record: &Record) -> Result<Self> {
+        let number = record.parse("nodeNumber")?;
+        let daemon_name = record.get("daemonNodeName")?.into();
+        let ip = record.get("ipAddress")?.into();
+        let admin_name = record.get("adminNodeName")?.into();
+
+        let mut roles = Role::from_designation(record.get("designation")?);
+
+        for role in record.get("otherNodeRoles")?.split(',') {
+            if !role.is_empty() {
+                roles.push(role.parse()?);
+            }
+        }
+
+        Ok(Self {
+            number,
+            daemon_name,
+            ip,
+            admin_name,
+            roles,
+        })
+    }
+}
+
+// ----------------------------------------------------------------------------
+// tests
+// ----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        let input = include_str!("cluster-example.in");
+
+        let cluster = Cluster::from_reader(input.as_bytes()).unwrap();
+
+        assert_eq!(cluster.name(), "storage.example.org");
+        assert_eq!(cluster.id(), "17093874537593845963");
+        assert_eq!(cluster.uid_domain(), "example.org");
+        assert_eq!(cluster.rsh_path(), "/usr/bin/ssh");
+        assert_eq!(cluster.rcp_path(), "/usr/bin/scp");
+        assert_eq!(cluster.repository_type(), "CCR");
+        assert_eq!(cluster.nodes().len(), 4);
+
+        assert_eq!(
+            cluster.nodes()[0],
+            Node {
+                number: 1,
+                daemon_name: "filer1.example.org".into(),
+                ip: "10.0.0.1".into(),
+                admin_name: "filer1-adm.example.org".into(),
+                roles: vec![Role::Quorum, Role::Manager, Role::Perfmon],
+            }
+        );
+
+        assert_eq!(
+            cluster.nodes()[2].roles(),
+            [Role::Quorum, Role::Gateway, Role::Perfmon]
+        );
+
+        assert_eq!(
+            cluster.nodes()[3].roles(),
+            [Role::Unknown("cesNode".into())]
+        );
+    }
+
+    #[test]
+    fn roles() {
+        let input = include_str!("cluster-example.in");
+
+        let cluster = Cluster::from_reader(input.as_bytes()).unwrap();
+
+        let quorum_managers = cluster
+            .nodes()
+            .iter()
+            .filter(|node| node.is_quorum_manager())
+            .map(Node::number)
+            .collect::<Vec<_>>();
+        assert_eq!(quorum_managers, [1, 2]);
+
+        let perfmon = cluster
+            .nodes_with(Role::Perfmon)
+            .map(Node::number)
+            .collect::<Vec<_>>();
+        assert_eq!(perfmon, [1, 3]);
+
+        assert_eq!(cluster.node("filer2").map(Node::number), Some(2));
+        assert_eq!(cluster.node("filer3-adm").map(Node::number), Some(3));
+        assert_eq!(
+            cluster.node("node001.example.org").map(Node::number),
+            Some(4)
+        );
+        assert_eq!(cluster.node("filer"), None);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde() {
+        for (value, name) in [
+            (Role::Quorum, "quorum"),
+            (Role::Perfmon, "perfmon"),
+            (Role::Unknown("fooNode".into()), "fooNode"),
+        ] {
+            assert_eq!(value.to_string(), name);
+
+            let json = serde_json::to_string(&value).unwrap();
+            assert_eq!(json, format!("\"{name}\""));
+
+            let parsed: Role = serde_json::from_str(&json).unwrap();
+            assert_eq!(parsed, value);
+        }
+    }
+}
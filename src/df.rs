@@ -2,32 +2,55 @@
 
 use std::collections::HashMap;
 use std::hash::BuildHasher;
-use std::io::{BufRead, Write};
-use std::process::Command;
+use std::io::BufRead;
 
-use anyhow::{Context, Result, anyhow};
-
-use crate::prom::ToText;
+use crate::parse::{FromRecord, Record};
+use crate::prom::{Family, ToMetrics, kb_to_bytes};
+use crate::run::{Cmd, Runner};
 use crate::util::MMBool;
+use crate::{Error, Result};
 
 /// Runs `mmdf` on all file systems.
 ///
 /// # Errors
 ///
-/// Returns an error if running any `mm*` command fails, if parsing their
-/// output fails, or if writing to `output` fails.
-pub fn run() -> Result<Data> {
+/// Returns an error if running any `mm*` command fails or if parsing their
+/// output fails.
+pub fn run(runner: &dyn Runner) -> Result<Data> {
+    let (data, errors) = run_partial(runner)?;
+
+    errors.into_iter().next().map_or(Ok(data), Err)
+}
+
+/// Runs `mmdf` on all file systems, skipping the ones where it fails.
+///
+/// Returns the data of all file systems where `mmdf` succeeded along with the
+/// errors of all file systems where it failed.
+///
+/// # Errors
+///
+/// Returns an error if listing the file systems fails.
+pub fn run_partial(runner: &dyn Runner) -> Result<(Data, Vec<Error>)> {
     let mut all_nsds: HashMap<String, Vec<Nsd>> = HashMap::default();
     let mut all_pools: HashMap<String, Vec<Pool>> = HashMap::default();
     let mut all_totals: HashMap<String, Filesystem> = HashMap::default();
+    let mut errors = vec![];
+
+    for fs in crate::fs::names(runner)? {
+        let summary = match run_one(runner, &fs) {
+            Ok(summary) => summary,
+            Err(error) => {
+                errors.push(error);
+                continue;
+            }
+        };
 
-    for fs in crate::fs::names()? {
         let FsSummary {
             fs,
             nsds,
             pools,
             total,
-        } = run_one(&fs)?;
+        } = summary;
 
         all_nsds.insert(fs.clone(), nsds);
         all_pools.insert(fs.clone(), pools);
@@ -40,17 +63,13 @@ pub fn run() -> Result<Data> {
         totals: all_totals,
     };
 
-    Ok(data)
+    Ok((data, errors))
 }
 
-fn run_one(fs: &str) -> Result<FsSummary> {
-    let mut cmd = Command::new("mmdf");
-    cmd.arg(fs);
-    cmd.arg("-Y");
+fn run_one(runner: &dyn Runner, fs: &str) -> Result<FsSummary> {
+    let cmd = Cmd::new("mmdf").arg(fs).arg("-Y");
 
-    let output = cmd
-        .output()
-        .with_context(|| format!("error running: {cmd:?}"))?;
+    let output = runner.run_checked(&cmd)?;
 
     let data = FsSummary::from_reader(fs, output.stdout.as_slice())?;
 
@@ -58,19 +77,40 @@ fn run_one(fs: &str) -> Result<FsSummary> {
 }
 
 /// Summed up data.
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Data {
     nsds: HashMap<String, Vec<Nsd>>,
     pools: HashMap<String, Vec<Pool>>,
     totals: HashMap<String, Filesystem>,
 }
 
-impl ToText for Data {
-    fn to_prom(&self, output: &mut impl Write) -> Result<()> {
-        self.nsds.to_prom(output)?;
-        self.pools.to_prom(output)?;
-        self.totals.to_prom(output)?;
+impl Data {
+    /// Returns the NSDs by file system name.
+    #[must_use]
+    pub const fn nsds(&self) -> &HashMap<String, Vec<Nsd>> {
+        &self.nsds
+    }
 
-        Ok(())
+    /// Returns the pools by file system name.
+    #[must_use]
+    pub const fn pools(&self) -> &HashMap<String, Vec<Pool>> {
+        &self.pools
+    }
+
+    /// Returns the totals by file system name.
+    #[must_use]
+    pub const fn totals(&self) -> &HashMap<String, Filesystem> {
+        &self.totals
+    }
+}
+
+impl ToMetrics for Data {
+    fn to_metrics(&self) -> Vec<Family> {
+        let mut families = self.nsds.to_metrics();
+        families.extend(self.pools.to_metrics());
+        families.extend(self.totals.to_metrics());
+        families
     }
 }
 
@@ -92,8 +132,10 @@ impl FsSummary {
     }
 }
 
-#[derive(Debug, Default, PartialEq)]
-struct Filesystem {
+/// File system totals.
+#[derive(Clone, Copy, Eq, PartialEq, Hash, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Filesystem {
     size: u64,
     free_blocks: u64,
     free_blocks_percent: u64,
@@ -101,88 +143,107 @@ struct Filesystem {
     free_fragments_percent: u64,
 }
 
-impl<S: BuildHasher> ToText for HashMap<String, Filesystem, S> {
-    fn to_prom(&self, output: &mut impl Write) -> Result<()> {
-        writeln!(
-            output,
-            "# HELP gpfs_df_fs_size GPFS mmdf pool size in kilobytes"
-        )?;
-        writeln!(output, "# TYPE gpfs_df_fs_size gauge")?;
-
-        for (fs_name, fs) in self {
-            writeln!(
-                output,
-                "gpfs_df_fs_size{{name=\"{fs_name}\"}} {}",
-                fs.size,
-            )?;
-        }
-
-        writeln!(
-            output,
-            "# HELP gpfs_df_fs_free_blocks GPFS mmdf pool free blocks in kilobytes"
-        )?;
-
-        writeln!(output, "# TYPE gpfs_df_fs_free_blocks gauge")?;
-
-        for (fs_name, fs) in self {
-            writeln!(
-                output,
-                "gpfs_df_fs_free_blocks{{name=\"{fs_name}\"}} {}",
-                fs.free_blocks,
-            )?;
-        }
-
-        writeln!(
-            output,
-            "# HELP gpfs_df_fs_free_blocks_percent GPFS mmdf pool free blocks percent"
-        )?;
-
-        writeln!(output, "# TYPE gpfs_df_fs_free_blocks_percent gauge")?;
-
-        for (fs_name, fs) in self {
-            writeln!(
-                output,
-                "gpfs_df_fs_free_blocks_percent{{name=\"{fs_name}\"}} {}",
-                fs.free_blocks_percent,
-            )?;
-        }
+impl Filesystem {
+    /// Returns the size in kilobytes.
+    #[must_use]
+    pub const fn size(&self) -> u64 {
+        self.size
+    }
 
-        writeln!(
-            output,
-            "# HELP gpfs_df_fs_free_fragments GPFS mmdf pool free fragments in kilobytes"
-        )?;
+    /// Returns the free blocks in kilobytes.
+    #[must_use]
+    pub const fn free_blocks(&self) -> u64 {
+        self.free_blocks
+    }
 
-        writeln!(output, "# TYPE gpfs_df_fs_free_fragments gauge")?;
+    /// Returns the free blocks in percent.
+    #[must_use]
+    pub const fn free_blocks_percent(&self) -> u64 {
+        self.free_blocks_percent
+    }
 
-        for (fs_name, fs) in self {
-            writeln!(
-                output,
-                "gpfs_df_fs_free_fragments{{name=\"{fs_name}\"}} {}",
-                fs.free_fragments,
-            )?;
-        }
+    /// Returns the free fragments in kilobytes.
+    #[must_use]
+    pub const fn free_fragments(&self) -> u64 {
+        self.free_fragments
+    }
 
-        writeln!(
-            output,
-            "# HELP gpfs_df_fs_free_fragments_percent GPFS mmdf pool free fragments percent"
-        )?;
+    /// Returns the free fragments in percent.
+    #[must_use]
+    pub const fn free_fragments_percent(&self) -> u64 {
+        self.free_fragments_percent
+    }
+}
 
-        writeln!(output, "# TYPE gpfs_df_fs_free_fragments_percent gauge")?;
+impl<S: BuildHasher> ToMetrics for HashMap<String, Filesystem, S> {
+    fn to_metrics(&self) -> Vec<Family> {
+        let mut size = Family::gauge(
+            "gpfs_df_fs_size",
+            "GPFS mmdf pool size in kilobytes",
+        );
+        let mut free_blocks = Family::gauge(
+            "gpfs_df_fs_free_blocks",
+            "GPFS mmdf pool free blocks in kilobytes",
+        );
+        let mut free_blocks_percent = Family::gauge(
+            "gpfs_df_fs_free_blocks_percent",
+            "GPFS mmdf pool free blocks percent",
+        );
+        let mut free_fragments = Family::gauge(
+            "gpfs_df_fs_free_fragments",
+            "GPFS mmdf pool free fragments in kilobytes",
+        );
+        let mut free_fragments_percent = Family::gauge(
+            "gpfs_df_fs_free_fragments_percent",
+            "GPFS mmdf pool free fragments percent",
+        );
+        let mut size_bytes = Family::gauge(
+            "gpfs_df_fs_size_bytes",
+            "GPFS mmdf pool size in bytes",
+        )
+        .with_unit("bytes");
+        let mut free_blocks_bytes = Family::gauge(
+            "gpfs_df_fs_free_blocks_bytes",
+            "GPFS mmdf pool free blocks in bytes",
+        )
+        .with_unit("bytes");
+        let mut free_fragments_bytes = Family::gauge(
+            "gpfs_df_fs_free_fragments_bytes",
+            "GPFS mmdf pool free fragments in bytes",
+        )
+        .with_unit("bytes");
 
         for (fs_name, fs) in self {
-            writeln!(
-                output,
-                "gpfs_df_fs_free_fragments_percent{{name=\"{fs_name}\"}} {}",
-                fs.free_fragments_percent,
-            )?;
+            let labels = [("name", fs_name.as_str())];
+
+            size.sample(&labels, fs.size);
+            free_blocks.sample(&labels, fs.free_blocks);
+            free_blocks_percent.sample(&labels, fs.free_blocks_percent);
+            free_fragments.sample(&labels, fs.free_fragments);
+            free_fragments_percent.sample(&labels, fs.free_fragments_percent);
+            size_bytes.sample(&labels, kb_to_bytes(fs.size));
+            free_blocks_bytes.sample(&labels, kb_to_bytes(fs.free_blocks));
+            free_fragments_bytes
+                .sample(&labels, kb_to_bytes(fs.free_fragments));
         }
 
-        Ok(())
+        vec![
+            size,
+            free_blocks,
+            free_blocks_percent,
+            free_fragments,
+            free_fragments_percent,
+            size_bytes,
+            free_blocks_bytes,
+            free_fragments_bytes,
+        ]
     }
 }
 
-#[derive(Debug, PartialEq)]
-struct Pool {
+/// Pool data.
+#[derive(Clone, Eq, PartialEq, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Pool {
     name: String,
     size: u64,
     free_blocks: u64,
@@ -191,99 +252,117 @@ struct Pool {
     free_fragments_percent: u64,
 }
 
-impl<S: BuildHasher> ToText for HashMap<String, Vec<Pool>, S> {
-    fn to_prom(&self, output: &mut impl Write) -> Result<()> {
-        writeln!(
-            output,
-            "# HELP gpfs_df_pool_size GPFS mmdf pool size in kilobytes"
-        )?;
-
-        writeln!(output, "# TYPE gpfs_df_pool_size gauge")?;
-
-        for (fs, pools) in self {
-            for pool in pools {
-                writeln!(
-                    output,
-                    "gpfs_df_pool_size{{name=\"{}\",fs=\"{fs}\"}} {}",
-                    pool.name, pool.size,
-                )?;
-            }
-        }
-
-        writeln!(
-            output,
-            "# HELP gpfs_df_pool_free_blocks GPFS mmdf pool free blocks in kilobytes"
-        )?;
-
-        writeln!(output, "# TYPE gpfs_df_pool_free_blocks gauge")?;
-
-        for (fs, pools) in self {
-            for pool in pools {
-                writeln!(
-                    output,
-                    "gpfs_df_pool_free_blocks{{name=\"{}\",fs=\"{fs}\"}} {}",
-                    pool.name, pool.free_blocks,
-                )?;
-            }
-        }
-
-        writeln!(
-            output,
-            "# HELP gpfs_df_pool_free_blocks_percent GPFS mmdf pool free blocks percent"
-        )?;
-
-        writeln!(output, "# TYPE gpfs_df_pool_free_blocks_percent gauge")?;
+impl Pool {
+    /// Returns the pool name.
+    #[must_use]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
 
-        for (fs, pools) in self {
-            for pool in pools {
-                writeln!(
-                    output,
-                    "gpfs_df_pool_free_blocks_percent{{name=\"{}\",fs=\"{fs}\"}} {}",
-                    pool.name, pool.free_blocks_percent,
-                )?;
-            }
-        }
+    /// Returns the size in kilobytes.
+    #[must_use]
+    pub const fn size(&self) -> u64 {
+        self.size
+    }
 
-        writeln!(
-            output,
-            "# HELP gpfs_df_pool_free_fragments GPFS mmdf pool free fragments in kilobytes"
-        )?;
+    /// Returns the free blocks in kilobytes.
+    #[must_use]
+    pub const fn free_blocks(&self) -> u64 {
+        self.free_blocks
+    }
 
-        writeln!(output, "# TYPE gpfs_df_pool_free_fragments gauge")?;
+    /// Returns the free blocks in percent.
+    #[must_use]
+    pub const fn free_blocks_percent(&self) -> u64 {
+        self.free_blocks_percent
+    }
 
-        for (fs, pools) in self {
-            for pool in pools {
-                writeln!(
-                    output,
-                    "gpfs_df_pool_free_fragments{{name=\"{}\",fs=\"{fs}\"}} {}",
-                    pool.name, pool.free_fragments,
-                )?;
-            }
-        }
+    /// Returns the free fragments in kilobytes.
+    #[must_use]
+    pub const fn free_fragments(&self) -> u64 {
+        self.free_fragments
+    }
 
-        writeln!(
-            output,
-            "# HELP gpfs_df_pool_free_fragments_percent GPFS mmdf pool free fragments percent"
-        )?;
+    /// Returns the free fragments in percent.
+    #[must_use]
+    pub const fn free_fragments_percent(&self) -> u64 {
+        self.free_fragments_percent
+    }
+}
 
-        writeln!(output, "# TYPE gpfs_df_pool_free_fragments_percent gauge")?;
+impl<S: BuildHasher> ToMetrics for HashMap<String, Vec<Pool>, S> {
+    fn to_metrics(&self) -> Vec<Family> {
+        let mut size = Family::gauge(
+            "gpfs_df_pool_size",
+            "GPFS mmdf pool size in kilobytes",
+        );
+        let mut free_blocks = Family::gauge(
+            "gpfs_df_pool_free_blocks",
+            "GPFS mmdf pool free blocks in kilobytes",
+        );
+        let mut free_blocks_percent = Family::gauge(
+            "gpfs_df_pool_free_blocks_percent",
+            "GPFS mmdf pool free blocks percent",
+        );
+        let mut free_fragments = Family::gauge(
+            "gpfs_df_pool_free_fragments",
+            "GPFS mmdf pool free fragments in kilobytes",
+        );
+        let mut free_fragments_percent = Family::gauge(
+            "gpfs_df_pool_free_fragments_percent",
+            "GPFS mmdf pool free fragments percent",
+        );
+        let mut size_bytes = Family::gauge(
+            "gpfs_df_pool_size_bytes",
+            "GPFS mmdf pool size in bytes",
+        )
+        .with_unit("bytes");
+        let mut free_blocks_bytes = Family::gauge(
+            "gpfs_df_pool_free_blocks_bytes",
+            "GPFS mmdf pool free blocks in bytes",
+        )
+        .with_unit("bytes");
+        let mut free_fragments_bytes = Family::gauge(
+            "gpfs_df_pool_free_fragments_bytes",
+            "GPFS mmdf pool free fragments in bytes",
+        )
+        .with_unit("bytes");
 
         for (fs, pools) in self {
             for pool in pools {
-                writeln!(
-                    output,
-                    "gpfs_df_pool_free_fragments_percent{{name=\"{}\",fs=\"{fs}\"}} {}",
-                    pool.name, pool.free_fragments_percent,
-                )?;
+                let labels = [("name", pool.name.as_str()), ("fs", fs)];
+
+                size.sample(&labels, pool.size);
+                free_blocks.sample(&labels, pool.free_blocks);
+                free_blocks_percent.sample(&labels, pool.free_blocks_percent);
+                free_fragments.sample(&labels, pool.free_fragments);
+                free_fragments_percent
+                    .sample(&labels, pool.free_fragments_percent);
+                size_bytes.sample(&labels, kb_to_bytes(pool.size));
+                free_blocks_bytes
+                    .sample(&labels, kb_to_bytes(pool.free_blocks));
+                free_fragments_bytes
+                    .sample(&labels, kb_to_bytes(pool.free_fragments));
             }
         }
 
-        Ok(())
+        vec![
+            size,
+            free_blocks,
+            free_blocks_percent,
+            free_fragments,
+            free_fragments_percent,
+            size_bytes,
+            free_blocks_bytes,
+            free_fragments_bytes,
+        ]
     }
 }
 
-#[derive(Debug, PartialEq)]
-struct Nsd {
+/// NSD data.
+#[derive(Clone, Eq, PartialEq, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Nsd {
     name: String,
     pool: String,
     size: u64,
@@ -295,114 +374,137 @@ struct Nsd {
     free_fragments_percent: u64,
 }
 
-impl<S: BuildHasher> ToText for HashMap<String, Vec<Nsd>, S> {
-    fn to_prom(&self, output: &mut impl Write) -> Result<()> {
-        writeln!(
-            output,
-            "# HELP gpfs_df_nsd_size GPFS mmdf NSD size in kilobytes"
-        )?;
-
-        writeln!(output, "# TYPE gpfs_df_nsd_size gauge")?;
-
-        for (fs, nsds) in self {
-            for nsd in nsds {
-                writeln!(
-                    output,
-                    "gpfs_df_nsd_size{{name=\"{}\",fs=\"{fs}\",pool=\"{}\",metadata=\"{}\",data=\"{}\"}} {}",
-                    nsd.name,
-                    nsd.pool,
-                    nsd.holds_metadata,
-                    nsd.holds_objectdata,
-                    nsd.size,
-                )?;
-            }
-        }
-
-        writeln!(
-            output,
-            "# HELP gpfs_df_nsd_free_blocks GPFS mmdf NSD free blocks in kilobytes"
-        )?;
-
-        writeln!(output, "# TYPE gpfs_df_nsd_free_blocks gauge")?;
+impl Nsd {
+    /// Returns the NSD name.
+    #[must_use]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
 
-        for (fs, nsds) in self {
-            for nsd in nsds {
-                writeln!(
-                    output,
-                    "gpfs_df_nsd_free_blocks{{name=\"{}\",fs=\"{fs}\",pool=\"{}\",metadata=\"{}\",data=\"{}\"}} {}",
-                    nsd.name,
-                    nsd.pool,
-                    nsd.holds_metadata,
-                    nsd.holds_objectdata,
-                    nsd.free_blocks,
-                )?;
-            }
-        }
+    /// Returns the pool name.
+    #[must_use]
+    pub fn pool(&self) -> &str {
+        &self.pool
+    }
 
-        writeln!(
-            output,
-            "# HELP gpfs_df_nsd_free_blocks_percent GPFS mmdf NSD free blocks percent"
-        )?;
+    /// Returns the size in kilobytes.
+    #[must_use]
+    pub const fn size(&self) -> u64 {
+        self.size
+    }
 
-        writeln!(output, "# TYPE gpfs_df_nsd_free_blocks_percent gauge")?;
+    /// Returns if this NSD holds metadata.
+    #[must_use]
+    pub const fn holds_metadata(&self) -> bool {
+        self.holds_metadata
+    }
 
-        for (fs, nsds) in self {
-            for nsd in nsds {
-                writeln!(
-                    output,
-                    "gpfs_df_nsd_free_blocks_percent{{name=\"{}\",fs=\"{fs}\",pool=\"{}\",metadata=\"{}\",data=\"{}\"}} {}",
-                    nsd.name,
-                    nsd.pool,
-                    nsd.holds_metadata,
-                    nsd.holds_objectdata,
-                    nsd.free_blocks_percent,
-                )?;
-            }
-        }
+    /// Returns if this NSD holds data.
+    #[must_use]
+    pub const fn holds_objectdata(&self) -> bool {
+        self.holds_objectdata
+    }
 
-        writeln!(
-            output,
-            "# HELP gpfs_df_nsd_free_fragments GPFS mmdf NSD free fragments in kilobytes"
-        )?;
+    /// Returns the free blocks in kilobytes.
+    #[must_use]
+    pub const fn free_blocks(&self) -> u64 {
+        self.free_blocks
+    }
 
-        writeln!(output, "# TYPE gpfs_df_nsd_free_fragments gauge")?;
+    /// Returns the free blocks in percent.
+    #[must_use]
+    pub const fn free_blocks_percent(&self) -> u64 {
+        self.free_blocks_percent
+    }
 
-        for (fs, nsds) in self {
-            for nsd in nsds {
-                writeln!(
-                    output,
-                    "gpfs_df_nsd_free_fragments{{name=\"{}\",fs=\"{fs}\",pool=\"{}\",metadata=\"{}\",data=\"{}\"}} {}",
-                    nsd.name,
-                    nsd.pool,
-                    nsd.holds_metadata,
-                    nsd.holds_objectdata,
-                    nsd.free_fragments,
-                )?;
-            }
-        }
+    /// Returns the free fragments in kilobytes.
+    #[must_use]
+    pub const fn free_fragments(&self) -> u64 {
+        self.free_fragments
+    }
 
-        writeln!(
-            output,
-            "# HELP gpfs_df_nsd_free_fragments_percent GPFS mmdf NSD free fragments percent"
-        )?;
+    /// Returns the free fragments in percent.
+    #[must_use]
+    pub const fn free_fragments_percent(&self) -> u64 {
+        self.free_fragments_percent
+    }
+}
 
-        writeln!(output, "# TYPE gpfs_df_nsd_free_fragments_percent gauge")?;
+impl<S: BuildHasher> ToMetrics for HashMap<String, Vec<Nsd>, S> {
+    fn to_metrics(&self) -> Vec<Family> {
+        let mut size = Family::gauge(
+            "gpfs_df_nsd_size",
+            "GPFS mmdf NSD size in kilobytes",
+        );
+        let mut free_blocks = Family::gauge(
+            "gpfs_df_nsd_free_blocks",
+            "GPFS mmdf NSD free blocks in kilobytes",
+        );
+        let mut free_blocks_percent = Family::gauge(
+            "gpfs_df_nsd_free_blocks_percent",
+            "GPFS mmdf NSD free blocks percent",
+        );
+        let mut free_fragments = Family::gauge(
+            "gpfs_df_nsd_free_fragments",
+            "GPFS mmdf NSD free fragments in kilobytes",
+        );
+        let mut free_fragments_percent = Family::gauge(
+            "gpfs_df_nsd_free_fragments_percent",
+            "GPFS mmdf NSD free fragments percent",
+        );
+        let mut size_bytes = Family::gauge(
+            "gpfs_df_nsd_size_bytes",
+            "GPFS mmdf NSD size in bytes",
+        )
+        .with_unit("bytes");
+        let mut free_blocks_bytes = Family::gauge(
+            "gpfs_df_nsd_free_blocks_bytes",
+            "GPFS mmdf NSD free blocks in bytes",
+        )
+        .with_unit("bytes");
+        let mut free_fragments_bytes = Family::gauge(
+            "gpfs_df_nsd_free_fragments_bytes",
+            "GPFS mmdf NSD free fragments in bytes",
+        )
+        .with_unit("bytes");
 
         for (fs, nsds) in self {
             for nsd in nsds {
-                writeln!(
-                    output,
-                    "gpfs_df_nsd_free_fragments_percent{{name=\"{}\",fs=\"{fs}\",pool=\"{}\",metadata=\"{}\",data=\"{}\"}} {}",
-                    nsd.name,
-                    nsd.pool,
-                    nsd.holds_metadata,
-                    nsd.holds_objectdata,
-                    nsd.free_fragments_percent,
-                )?;
+                let metadata = nsd.holds_metadata.to_string();
+                let data = nsd.holds_objectdata.to_string();
+
+                let labels = [
+                    ("name", nsd.name.as_str()),
+                    ("fs", fs),
+                    ("pool", &nsd.pool),
+                    ("metadata", &metadata),
+                    ("data", &data),
+                ];
+
+                size.sample(&labels, nsd.size);
+                free_blocks.sample(&labels, nsd.free_blocks);
+                free_blocks_percent.sample(&labels, nsd.free_blocks_percent);
+                free_fragments.sample(&labels, nsd.free_fragments);
+                free_fragments_percent
+                    .sample(&labels, nsd.free_fragments_percent);
+                size_bytes.sample(&labels, kb_to_bytes(nsd.size));
+                free_blocks_bytes
+                    .sample(&labels, kb_to_bytes(nsd.free_blocks));
+                free_fragments_bytes
+                    .sample(&labels, kb_to_bytes(nsd.free_fragments));
             }
         }
 
-        Ok(())
+        vec![
+            size,
+            free_blocks,
+            free_blocks_percent,
+            free_fragments,
+            free_fragments_percent,
+            size_bytes,
+            free_blocks_bytes,
+            free_fragments_bytes,
+        ]
     }
 }
 
@@ -412,320 +514,60 @@ impl<S: BuildHasher> ToText for HashMap<String, Vec<Nsd>, S> {
 
 impl FsSummary {
     fn from_reader<Input: BufRead>(fs: &str, input: Input) -> Result<Self> {
-        let mut fs_index = FilesystemIndex::default();
-        let mut nsd_index = NsdIndex::default();
-        let mut pool_index = PoolIndex::default();
-
         let mut data = Self::new(fs);
 
-        for line in input.lines() {
-            let line = line?;
-
-            let tokens = line.split(':').collect::<Vec<_>>();
-
-            if tokens[1] == "nsd" {
-                if tokens[2] == "HEADER" {
-                    nsd_index.with_tokens(&tokens);
-                } else {
-                    let entry = Nsd::from_tokens(&tokens, &nsd_index)?;
-                    data.nsds.push(entry);
-                }
-            } else if tokens[1] == "poolTotal" {
-                if tokens[2] == "HEADER" {
-                    pool_index.with_tokens(&tokens);
-                } else {
-                    let entry = Pool::from_tokens(&tokens, &pool_index)?;
-                    data.pools.push(entry);
-                }
-            } else if tokens[1] == "fsTotal" {
-                if tokens[2] == "HEADER" {
-                    fs_index.with_tokens(&tokens);
-                } else {
-                    let entry = Filesystem::from_tokens(&tokens, &fs_index)?;
-                    data.total = entry;
-                }
-            }
-        }
+        for record in crate::parse::records(input) {
+            let record = record?;
 
-        Ok(data)
-    }
-}
-
-#[derive(Default)]
-struct FilesystemIndex {
-    size: Option<usize>,
-    free_blocks: Option<usize>,
-    free_blocks_percent: Option<usize>,
-    free_fragments: Option<usize>,
-    free_fragments_percent: Option<usize>,
-}
-
-impl FilesystemIndex {
-    fn with_tokens(&mut self, tokens: &[&str]) {
-        for (i, token) in tokens.iter().enumerate() {
-            match *token {
-                "fsSize" => self.size = Some(i),
-                "freeBlocks" => self.free_blocks = Some(i),
-                "freeBlocksPct" => self.free_blocks_percent = Some(i),
-                "freeFragments" => self.free_fragments = Some(i),
-                "freeFragmentsPct" => self.free_fragments_percent = Some(i),
-                _ => {}
-            }
-        }
-    }
-}
-
-#[derive(Default)]
-struct NsdIndex {
-    name: Option<usize>,
-    pool: Option<usize>,
-    size: Option<usize>,
-    metadata: Option<usize>,
-    data: Option<usize>,
-    free_blocks: Option<usize>,
-    free_blocks_percent: Option<usize>,
-    free_fragments: Option<usize>,
-    free_fragments_percent: Option<usize>,
-}
-
-impl NsdIndex {
-    fn with_tokens(&mut self, tokens: &[&str]) {
-        for (i, token) in tokens.iter().enumerate() {
-            match *token {
-                "nsdName" => self.name = Some(i),
-                "storagePool" => self.pool = Some(i),
-                "diskSize" => self.size = Some(i),
-                "metadata" => self.metadata = Some(i),
-                "data" => self.data = Some(i),
-                "freeBlocks" => self.free_blocks = Some(i),
-                "freeBlocksPct" => self.free_blocks_percent = Some(i),
-                "freeFragments" => self.free_fragments = Some(i),
-                "freeFragmentsPct" => self.free_fragments_percent = Some(i),
+            match record.section() {
+                "nsd" => data.nsds.push(Nsd::from_record(&record)?),
+                "poolTotal" => data.pools.push(Pool::from_record(&record)?),
+                "fsTotal" => data.total = Filesystem::from_record(&record)?,
                 _ => {}
             }
         }
-    }
-}
 
-#[derive(Default)]
-struct PoolIndex {
-    name: Option<usize>,
-    size: Option<usize>,
-    free_blocks: Option<usize>,
-    free_blocks_percent: Option<usize>,
-    free_fragments: Option<usize>,
-    free_fragments_percent: Option<usize>,
-}
-
-impl PoolIndex {
-    fn with_tokens(&mut self, tokens: &[&str]) {
-        for (i, token) in tokens.iter().enumerate() {
-            match *token {
-                "poolName" => self.name = Some(i),
-                "poolSize" => self.size = Some(i),
-                "freeBlocks" => self.free_blocks = Some(i),
-                "freeBlocksPct" => self.free_blocks_percent = Some(i),
-                "freeFragments" => self.free_fragments = Some(i),
-                "freeFragmentsPct" => self.free_fragments_percent = Some(i),
-                _ => {}
-            }
-        }
+        Ok(data)
     }
 }
 
-impl Filesystem {
-    fn from_tokens(tokens: &[&str], index: &FilesystemIndex) -> Result<Self> {
-        let size_index =
-            index.size.ok_or_else(|| anyhow!("no fsSize index"))?;
-        let size = tokens[size_index];
-        let size = size
-            .parse()
-            .with_context(|| format!("invalid fsSize field: {size}"))?;
-
-        let free_blocks_index = index
-            .free_blocks
-            .ok_or_else(|| anyhow!("no freeBlocks index"))?;
-        let free_blocks = tokens[free_blocks_index];
-        let free_blocks = free_blocks.parse().with_context(|| {
-            format!("invalid freeBlocks field: {free_blocks}")
-        })?;
-
-        let free_blocks_percent_index = index
-            .free_blocks_percent
-            .ok_or_else(|| anyhow!("no freeBlocksPct index"))?;
-        let free_blocks_percent = tokens[free_blocks_percent_index];
-        let free_blocks_percent =
-            free_blocks_percent.parse().with_context(|| {
-                format!("invalid freeBlocksPct field: {free_blocks_percent}")
-            })?;
-
-        let free_fragments_index = index
-            .free_fragments
-            .ok_or_else(|| anyhow!("no freeFragments index"))?;
-        let free_fragments = tokens[free_fragments_index];
-        let free_fragments = free_fragments.parse().with_context(|| {
-            format!("invalid freeFragments field: {free_fragments}")
-        })?;
-
-        let free_fragments_percent_index = index
-            .free_fragments_percent
-            .ok_or_else(|| anyhow!("no freeFragmentsPct index"))?;
-        let free_fragments_percent = tokens[free_fragments_percent_index];
-        let free_fragments_percent =
-            free_fragments_percent.parse().with_context(|| {
-                format!(
-                    "invalid freeFragmentsPct field: {free_fragments_percent}"
-                )
-            })?;
-
+impl FromRecord for Filesystem {
+    fn from_record(record: &Record) -> Result<Self> {
         Ok(Self {
-            size,
-            free_blocks,
-            free_blocks_percent,
-            free_fragments,
-            free_fragments_percent,
+            size: record.parse("fsSize")?,
+            free_blocks: record.parse("freeBlocks")?,
+            free_blocks_percent: record.parse("freeBlocksPct")?,
+            free_fragments: record.parse("freeFragments")?,
+            free_fragments_percent: record.parse("freeFragmentsPct")?,
         })
     }
 }
 
-impl Nsd {
-    fn from_tokens(tokens: &[&str], index: &NsdIndex) -> Result<Self> {
-        let name_index =
-            index.name.ok_or_else(|| anyhow!("no nsdName index"))?;
-        let name = tokens[name_index].into();
-
-        let storage_pool_index =
-            index.pool.ok_or_else(|| anyhow!("no storagePool index"))?;
-        let pool = tokens[storage_pool_index].into();
-
-        let disk_size_index =
-            index.size.ok_or_else(|| anyhow!("no diskSize index"))?;
-        let size = tokens[disk_size_index];
-        let size = size
-            .parse()
-            .with_context(|| format!("invalid diskSize field: {size}"))?;
-
-        let metadata_index =
-            index.metadata.ok_or_else(|| anyhow!("no metadata index"))?;
-        let metadata = tokens[metadata_index];
-        let metadata = metadata
-            .parse::<MMBool>()
-            .with_context(|| format!("invalid metadata field: {metadata}"))?
-            .as_bool();
-
-        let data_index = index.data.ok_or_else(|| anyhow!("no data index"))?;
-        let data = tokens[data_index];
-        let data = data
-            .parse::<MMBool>()
-            .with_context(|| format!("invalid data field: {data}"))?
-            .as_bool();
-
-        let free_blocks_index = index
-            .free_blocks
-            .ok_or_else(|| anyhow!("no freeBlocks index"))?;
-        let free_blocks = tokens[free_blocks_index];
-        let free_blocks = free_blocks.parse().with_context(|| {
-            format!("invalid freeBlocks field: {free_blocks}")
-        })?;
-
-        let free_blocks_percent_index = index
-            .free_blocks_percent
-            .ok_or_else(|| anyhow!("no freeBlocksPct index"))?;
-        let free_blocks_percent = tokens[free_blocks_percent_index];
-        let free_blocks_percent =
-            free_blocks_percent.parse().with_context(|| {
-                format!("invalid freeBlocksPct field: {free_blocks_percent}")
-            })?;
-
-        let free_fragments_index = index
-            .free_fragments
-            .ok_or_else(|| anyhow!("no freeFragments index"))?;
-        let free_fragments = tokens[free_fragments_index];
-        let free_fragments = free_fragments.parse().with_context(|| {
-            format!("invalid freeFragments field: {free_fragments}")
-        })?;
-
-        let free_fragments_percent_index = index
-            .free_fragments_percent
-            .ok_or_else(|| anyhow!("no freeFragmentsPct index"))?;
-        let free_fragments_percent = tokens[free_fragments_percent_index];
-        let free_fragments_percent =
-            free_fragments_percent.parse().with_context(|| {
-                format!(
-                    "invalid freeFragmentsPct field: {free_fragments_percent}"
-                )
-            })?;
-
+impl FromRecord for Nsd {
+    fn from_record(record: &Record) -> Result<Self> {
         Ok(Self {
-            name,
-            pool,
-            size,
-            holds_metadata: metadata,
-            holds_objectdata: data,
-            free_blocks,
-            free_blocks_percent,
-            free_fragments,
-            free_fragments_percent,
+            name: record.get("nsdName")?.into(),
+            pool: record.get("storagePool")?.into(),
+            size: record.parse("diskSize")?,
+            holds_metadata: record.parse::<MMBool>("metadata")?.as_bool(),
+            holds_objectdata: record.parse::<MMBool>("data")?.as_bool(),
+            free_blocks: record.parse("freeBlocks")?,
+            free_blocks_percent: record.parse("freeBlocksPct")?,
+            free_fragments: record.parse("freeFragments")?,
+            free_fragments_percent: record.parse("freeFragmentsPct")?,
         })
     }
 }
 
-impl Pool {
-    fn from_tokens(tokens: &[&str], index: &PoolIndex) -> Result<Self> {
-        let name_index =
-            index.name.ok_or_else(|| anyhow!("no poolName index"))?;
-        let name = tokens[name_index].into();
-
-        let size_index =
-            index.size.ok_or_else(|| anyhow!("no poolSize index"))?;
-        let size = tokens[size_index];
-        let size = size
-            .parse()
-            .with_context(|| format!("invalid poolSize field: {size}"))?;
-
-        let free_blocks_index = index
-            .free_blocks
-            .ok_or_else(|| anyhow!("no freeBlocks index"))?;
-        let free_blocks = tokens[free_blocks_index];
-        let free_blocks = free_blocks.parse().with_context(|| {
-            format!("invalid freeBlocks field: {free_blocks}")
-        })?;
-
-        let free_blocks_percent_index = index
-            .free_blocks_percent
-            .ok_or_else(|| anyhow!("no freeBlocksPct index"))?;
-        let free_blocks_percent = tokens[free_blocks_percent_index];
-        let free_blocks_percent =
-            free_blocks_percent.parse().with_context(|| {
-                format!("invalid freeBlocksPct field: {free_blocks_percent}")
-            })?;
-
-        let free_fragments_index = index
-            .free_fragments
-            .ok_or_else(|| anyhow!("no freeFragments index"))?;
-        let free_fragments = tokens[free_fragments_index];
-        let free_fragments = free_fragments.parse().with_context(|| {
-            format!("invalid freeFragments field: {free_fragments}")
-        })?;
-
-        let free_fragments_percent_index = index
-            .free_fragments_percent
-            .ok_or_else(|| anyhow!("no freeFragmentsPct index"))?;
-        let free_fragments_percent = tokens[free_fragments_percent_index];
-        let free_fragments_percent =
-            free_fragments_percent.parse().with_context(|| {
-                format!(
-                    "invalid freeFragmentsPct field: {free_fragments_percent}"
-                )
-            })?;
-
+impl FromRecord for Pool {
+    fn from_record(record: &Record) -> Result<Self> {
         Ok(Self {
-            name,
-            size,
-            free_blocks,
-            free_blocks_percent,
-            free_fragments,
-            free_fragments_percent,
+            name: record.get("poolName")?.into(),
+            size: record.parse("poolSize")?,
+            free_blocks: record.parse("freeBlocks")?,
+            free_blocks_percent: record.parse("freeBlocksPct")?,
+            free_fragments: record.parse("freeFragments")?,
+            free_fragments_percent: record.parse("freeFragmentsPct")?,
         })
     }
 }
@@ -737,6 +579,7 @@ impl Pool {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::run::{Output, Replay};
 
     #[test]
     fn parse() {
@@ -837,4 +680,39 @@ mod tests {
             }
         );
     }
+
+    #[test]
+    fn partial() {
+        let runner = Replay::new()
+            .with(
+                Cmd::new("mmlsfs").args(["all", "-Y", "-B"]),
+                "\
+mmlsfs::HEADER:version:reserved:reserved:deviceName:fieldName:data:remarks:
+mmlsfs::0:1:::gpfs1:blockSize:8388608::
+mmlsfs::0:1:::gpfs2:blockSize:8388608::
+",
+            )
+            .with(
+                Cmd::new("mmdf").args(["gpfs1", "-Y"]),
+                include_str!("df-example.in"),
+            )
+            .with_output(
+                Cmd::new("mmdf").args(["gpfs2", "-Y"]),
+                Output {
+                    code: Some(1),
+                    stdout: vec![],
+                    stderr: b"mmdf: File system gpfs2 is not mounted.\n"
+                        .to_vec(),
+                },
+            );
+
+        let (data, errors) = run_partial(&runner).unwrap();
+
+        assert!(data.totals.contains_key("gpfs1"));
+        assert!(!data.totals.contains_key("gpfs2"));
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].is_transient());
+
+        assert!(matches!(run(&runner), Err(Error::Command(_))));
+    }
 }
@@ -1,53 +1,140 @@
 //! `mmdiag` parsing.
 
-use std::io::{BufRead, Write};
-use std::process::Command;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Display;
+use std::io::BufRead;
+use std::str::FromStr;
 
-use anyhow::{Context, Result, anyhow};
+use crate::nsd::FsPoolId;
+use crate::parse::{FromRecord, Record};
+use crate::prom::{self, Family, Histogram, ToMetrics};
+use crate::run::{Cmd, Runner};
+use crate::util::serde_via_str;
+use crate::{Error, Result};
 
 /// Returns the deadlock.
 ///
 /// # Errors
 ///
-/// Returns an error if running `mmlsmgr` fails or if parsing its output fails.
-pub fn deadlock() -> Result<Deadlock> {
-    let mut cmd = Command::new("mmdiag");
-    cmd.arg("--deadlock");
-    cmd.arg("-Y");
+/// Returns an error if running `mmdiag` fails or if parsing its output fails.
+pub fn deadlock(runner: &dyn Runner) -> Result<Deadlock> {
+    let cmd = Cmd::new("mmdiag").args(["--deadlock", "-Y"]);
 
-    let output = cmd
-        .output()
-        .with_context(|| format!("error running: {cmd:?}"))?;
+    let output = runner.run_checked(&cmd)?;
 
     let data = Deadlock::from_reader(output.stdout.as_slice())?;
 
     Ok(data)
 }
 
+/// Returns the waiters of the local node.
+///
+/// The local `node` name is only used for labeling, e.g. from
+/// [`crate::state::local_node_name`]. It is passed in so that callers can
+/// resolve it once instead of with every call.
+///
+/// # Errors
+///
+/// Returns an error if running `mmdiag` fails or if parsing its output fails.
+pub fn waiters(runner: &dyn Runner, node: &str) -> Result<Waiters> {
+    let cmd = Cmd::new("mmdiag").args(["--waiters", "-Y"]);
+
+    let output = runner.run_checked(&cmd)?;
+
+    let waiters = Waiter::from_reader(output.stdout.as_slice())?;
+
+    Ok(Waiters {
+        node: node.into(),
+        waiters,
+    })
+}
+
+/// Returns the recent I/O history of the local node.
+///
+/// # Errors
+///
+/// Returns an error if running `mmdiag` fails or if parsing its output fails.
+pub fn iohist(runner: &dyn Runner) -> Result<IoHistory> {
+    let cmd = Cmd::new("mmdiag").args(["--iohist", "-Y"]);
+
+    let output = runner.run_checked(&cmd)?;
+
+    IoHistory::from_reader(output.stdout.as_slice())
+}
+
+/// Returns the memory use of the local node.
+///
+/// The local `node` name is only used for labeling, see [`waiters`].
+///
+/// # Errors
+///
+/// Returns an error if running `mmdiag` fails or if parsing its output fails.
+pub fn memory(runner: &dyn Runner, node: &str) -> Result<Memory> {
+    let cmd = Cmd::new("mmdiag").args(["--memory", "-Y"]);
+
+    let output = runner.run_checked(&cmd)?;
+
+    Memory::from_reader(node.into(), output.stdout.as_slice())
+}
+
+/// Returns the pending messages and connections of the local node.
+///
+/// The local `node` name is only used for labeling, see [`waiters`].
+///
+/// # Errors
+///
+/// Returns an error if running `mmdiag` fails or if parsing its output fails.
+pub fn network(runner: &dyn Runner, node: &str) -> Result<Network> {
+    let cmd = Cmd::new("mmdiag").args(["--network", "-Y"]);
+
+    let output = runner.run_checked(&cmd)?;
+
+    Network::from_reader(node.into(), output.stdout.as_slice())
+}
+
+/// Returns the daemon statistics of the local node.
+///
+/// The local `node` name is only used for labeling, see [`waiters`].
+///
+/// # Errors
+///
+/// Returns an error if running `mmdiag` fails or if parsing its output fails.
+pub fn stats(runner: &dyn Runner, node: &str) -> Result<Stats> {
+    let cmd = Cmd::new("mmdiag").args(["--stats", "-Y"]);
+
+    let output = runner.run_checked(&cmd)?;
+
+    let stats = crate::parse::from_reader(output.stdout.as_slice())?;
+
+    Ok(Stats {
+        node: node.into(),
+        stats,
+    })
+}
+
 /// Deadlock.
 #[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Deadlock {
     node_list: Vec<String>,
 }
 
 impl Deadlock {
+    /// Returns the nodes with deadlocks.
+    #[must_use]
+    pub fn nodes(&self) -> &[String] {
+        &self.node_list
+    }
+
     fn from_reader<Input: BufRead>(input: Input) -> Result<Self> {
-        let mut index = Index::default();
         let mut data = Self::default();
 
-        for line in input.lines() {
-            let line = line?;
-
-            let tokens = line.split(':').collect::<Vec<_>>();
+        for record in crate::parse::records(input) {
+            let record = record?;
 
-            if tokens[1] == "deadlockNodes" {
-                if tokens[2] == "HEADER" {
-                    index = Index::default();
-                    index.with_tokens(&tokens);
-                } else {
-                    let new = Self::from_tokens(&tokens, &index)?;
-                    data.node_list.extend(new.node_list);
-                }
+            if record.section() == "deadlockNodes" {
+                let node = record.get("nodeList")?;
+                data.node_list.push(node.into());
             }
         }
 
@@ -55,49 +142,1189 @@ impl Deadlock {
     }
 }
 
-// ----------------------------------------------------------------------------
-// boiler-platy parsing
-// ----------------------------------------------------------------------------
+/// Waiters of a node.
+#[derive(Clone, PartialEq, PartialOrd, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Waiters {
+    node: String,
+    waiters: Vec<Waiter>,
+}
 
-impl Deadlock {
-    fn from_tokens(tokens: &[&str], index: &Index) -> Result<Self> {
-        let node_list = index
-            .node_list
-            .ok_or_else(|| anyhow!("no node name index"))?;
-        let node_list = tokens[node_list].into();
+impl Waiters {
+    /// Returns the node name.
+    #[must_use]
+    pub fn node(&self) -> &str {
+        &self.node
+    }
+
+    /// Returns the waiters.
+    #[must_use]
+    pub fn waiters(&self) -> &[Waiter] {
+        &self.waiters
+    }
+
+    /// Returns the longest waiter.
+    #[must_use]
+    pub fn longest(&self) -> Option<&Waiter> {
+        self.waiters
+            .iter()
+            .max_by(|a, b| a.wait_time.total_cmp(&b.wait_time))
+    }
+}
+
+/// A thread waiting for something, e.g. I/O, an RPC or a lock.
+#[derive(Clone, PartialEq, PartialOrd, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Waiter {
+    thread_id: u64,
+    thread_name: String,
+    wait_start: String,
+    wait_time: f64,
+    reason: String,
+    condvar_name: String,
+    mutex_name: String,
+    target: Option<String>,
+}
+
+impl Waiter {
+    /// Waiter categories, see [`Waiter::category`].
+    pub const CATEGORIES: [&str; 4] = ["io", "rpc", "lock", "other"];
+
+    /// Returns the thread ID.
+    #[must_use]
+    pub const fn thread_id(&self) -> u64 {
+        self.thread_id
+    }
+
+    /// Returns the thread name, e.g. `NSDThread`.
+    #[must_use]
+    pub fn thread_name(&self) -> &str {
+        &self.thread_name
+    }
+
+    /// Returns when the thread started waiting.
+    #[must_use]
+    pub fn wait_start(&self) -> &str {
+        &self.wait_start
+    }
+
+    /// Returns for how many seconds the thread has been waiting.
+    #[must_use]
+    pub const fn wait_time(&self) -> f64 {
+        self.wait_time
+    }
+
+    /// Returns the reason, e.g. `for I/O completion on disk sdb`.
+    #[must_use]
+    pub fn reason(&self) -> &str {
+        &self.reason
+    }
+
+    /// Returns the name of the condition variable the thread waits on.
+    #[must_use]
+    pub fn condvar_name(&self) -> &str {
+        &self.condvar_name
+    }
+
+    /// Returns the name of the mutex the thread waits on.
+    #[must_use]
+    pub fn mutex_name(&self) -> &str {
+        &self.mutex_name
+    }
+
+    /// Returns the node the thread waits on, e.g. `10.0.0.2 <c0n1>`.
+    #[must_use]
+    pub fn target(&self) -> Option<&str> {
+        self.target.as_deref()
+    }
+
+    /// Returns what the thread waits for: `io`, `rpc`, `lock` or `other`.
+    ///
+    /// The reasons are free text, so this is only a rough classification
+    /// that is good enough to tell slow disks from slow networks from lock
+    /// contention.
+    #[must_use]
+    pub fn category(&self) -> &'static str {
+        if self.reason.contains("I/O") || self.reason.contains("RDMA") {
+            "io"
+        } else if self.reason.contains("RPC") {
+            "rpc"
+        } else if self.reason.contains("lock")
+            || self.condvar_name.starts_with("Lk")
+            || !self.mutex_name.is_empty()
+        {
+            "lock"
+        } else {
+            "other"
+        }
+    }
+
+    fn from_reader<Input: BufRead>(input: Input) -> Result<Vec<Self>> {
+        let mut waiters = vec![];
+
+        for record in crate::parse::records(input) {
+            let record = record?;
+
+            if record.section() == "waiters" {
+                waiters.push(Self::from_record(&record)?);
+            }
+        }
+
+        Ok(waiters)
+    }
+}
+
+/// Recent I/Os of a node.
+///
+/// The GPFS daemon keeps only a limited number of recent I/Os, so this is a
+/// snapshot rather than a complete record.
+#[derive(Clone, PartialEq, PartialOrd, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct IoHistory(Vec<Io>);
+
+impl IoHistory {
+    /// Upper bounds of the latency histogram buckets in seconds.
+    pub const LATENCY_BUCKETS: [f64; 11] = [
+        0.0005, 0.001, 0.002, 0.005, 0.01, 0.02, 0.05, 0.1, 0.2, 0.5, 1.0,
+    ];
+
+    fn from_reader<Input: BufRead>(input: Input) -> Result<Self> {
+        crate::parse::from_reader(input).map(Self)
+    }
+
+    /// Returns the I/Os.
+    #[must_use]
+    pub fn ios(&self) -> &[Io] {
+        &self.0
+    }
+
+    /// Returns latency histograms per NSD and per pool.
+    ///
+    /// I/Os of NSDs that are not in `pools`, e.g. because they don't belong
+    /// to a file system, only count towards the NSD histograms.
+    #[must_use]
+    pub fn latency(&self, pools: &HashMap<String, FsPoolId>) -> IoLatency {
+        let mut latency = IoLatency::default();
+
+        for io in &self.0 {
+            let seconds = io.time_ms / 1000.0;
+
+            latency
+                .nsds
+                .entry((io.nsd.clone(), io.direction))
+                .or_insert_with(|| Histogram::new(Self::LATENCY_BUCKETS))
+                .observe(seconds);
+
+            if let Some(id) = pools.get(&io.nsd) {
+                latency
+                    .pools
+                    .entry((id.clone(), io.direction))
+                    .or_insert_with(|| Histogram::new(Self::LATENCY_BUCKETS))
+                    .observe(seconds);
+            }
+        }
+
+        latency
+    }
+}
+
+/// A single I/O.
+#[derive(Clone, PartialEq, PartialOrd, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Io {
+    start: String,
+    direction: Direction,
+    buf_type: String,
+    disk_num: u64,
+    sector: u64,
+    sectors: u64,
+    time_ms: f64,
+    kind: String,
+    nsd: String,
+    client: String,
+}
+
+impl Io {
+    /// Returns the time of day the I/O started.
+    #[must_use]
+    pub fn start(&self) -> &str {
+        &self.start
+    }
+
+    /// Returns whether the I/O is a read or a write.
+    #[must_use]
+    pub const fn direction(&self) -> Direction {
+        self.direction
+    }
+
+    /// Returns the buffer type, e.g. `data`, `inode` or `logData`.
+    #[must_use]
+    pub fn buf_type(&self) -> &str {
+        &self.buf_type
+    }
+
+    /// Returns the disk number within the file system.
+    #[must_use]
+    pub const fn disk_num(&self) -> u64 {
+        self.disk_num
+    }
+
+    /// Returns the first sector.
+    #[must_use]
+    pub const fn sector(&self) -> u64 {
+        self.sector
+    }
+
+    /// Returns the number of 512 byte sectors.
+    #[must_use]
+    pub const fn sectors(&self) -> u64 {
+        self.sectors
+    }
+
+    /// Returns the size in bytes.
+    #[must_use]
+    pub const fn bytes(&self) -> u64 {
+        self.sectors * 512
+    }
+
+    /// Returns how long the I/O took in milliseconds.
+    #[must_use]
+    pub const fn time_ms(&self) -> f64 {
+        self.time_ms
+    }
+
+    /// Returns the I/O type, i.e. `cli` for I/O of an NSD client, `srv` for
+    /// I/O of an NSD server on behalf of a client and `lcl` for local I/O.
+    #[must_use]
+    pub fn kind(&self) -> &str {
+        &self.kind
+    }
+
+    /// Returns the NSD name.
+    #[must_use]
+    pub fn nsd(&self) -> &str {
+        &self.nsd
+    }
+
+    /// Returns the client an NSD server did the I/O for, which is empty for
+    /// local I/O.
+    #[must_use]
+    pub fn client(&self) -> &str {
+        &self.client
+    }
+}
+
+/// Direction of an I/O.
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum Direction {
+    /// Read.
+    Read,
+
+    /// Write.
+    Write,
+}
+
+impl Display for Direction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            Self::Read => "read",
+            Self::Write => "write",
+        };
+
+        write!(f, "{s}")
+    }
+}
+
+impl FromStr for Direction {
+    type Err = crate::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "R" => Ok(Self::Read),
+            "W" => Ok(Self::Write),
+            unknown => Err(Error::InvalidValue {
+                kind: "I/O direction",
+                value: unknown.into(),
+            }),
+        }
+    }
+}
+
+/// I/O latency histograms per NSD and per pool, split by direction.
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct IoLatency {
+    nsds: BTreeMap<(String, Direction), Histogram>,
+    pools: BTreeMap<(FsPoolId, Direction), Histogram>,
+}
+
+impl IoLatency {
+    /// Returns the latency histograms in seconds per NSD.
+    #[must_use]
+    pub const fn nsds(&self) -> &BTreeMap<(String, Direction), Histogram> {
+        &self.nsds
+    }
+
+    /// Returns the latency histograms in seconds per pool.
+    #[must_use]
+    pub const fn pools(&self) -> &BTreeMap<(FsPoolId, Direction), Histogram> {
+        &self.pools
+    }
+}
+
+/// Memory use of a node.
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Memory {
+    node: String,
+    pools: Vec<MemoryPool>,
+    pagepool: Pagepool,
+}
+
+impl Memory {
+    /// Returns the node name.
+    #[must_use]
+    pub fn node(&self) -> &str {
+        &self.node
+    }
+
+    /// Returns the daemon memory pools.
+    #[must_use]
+    pub fn pools(&self) -> &[MemoryPool] {
+        &self.pools
+    }
+
+    /// Returns the pool with the given name, e.g. `Shared Segment`.
+    #[must_use]
+    pub fn pool(&self, name: &str) -> Option<&MemoryPool> {
+        self.pools.iter().find(|pool| pool.name == name)
+    }
+
+    /// Returns the pool the token manager allocates its tokens from.
+    #[must_use]
+    pub fn token_manager(&self) -> Option<&MemoryPool> {
+        self.pool("Token Manager")
+    }
+
+    /// Returns the pagepool.
+    #[must_use]
+    pub const fn pagepool(&self) -> &Pagepool {
+        &self.pagepool
+    }
+
+    fn from_reader<Input: BufRead>(
+        node: String,
+        input: Input,
+    ) -> Result<Self> {
+        let mut pools = vec![];
+        let mut pagepool = None;
+
+        for record in crate::parse::records(input) {
+            let record = record?;
+
+            match record.section() {
+                "memoryPool" => pools.push(MemoryPool::from_record(&record)?),
+                "pagepool" => {
+                    pagepool = Some(Pagepool {
+                        size: record.parse("bytesTotal")?,
+                        used: record.parse("bytesInUse")?,
+                    });
+                }
+                _ => {}
+            }
+        }
+
+        let pagepool =
+            pagepool.ok_or_else(|| Error::NotFound("pagepool".into()))?;
 
         Ok(Self {
-            node_list: vec![node_list],
+            node,
+            pools,
+            pagepool,
         })
     }
 }
 
-#[derive(Debug, Default)]
-struct Index {
-    node_list: Option<usize>,
+/// Daemon memory pool.
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MemoryPool {
+    id: u64,
+    name: String,
+    used: u64,
+    limit: Option<u64>,
+}
+
+impl MemoryPool {
+    /// Returns the pool ID.
+    #[must_use]
+    pub const fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// Returns the pool name, e.g. `Token Manager`.
+    #[must_use]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the bytes in use.
+    #[must_use]
+    pub const fn used(&self) -> u64 {
+        self.used
+    }
+
+    /// Returns the maximum size in bytes, or [`None`] if unlimited.
+    #[must_use]
+    pub const fn limit(&self) -> Option<u64> {
+        self.limit
+    }
+}
+
+/// Pagepool, i.e. the cache for file data and metadata.
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Pagepool {
+    size: u64,
+    used: u64,
+}
+
+impl Pagepool {
+    /// Returns the size in bytes.
+    #[must_use]
+    pub const fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// Returns the bytes in use.
+    #[must_use]
+    pub const fn used(&self) -> u64 {
+        self.used
+    }
+}
+
+/// Pending messages and connections of a node.
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Network {
+    node: String,
+    pending: Vec<PendingMessage>,
+    connections: Vec<Connection>,
 }
 
-impl Index {
-    fn with_tokens(&mut self, tokens: &[&str]) {
-        for (i, token) in tokens.iter().enumerate() {
-            if *token == "nodeList" {
-                self.node_list = Some(i);
+impl Network {
+    /// Returns the node name.
+    #[must_use]
+    pub fn node(&self) -> &str {
+        &self.node
+    }
+
+    /// Returns the messages that wait for a reply.
+    #[must_use]
+    pub fn pending(&self) -> &[PendingMessage] {
+        &self.pending
+    }
+
+    /// Returns the connections to other nodes.
+    #[must_use]
+    pub fn connections(&self) -> &[Connection] {
+        &self.connections
+    }
+
+    /// Returns the host name of the peer with the given IP address, or the
+    /// address itself if there is no connection to it.
+    #[must_use]
+    pub fn peer<'a>(&'a self, destination: &'a str) -> &'a str {
+        self.connections
+            .iter()
+            .find(|connection| connection.destination == destination)
+            .map_or(destination, Connection::hostname)
+    }
+
+    fn from_reader<Input: BufRead>(
+        node: String,
+        input: Input,
+    ) -> Result<Self> {
+        let mut pending = vec![];
+        let mut connections = vec![];
+
+        for record in crate::parse::records(input) {
+            let record = record?;
+
+            match record.section() {
+                "pendingMessage" => {
+                    pending.push(PendingMessage::from_record(&record)?);
+                }
+                "connection" => {
+                    connections.push(Connection::from_record(&record)?);
+                }
+                _ => {}
             }
         }
+
+        Ok(Self {
+            node,
+            pending,
+            connections,
+        })
+    }
+}
+
+/// A message that waits for a reply.
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PendingMessage {
+    id: u64,
+    kind: String,
+    destination: String,
+    node_number: u64,
+    elapsed_ms: u64,
+}
+
+impl PendingMessage {
+    /// Returns the message ID.
+    #[must_use]
+    pub const fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// Returns the message type, e.g. `nsdMsgReadExt`.
+    #[must_use]
+    pub fn kind(&self) -> &str {
+        &self.kind
+    }
+
+    /// Returns the IP address of the destination node.
+    #[must_use]
+    pub fn destination(&self) -> &str {
+        &self.destination
+    }
+
+    /// Returns the node number of the destination node.
+    #[must_use]
+    pub const fn node_number(&self) -> u64 {
+        self.node_number
+    }
+
+    /// Returns for how many milliseconds the message has been pending.
+    #[must_use]
+    pub const fn elapsed_ms(&self) -> u64 {
+        self.elapsed_ms
+    }
+}
+
+/// A connection to another node.
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Connection {
+    hostname: String,
+    destination: String,
+    node_number: u64,
+    status: ConnectionStatus,
+    error: u64,
+    sent: u64,
+    received: u64,
+    retries: u64,
+}
+
+impl Connection {
+    /// Returns the host name of the peer.
+    #[must_use]
+    pub fn hostname(&self) -> &str {
+        &self.hostname
+    }
+
+    /// Returns the IP address of the peer.
+    #[must_use]
+    pub fn destination(&self) -> &str {
+        &self.destination
+    }
+
+    /// Returns the node number of the peer.
+    #[must_use]
+    pub const fn node_number(&self) -> u64 {
+        self.node_number
+    }
+
+    /// Returns the connection status.
+    #[must_use]
+    pub const fn status(&self) -> &ConnectionStatus {
+        &self.status
+    }
+
+    /// Returns the last socket error number, `0` if there was none.
+    #[must_use]
+    pub const fn error(&self) -> u64 {
+        self.error
+    }
+
+    /// Returns the number of messages sent.
+    #[must_use]
+    pub const fn sent(&self) -> u64 {
+        self.sent
+    }
+
+    /// Returns the number of messages received.
+    #[must_use]
+    pub const fn received(&self) -> u64 {
+        self.received
+    }
+
+    /// Returns the number of reconnect attempts.
+    #[must_use]
+    pub const fn retries(&self) -> u64 {
+        self.retries
+    }
+}
+
+/// Status of a connection to another node.
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+#[non_exhaustive]
+pub enum ConnectionStatus {
+    /// The connection is established.
+    Connected,
+
+    /// The connection was closed, e.g. because the peer left the cluster.
+    Disconnected,
+
+    /// The connection failed and the daemon tries to reconnect.
+    Broken,
+
+    /// Unknown status.
+    Unknown(String),
+}
+
+impl ConnectionStatus {
+    const ALL: [&'static str; 4] =
+        ["connected", "disconnected", "broken", "unknown"];
+
+    /// Returns the status name, with all unknown states named `unknown`.
+    const fn name(&self) -> &'static str {
+        match self {
+            Self::Connected => "connected",
+            Self::Disconnected => "disconnected",
+            Self::Broken => "broken",
+            Self::Unknown(_) => "unknown",
+        }
+    }
+}
+
+impl Display for ConnectionStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            Self::Unknown(s) => s.as_str(),
+            status => status.name(),
+        };
+
+        write!(f, "{s}")
+    }
+}
+
+impl FromStr for ConnectionStatus {
+    type Err = crate::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "connected" => Ok(Self::Connected),
+            "disconnected" => Ok(Self::Disconnected),
+            "broken" => Ok(Self::Broken),
+            unknown => Ok(Self::Unknown(unknown.into())),
+        }
+    }
+}
+
+serde_via_str!(ConnectionStatus);
+
+/// Daemon statistics of a node.
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Stats {
+    node: String,
+    stats: Vec<Stat>,
+}
+
+impl Stats {
+    /// Returns the node name.
+    #[must_use]
+    pub fn node(&self) -> &str {
+        &self.node
+    }
+
+    /// Returns the statistics.
+    #[must_use]
+    pub fn stats(&self) -> &[Stat] {
+        &self.stats
+    }
+
+    /// Returns the value of a statistic, e.g. `("openFile", "inUse")`.
+    #[must_use]
+    pub fn get(&self, category: &str, name: &str) -> Option<u64> {
+        self.stats
+            .iter()
+            .find(|stat| stat.category == category && stat.name == name)
+            .map(Stat::value)
+    }
+}
+
+/// A daemon statistic.
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Stat {
+    category: String,
+    name: String,
+    value: u64,
+}
+
+impl Stat {
+    /// Returns the category, e.g. `openFile`.
+    #[must_use]
+    pub fn category(&self) -> &str {
+        &self.category
+    }
+
+    /// Returns the name, e.g. `inUse`.
+    #[must_use]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the value.
+    #[must_use]
+    pub const fn value(&self) -> u64 {
+        self.value
+    }
+
+    /// Returns `true` if the value only ever increases since the daemon
+    /// started, e.g. `created`, instead of being a current count, e.g.
+    /// `inUse`.
+    #[must_use]
+    pub fn is_cumulative(&self) -> bool {
+        CUMULATIVE_STATS.contains(&self.name.as_str())
     }
 }
 
+/// Names of statistics that are cumulative, see [`Stat::is_cumulative`].
+const CUMULATIVE_STATS: [&str; 3] = ["created", "deleted", "destroyed"];
+
 // ----------------------------------------------------------------------------
 // prometheus
 // ----------------------------------------------------------------------------
 
-impl crate::prom::ToText for Deadlock {
-    fn to_prom(&self, output: &mut impl Write) -> Result<()> {
-        writeln!(output, "# HELP gpfs_diag_deadlocks GPFS deadlock nodes.")?;
-        writeln!(output, "# TYPE gpfs_diag_deadlocks gauge")?;
-        writeln!(output, "gpfs_diag_deadlocks {}", self.node_list.len())?;
+impl ToMetrics for Deadlock {
+    fn to_metrics(&self) -> Vec<Family> {
+        let mut deadlocks =
+            Family::gauge("gpfs_diag_deadlocks", "GPFS deadlock nodes.");
+        deadlocks.sample(&[], self.node_list.len());
+
+        vec![deadlocks]
+    }
+}
+
+/// Waiter duration buckets in seconds, as lower bound and label.
+const WAITER_BUCKETS: [(f64, &str); 5] = [
+    (0.0, "0-1"),
+    (1.0, "1-10"),
+    (10.0, "10-60"),
+    (60.0, "60-300"),
+    (300.0, "300+"),
+];
+
+impl ToMetrics for Waiters {
+    fn to_metrics(&self) -> Vec<Family> {
+        let mut waiters = Family::gauge(
+            "gpfs_diag_waiters",
+            "GPFS waiters by duration in seconds and by category, which is a \
+             rough classification of the wait reason into io, rpc, lock or \
+             other.",
+        );
+
+        for category in Waiter::CATEGORIES {
+            for (_, duration) in WAITER_BUCKETS {
+                let count = self
+                    .waiters
+                    .iter()
+                    .filter(|waiter| waiter.category() == category)
+                    .filter(|waiter| {
+                        waiter_bucket(waiter.wait_time) == duration
+                    })
+                    .count();
+
+                waiters.sample(
+                    &[
+                        ("node", &self.node),
+                        ("category", category),
+                        ("duration", duration),
+                    ],
+                    count,
+                );
+            }
+        }
+
+        let mut longest = Family::gauge(
+            "gpfs_diag_longest_waiter_seconds",
+            "GPFS longest waiter wait time.",
+        )
+        .with_unit("seconds");
+
+        longest.sample(
+            &[("node", &self.node)],
+            self.longest().map_or(0.0, Waiter::wait_time),
+        );
 
-        Ok(())
+        vec![waiters, longest]
+    }
+}
+
+/// The I/O history only covers the most recent I/Os, so the histograms are not
+/// cumulative. That's why they are exported as gauges of the recent I/Os per
+/// latency bucket, with the upper bound in seconds as `le` label like with
+/// Prometheus histograms, and the total time of the recent I/Os.
+impl ToMetrics for IoLatency {
+    fn to_metrics(&self) -> Vec<Family> {
+        let mut nsd_ios = Family::gauge(
+            "gpfs_nsd_recent_ios",
+            "GPFS recent I/Os per NSD up to the latency in seconds.",
+        );
+        let mut nsd_time = Family::gauge(
+            "gpfs_nsd_recent_io_time_seconds",
+            "GPFS total latency of recent I/Os per NSD.",
+        )
+        .with_unit("seconds");
+
+        for ((name, direction), histogram) in &self.nsds {
+            let direction = direction.to_string();
+            let labels = [("nsd", name.as_str()), ("rw", &direction)];
+
+            latency_samples(&mut nsd_ios, &mut nsd_time, &labels, histogram);
+        }
+
+        let mut pool_ios = Family::gauge(
+            "gpfs_pool_recent_ios",
+            "GPFS recent I/Os per pool up to the latency in seconds.",
+        );
+        let mut pool_time = Family::gauge(
+            "gpfs_pool_recent_io_time_seconds",
+            "GPFS total latency of recent I/Os per pool.",
+        )
+        .with_unit("seconds");
+
+        for ((id, direction), histogram) in &self.pools {
+            let direction = direction.to_string();
+            let labels =
+                [("fs", id.fs()), ("pool", id.pool()), ("rw", &direction)];
+
+            latency_samples(&mut pool_ios, &mut pool_time, &labels, histogram);
+        }
+
+        vec![nsd_ios, nsd_time, pool_ios, pool_time]
+    }
+}
+
+/// Adds the bucket samples of `histogram` to `ios` and its sum to `time`.
+fn latency_samples(
+    ios: &mut Family,
+    time: &mut Family,
+    labels: &[(&'static str, &str)],
+    histogram: &Histogram,
+) {
+    for (bound, count) in histogram.buckets() {
+        let le = prom::le(bound);
+
+        let mut labels = labels.to_vec();
+        labels.push(("le", &le));
+
+        ios.sample(&labels, count);
+    }
+
+    time.sample(labels, histogram.sum());
+}
+
+impl ToMetrics for Memory {
+    fn to_metrics(&self) -> Vec<Family> {
+        let node = [("node", self.node.as_str())];
+
+        let mut pagepool =
+            Family::gauge("gpfs_diag_pagepool_bytes", "GPFS pagepool size.")
+                .with_unit("bytes");
+        pagepool.sample(&node, self.pagepool.size);
+
+        let mut pagepool_used = Family::gauge(
+            "gpfs_diag_pagepool_used_bytes",
+            "GPFS pagepool bytes in use.",
+        )
+        .with_unit("bytes");
+        pagepool_used.sample(&node, self.pagepool.used);
+
+        let mut pool_used = Family::gauge(
+            "gpfs_diag_memory_pool_used_bytes",
+            "GPFS daemon memory pool bytes in use.",
+        )
+        .with_unit("bytes");
+
+        let mut pool_limit = Family::gauge(
+            "gpfs_diag_memory_pool_limit_bytes",
+            "GPFS daemon memory pool maximum size.",
+        )
+        .with_unit("bytes");
+
+        for pool in &self.pools {
+            let labels = [("node", self.node.as_str()), ("pool", &pool.name)];
+
+            pool_used.sample(&labels, pool.used);
+
+            if let Some(limit) = pool.limit {
+                pool_limit.sample(&labels, limit);
+            }
+        }
+
+        vec![pagepool, pagepool_used, pool_used, pool_limit]
+    }
+}
+
+impl ToMetrics for Network {
+    fn to_metrics(&self) -> Vec<Family> {
+        let mut pending = Family::gauge(
+            "gpfs_diag_network_pending_messages",
+            "GPFS messages waiting for a reply per peer.",
+        );
+
+        let mut peers = BTreeMap::new();
+
+        // connected peers without pending messages
+        for connection in &self.connections {
+            if connection.status == ConnectionStatus::Connected {
+                peers.insert(connection.hostname.as_str(), 0);
+            }
+        }
+
+        for message in &self.pending {
+            *peers.entry(self.peer(&message.destination)).or_insert(0) += 1;
+        }
+
+        for (peer, count) in peers {
+            pending.sample(&[("node", &self.node), ("peer", peer)], count);
+        }
+
+        let mut state = Family::gauge(
+            "gpfs_diag_network_connection_state",
+            "GPFS connection status per peer.",
+        );
+        let mut sent = Family::counter(
+            "gpfs_diag_network_sent_messages_total",
+            "GPFS messages sent per peer.",
+        );
+        let mut received = Family::counter(
+            "gpfs_diag_network_received_messages_total",
+            "GPFS messages received per peer.",
+        );
+
+        for connection in &self.connections {
+            let labels =
+                [("node", self.node.as_str()), ("peer", &connection.hostname)];
+
+            for name in ConnectionStatus::ALL {
+                state.sample(
+                    &[
+                        ("node", &self.node),
+                        ("peer", &connection.hostname),
+                        ("state", name),
+                    ],
+                    connection.status.name() == name,
+                );
+            }
+
+            sent.sample(&labels, connection.sent);
+            received.sample(&labels, connection.received);
+        }
+
+        vec![pending, state, sent, received]
+    }
+}
+
+/// Most statistics are current counts, e.g. of open files in use, so they
+/// are exported as gauges. Cumulative statistics, see [`Stat::is_cumulative`],
+/// are exported as counters.
+impl ToMetrics for Stats {
+    fn to_metrics(&self) -> Vec<Family> {
+        let mut stats =
+            Family::gauge("gpfs_diag_stat", "GPFS daemon statistic.");
+        let mut cumulative = Family::counter(
+            "gpfs_diag_stat_total",
+            "GPFS daemon cumulative statistic.",
+        );
+
+        for stat in &self.stats {
+            let family = if stat.is_cumulative() {
+                &mut cumulative
+            } else {
+                &mut stats
+            };
+
+            family.sample(
+                &[
+                    ("node", &self.node),
+                    ("category", &stat.category),
+                    ("stat", &stat.name),
+                ],
+                stat.value,
+            );
+        }
+
+        vec![stats, cumulative]
+    }
+}
+
+fn waiter_bucket(wait_time: f64) -> &'static str {
+    WAITER_BUCKETS
+        .iter()
+        .rev()
+        .find(|(min, _)| wait_time >= *min)
+        .map_or("0-1", |(_, label)| label)
+}
+
+// ----------------------------------------------------------------------------
+// boiler-platy parsing
+// ----------------------------------------------------------------------------
+
+impl FromRecord for Waiter {
+    fn from_record(record: &Record) -> Result<Self> {
+        let thread_id = record.parse("threadId")?;
+        let thread_name = record.get("threadName")?.into();
+        let wait_start = record.get("waitStartTime")?.into();
+        let wait_time = record.parse("waitTime")?;
+
+        let mut reasons = vec![];
+
+        for field in ["condVarReason", "auxReason", "delayReason"] {
+            let reason = record.get(field)?.trim();
+
+            if !reason.is_empty() {
+                reasons.push(reason);
+            }
+        }
+
+        let reason = reasons.join(" ");
+
+        let target = reason
+            .split_once("on node ")
+            .map(|(_, target)| target.trim().into());
+
+        let condvar_name = record.get("condVarName")?.into();
+        let mutex_name = record.get("mutexName")?.into();
+
+        Ok(Self {
+            thread_id,
+            thread_name,
+            wait_start,
+            wait_time,
+            reason,
+            condvar_name,
+            mutex_name,
+            target,
+        })
+    }
+}
+
+impl FromRecord for Io {
+    fn from_record(record: &Record) -> Result<Self> {
+        let start = record.get("ioStartTime")?.into();
+        let direction = record.parse("rw")?;
+        let buf_type = record.get("bufType")?.into();
+        let disk_num = record.parse("diskNum")?;
+        let sector = record.parse("sectorNum")?;
+        let sectors = record.parse("numSectors")?;
+        let time_ms = record.parse("timeMs")?;
+        let kind = record.get("ioType")?.into();
+        let nsd = record.get("nsdName")?.into();
+        let client = record.get("clientNode")?.into();
+
+        Ok(Self {
+            start,
+            direction,
+            buf_type,
+            disk_num,
+            sector,
+            sectors,
+            time_ms,
+            kind,
+            nsd,
+            client,
+        })
+    }
+}
+
+impl FromRecord for MemoryPool {
+    fn from_record(record: &Record) -> Result<Self> {
+        let id = record.parse("poolId")?;
+        let name = record.get("poolName")?.into();
+        let used = record.parse("bytesInUse")?;
+
+        // unlimited pools report a limit of zero
+        let limit = Some(record.parse("bytesLimit")?).filter(|&n| n > 0);
+
+        Ok(Self {
+            id,
+            name,
+            used,
+            limit,
+        })
+    }
+}
+
+impl FromRecord for PendingMessage {
+    fn from_record(record: &Record) -> Result<Self> {
+        let id = record.parse("msgId")?;
+        let kind = record.get("msgType")?.into();
+        let destination = record.get("destination")?.into();
+        let node_number = record.parse("nodeNumber")?;
+        let elapsed_ms = record.parse("elapsedMs")?;
+
+        Ok(Self {
+            id,
+            kind,
+            destination,
+            node_number,
+            elapsed_ms,
+        })
+    }
+}
+
+impl FromRecord for Connection {
+    fn from_record(record: &Record) -> Result<Self> {
+        let hostname = record.get("hostname")?.into();
+        let destination = record.get("destination")?.into();
+        let node_number = record.parse("nodeNumber")?;
+        let status = record.parse("status")?;
+        let error = record.parse("error")?;
+        let sent = record.parse("sent")?;
+        let received = record.parse("received")?;
+        let retries = record.parse("retries")?;
+
+        Ok(Self {
+            hostname,
+            destination,
+            node_number,
+            status,
+            error,
+            sent,
+            received,
+            retries,
+        })
+    }
+}
+
+impl FromRecord for Stat {
+    fn from_record(record: &Record) -> Result<Self> {
+        let category = record.get("category")?.into();
+        let name = record.get("name")?.into();
+        let value = record.parse("value")?;
+
+        Ok(Self {
+            category,
+            name,
+            value,
+        })
     }
 }
 
@@ -108,6 +1335,7 @@ impl crate::prom::ToText for Deadlock {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::prom::ToText;
 
     #[test]
     fn parse_empty() {
@@ -131,4 +1359,234 @@ mod tests {
             },
         );
     }
+
+    #[test]
+    fn waiters() {
+        let input = include_str!("diag-waiters-example.in");
+
+        let waiters = Waiter::from_reader(input.as_bytes()).unwrap();
+        assert_eq!(waiters.len(), 5);
+
+        let categories =
+            waiters.iter().map(Waiter::category).collect::<Vec<_>>();
+        assert_eq!(categories, ["io", "rpc", "lock", "lock", "io"]);
+
+        let rpc = &waiters[1];
+        assert_eq!(rpc.thread_id(), 29381);
+        assert_eq!(rpc.thread_name(), "Msg handler ccMsgGroupLeave");
+        assert!((rpc.wait_time() - 12.0451).abs() < f64::EPSILON);
+        assert_eq!(
+            rpc.reason(),
+            "RPC wait for ccMsgGroupLeave on node 10.0.1.5 <c0n12>"
+        );
+        assert_eq!(rpc.target(), Some("10.0.1.5 <c0n12>"));
+
+        assert_eq!(waiters[0].reason(), "for I/O completion on disk sdb");
+        assert_eq!(waiters[0].target(), None);
+
+        let waiters = Waiters {
+            node: "filer1".into(),
+            waiters,
+        };
+
+        assert_eq!(waiters.longest().map(Waiter::thread_id), Some(29383));
+    }
+
+    #[test]
+    fn waiters_prometheus() {
+        let input = include_str!("diag-waiters-example.in");
+
+        let waiters = Waiters {
+            node: "filer1".into(),
+            waiters: Waiter::from_reader(input.as_bytes()).unwrap(),
+        };
+
+        let mut output = vec![];
+        waiters.to_prom(&mut output).unwrap();
+
+        let metrics = std::str::from_utf8(output.as_slice()).unwrap();
+
+        let expected = include_str!("diag-waiters-example.prom");
+        assert_eq!(metrics, expected);
+    }
+
+    fn pools() -> HashMap<String, FsPoolId> {
+        let input = include_str!("disk-example.in");
+        let disks = crate::disk::Disks::from_reader(input.as_bytes()).unwrap();
+
+        disks
+            .iter()
+            .map(|disk| {
+                let id = FsPoolId::new("gpfs1", disk.pool());
+                (disk.nsd_name().to_owned(), id)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn iohist() {
+        let input = include_str!("diag-iohist-example.in");
+
+        let history = IoHistory::from_reader(input.as_bytes()).unwrap();
+        assert_eq!(history.ios().len(), 7);
+
+        let io = &history.ios()[2];
+        assert_eq!(io.start(), "10:00:00.109876");
+        assert_eq!(io.direction(), Direction::Write);
+        assert_eq!(io.buf_type(), "data");
+        assert_eq!(io.disk_num(), 2);
+        assert_eq!(io.sector(), 2_099_314_688);
+        assert_eq!(io.bytes(), 8 * 1024 * 1024);
+        assert!((io.time_ms() - 12.004).abs() < f64::EPSILON);
+        assert_eq!(io.kind(), "srv");
+        assert_eq!(io.nsd(), "disk4");
+        assert_eq!(io.client(), "10.0.1.1");
+
+        assert_eq!(history.ios()[4].client(), "");
+    }
+
+    #[test]
+    fn iohist_latency() {
+        let input = include_str!("diag-iohist-example.in");
+        let history = IoHistory::from_reader(input.as_bytes()).unwrap();
+
+        let latency = history.latency(&pools());
+
+        assert_eq!(latency.nsds().len(), 6);
+        assert_eq!(latency.pools().len(), 5);
+
+        // disk9 isn't part of any file system
+        assert!(
+            latency
+                .nsds()
+                .contains_key(&("disk9".into(), Direction::Read))
+        );
+
+        let nlsas = &latency.pools()
+            [&(FsPoolId::new("gpfs1", "nlsas"), Direction::Read)];
+        assert_eq!(nlsas.count(), 2);
+
+        let mut output = vec![];
+        latency.to_prom(&mut output).unwrap();
+
+        let metrics = std::str::from_utf8(output.as_slice()).unwrap();
+
+        let expected = include_str!("diag-iohist-example.prom");
+        assert_eq!(metrics, expected);
+    }
+
+    #[test]
+    fn memory() {
+        let input = include_str!("diag-memory-example.in");
+
+        let memory =
+            Memory::from_reader("filer1".into(), input.as_bytes()).unwrap();
+        assert_eq!(memory.pools().len(), 3);
+
+        assert_eq!(memory.pagepool().size(), 16 * 1024 * 1024 * 1024);
+        assert_eq!(memory.pagepool().used(), 12 * 1024 * 1024 * 1024);
+
+        let tokens = memory.token_manager().unwrap();
+        assert_eq!(tokens.id(), 3);
+        assert_eq!(tokens.used(), 1_283_904);
+        assert_eq!(tokens.limit(), Some(1024 * 1024 * 1024));
+
+        assert_eq!(memory.pool("Shared Segment").unwrap().limit(), None);
+
+        let mut output = vec![];
+        memory.to_prom(&mut output).unwrap();
+
+        let metrics = std::str::from_utf8(output.as_slice()).unwrap();
+
+        let expected = include_str!("diag-memory-example.prom");
+        assert_eq!(metrics, expected);
+    }
+
+    #[test]
+    fn network() {
+        let input = include_str!("diag-network-example.in");
+
+        let network =
+            Network::from_reader("filer1".into(), input.as_bytes()).unwrap();
+        assert_eq!(network.pending().len(), 3);
+        assert_eq!(network.connections().len(), 4);
+
+        let message = &network.pending()[0];
+        assert_eq!(message.id(), 88412);
+        assert_eq!(message.kind(), "nsdMsgReadExt");
+        assert_eq!(message.destination(), "10.0.0.2");
+        assert_eq!(message.node_number(), 2);
+        assert_eq!(message.elapsed_ms(), 1532);
+
+        let filer3 = &network.connections()[1];
+        assert_eq!(filer3.hostname(), "filer3");
+        assert_eq!(filer3.status(), &ConnectionStatus::Broken);
+        assert_eq!(filer3.error(), 113);
+        assert_eq!(filer3.retries(), 4);
+
+        assert_eq!(network.peer("10.0.0.2"), "filer2");
+        assert_eq!(network.peer("10.0.1.5"), "10.0.1.5");
+
+        let mut output = vec![];
+        network.to_prom(&mut output).unwrap();
+
+        let metrics = std::str::from_utf8(output.as_slice()).unwrap();
+
+        let expected = include_str!("diag-network-example.prom");
+        assert_eq!(metrics, expected);
+
+        // counters have the same sample names in both formats
+        let mut output = vec![];
+        network.to_openmetrics(&mut output).unwrap();
+
+        let metrics = std::str::from_utf8(output.as_slice()).unwrap();
+        assert!(metrics.contains("# TYPE gpfs_diag_network_sent_messages "));
+        assert!(
+            metrics.contains("\ngpfs_diag_network_sent_messages_total{node=")
+        );
+    }
+
+    #[test]
+    fn stats() {
+        let input = include_str!("diag-stats-example.in");
+
+        let stats = Stats {
+            node: "filer1".into(),
+            stats: crate::parse::from_reader(input.as_bytes()).unwrap(),
+        };
+        assert_eq!(stats.stats().len(), 7);
+
+        assert_eq!(stats.get("openFile", "inUse"), Some(412));
+        assert_eq!(stats.get("statCache", "created"), None);
+
+        assert!(stats.stats()[0].is_cumulative());
+        assert!(!stats.stats()[1].is_cumulative());
+
+        let mut output = vec![];
+        stats.to_prom(&mut output).unwrap();
+
+        let metrics = std::str::from_utf8(output.as_slice()).unwrap();
+
+        let expected = include_str!("diag-stats-example.prom");
+        assert_eq!(metrics, expected);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_connection_status() {
+        for (value, name) in [
+            (ConnectionStatus::Connected, "connected"),
+            (ConnectionStatus::Broken, "broken"),
+            (ConnectionStatus::Unknown("foo".into()), "foo"),
+        ] {
+            assert_eq!(value.to_string(), name);
+
+            let json = serde_json::to_string(&value).unwrap();
+            assert_eq!(json, format!("\"{name}\""));
+
+            let parsed: ConnectionStatus =
+                serde_json::from_str(&json).unwrap();
+            assert_eq!(parsed, value);
+        }
+    }
 }
@@ -2,57 +2,36 @@
 
 use std::collections::HashMap;
 use std::fmt::Display;
-use std::io::{BufRead, Write};
-use std::process::Command;
+use std::io::BufRead;
 use std::str::FromStr;
 
-use anyhow::{Context, Result, anyhow};
-
-use crate::util::MMBool;
+use crate::Result;
+use crate::parse::{FromRecord, Record};
+use crate::prom::{Family, ToMetrics};
+use crate::run::{Cmd, Runner};
+use crate::util::{MMBool, serde_via_str};
 
 /// Returns the disks.
 ///
 /// # Errors
 ///
 /// Returns an error if running `mmlsdisk` fails or if parsing its output fails.
-pub fn disks(fs_name: impl AsRef<str>) -> Result<Disks> {
-    let fs_name = fs_name.as_ref();
-
-    let mut cmd = Command::new("mmlsdisk");
-    cmd.arg(fs_name);
-    cmd.arg("-Y");
+pub fn disks(runner: &dyn Runner, fs_name: impl AsRef<str>) -> Result<Disks> {
+    let cmd = Cmd::new("mmlsdisk").arg(fs_name.as_ref()).arg("-Y");
 
-    let output = cmd
-        .output()
-        .with_context(|| format!("error running: {cmd:?}"))?;
+    let output = runner.run_checked(&cmd)?;
 
     Disks::from_reader(output.stdout.as_slice())
 }
 
 /// Parsed disks.
 #[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Disks(Vec<Disk>);
 
 impl Disks {
-    fn from_reader<Input: BufRead>(input: Input) -> Result<Self> {
-        let mut index = Index::default();
-        let mut disks = Self::default();
-
-        for line in input.lines() {
-            let line = line?;
-
-            let tokens = line.split(':').collect::<Vec<_>>();
-
-            if tokens[2] == "HEADER" {
-                index = Index::default();
-                header_to_index(&tokens, &mut index);
-            } else {
-                let entry = Disk::from_tokens(&tokens, &index)?;
-                disks.0.push(entry);
-            }
-        }
-
-        Ok(disks)
+    pub(crate) fn from_reader<Input: BufRead>(input: Input) -> Result<Self> {
+        crate::parse::from_reader(input).map(Self)
     }
 
     /// Returns an [`Iterator`] over the disks.
@@ -140,7 +119,7 @@ impl Display for Availability {
 }
 
 impl FromStr for Availability {
-    type Err = anyhow::Error;
+    type Err = crate::Error;
 
     fn from_str(s: &str) -> Result<Self> {
         match s {
@@ -153,14 +132,91 @@ impl FromStr for Availability {
     }
 }
 
+serde_via_str!(Availability);
+
+/// Disk status.
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+#[non_exhaustive]
+pub enum Status {
+    /// Normal status.
+    Ready,
+
+    /// No new data is allocated on the disk.
+    Suspended,
+
+    /// Disk is suspended and will be emptied by the next restripe.
+    ToBeEmptied,
+
+    /// Data is being migrated off the disk.
+    BeingEmptied,
+
+    /// Disk is empty.
+    Emptied,
+
+    /// Disk is being replaced.
+    Replacing,
+
+    /// Disk is the replacement of a disk being replaced.
+    Replacement,
+
+    /// Unknown status.
+    Unknown(String),
+}
+
+impl Display for Status {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            Self::Ready => "ready",
+            Self::Suspended => "suspended",
+            Self::ToBeEmptied => "to be emptied",
+            Self::BeingEmptied => "being emptied",
+            Self::Emptied => "emptied",
+            Self::Replacing => "replacing",
+            Self::Replacement => "replacement",
+            Self::Unknown(s) => s.as_str(),
+        };
+
+        write!(f, "{s}")
+    }
+}
+
+impl FromStr for Status {
+    type Err = crate::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "ready" => Ok(Self::Ready),
+            "suspended" => Ok(Self::Suspended),
+            "to be emptied" => Ok(Self::ToBeEmptied),
+            "being emptied" => Ok(Self::BeingEmptied),
+            "emptied" => Ok(Self::Emptied),
+            "replacing" => Ok(Self::Replacing),
+            "replacement" => Ok(Self::Replacement),
+            unknown => Ok(Self::Unknown(unknown.into())),
+        }
+    }
+}
+
+serde_via_str!(Status);
+
 /// Disk data.
 #[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Disk {
     nsd_name: String,
+    driver_type: String,
+    sector_size: u64,
+    failure_group: String,
     is_metadata: bool,
     is_objectdata: bool,
+    status: Status,
     availability: Availability,
+    id: u64,
     storage_pool: String,
+    remarks: Option<String>,
+    size_kb: u64,
+    thin_disk_type: Option<String>,
+    replica_type: Option<String>,
 }
 
 impl Disk {
@@ -170,6 +226,27 @@ impl Disk {
         &self.nsd_name
     }
 
+    /// Returns the driver type, e.g. `nsd`.
+    #[must_use]
+    pub fn driver_type(&self) -> &str {
+        &self.driver_type
+    }
+
+    /// Returns the sector size in bytes.
+    #[must_use]
+    pub const fn sector_size(&self) -> u64 {
+        self.sector_size
+    }
+
+    /// Returns the failure group.
+    ///
+    /// This is usually a number, but can also be a topology vector like
+    /// `1,0,1` in FPO clusters.
+    #[must_use]
+    pub fn failure_group(&self) -> &str {
+        &self.failure_group
+    }
+
     /// Returns `true` if this is a metadata disk.
     #[must_use]
     pub const fn is_metadata(&self) -> bool {
@@ -182,112 +259,147 @@ impl Disk {
         self.is_objectdata
     }
 
+    /// Returns the status.
+    #[must_use]
+    pub const fn status(&self) -> &Status {
+        &self.status
+    }
+
+    /// Returns the availability.
+    #[must_use]
+    pub const fn availability(&self) -> &Availability {
+        &self.availability
+    }
+
+    /// Returns the disk ID.
+    #[must_use]
+    pub const fn id(&self) -> u64 {
+        self.id
+    }
+
     /// Returns the storage pool this disk is in.
     #[must_use]
     pub fn pool(&self) -> &str {
         &self.storage_pool
     }
+
+    /// Optionally returns the remarks, e.g. `desc` for descriptor disks.
+    #[must_use]
+    pub const fn remarks(&self) -> Option<&String> {
+        self.remarks.as_ref()
+    }
+
+    /// Returns the disk size in kilobytes.
+    #[must_use]
+    pub const fn size_kb(&self) -> u64 {
+        self.size_kb
+    }
+
+    /// Optionally returns the thin disk type.
+    #[must_use]
+    pub const fn thin_disk_type(&self) -> Option<&String> {
+        self.thin_disk_type.as_ref()
+    }
+
+    /// Optionally returns the replica type.
+    #[must_use]
+    pub const fn replica_type(&self) -> Option<&String> {
+        self.replica_type.as_ref()
+    }
 }
 
 // ----------------------------------------------------------------------------
 // boiler-platy parsing
 // ----------------------------------------------------------------------------
 
-impl Disk {
-    fn from_tokens(tokens: &[&str], index: &Index) -> Result<Self> {
-        let nsd_name_index =
-            index.nsd_name.ok_or_else(|| anyhow!("no NSD name index"))?;
-        let nsd_name = tokens[nsd_name_index].into();
-
-        let is_metadata_index = index
-            .is_metadata
-            .ok_or_else(|| anyhow!("no is metadata index"))?;
-        let is_metadata =
-            tokens[is_metadata_index].parse::<MMBool>()?.as_bool();
-
-        let is_objectdata_index = index
-            .is_objectdata
-            .ok_or_else(|| anyhow!("no is objectdata index"))?;
-        let is_objectdata =
-            tokens[is_objectdata_index].parse::<MMBool>()?.as_bool();
-
-        let availability_index = index
-            .availability
-            .ok_or_else(|| anyhow!("no availability index"))?;
-        let availability =
-            tokens[availability_index].parse::<Availability>()?;
-
-        let storage_pool_index = index
-            .storage_pool
-            .ok_or_else(|| anyhow!("no storage pool index"))?;
-        let storage_pool = tokens[storage_pool_index].into();
+impl FromRecord for Disk {
+    fn from_record(record: &Record) -> Result<Self> {
+        let non_empty =
+            |s: &str| Some(s).filter(|s| !s.is_empty()).map(Into::into);
+
+        let nsd_name = record.get("nsdName")?.into();
+        let driver_type = record.get("driverType")?.into();
+        let sector_size = record.parse("sectorSize")?;
+        let failure_group = record.get("failureGroup")?.into();
+
+        let is_metadata = record.parse::<MMBool>("metadata")?.as_bool();
+        let is_objectdata = record.parse::<MMBool>("data")?.as_bool();
+
+        let status = record.parse::<Status>("status")?;
+        let availability = record.parse::<Availability>("availability")?;
+
+        let id = record.parse("diskID")?;
+        let storage_pool = record.get("storagePool")?.into();
+        let remarks = non_empty(record.get("remarks")?);
+        let size_kb = record.parse("diskSizeKB")?;
+
+        // not available in older versions
+        let thin_disk_type =
+            record.get_opt("thinDiskType").and_then(non_empty);
+        let replica_type = record.get_opt("replicaType").and_then(non_empty);
 
         Ok(Self {
             nsd_name,
+            driver_type,
+            sector_size,
+            failure_group,
             is_metadata,
             is_objectdata,
+            status,
             availability,
+            id,
             storage_pool,
+            remarks,
+            size_kb,
+            thin_disk_type,
+            replica_type,
         })
     }
 }
 
-#[derive(Debug, Default)]
-struct Index {
-    nsd_name: Option<usize>,
-    is_metadata: Option<usize>,
-    is_objectdata: Option<usize>,
-    availability: Option<usize>,
-    storage_pool: Option<usize>,
-}
-
-fn header_to_index(tokens: &[&str], index: &mut Index) {
-    for (i, token) in tokens.iter().enumerate() {
-        match *token {
-            "nsdName" => index.nsd_name = Some(i),
-            "metadata" => index.is_metadata = Some(i),
-            "data" => index.is_objectdata = Some(i),
-            "availability" => index.availability = Some(i),
-            "storagePool" => index.storage_pool = Some(i),
-            _ => {}
-        }
-    }
-}
-
 // ----------------------------------------------------------------------------
 // prometheus
 // ----------------------------------------------------------------------------
 
-impl<S: ::std::hash::BuildHasher> crate::prom::ToText
-    for HashMap<String, Disks, S>
-{
-    fn to_prom(&self, output: &mut impl Write) -> Result<()> {
-        for (fs, disks) in self {
-            writeln!(
-                output,
-                "# HELP gpfs_disk_availability GPFS disk availability."
-            )?;
-            writeln!(output, "# TYPE gpfs_disk_availability gauge")?;
+impl<S: ::std::hash::BuildHasher> ToMetrics for HashMap<String, Disks, S> {
+    fn to_metrics(&self) -> Vec<Family> {
+        let mut availability =
+            Family::gauge("gpfs_disk_availability", "GPFS disk availability.");
+        let mut status =
+            Family::gauge("gpfs_disk_status", "GPFS disk status.");
+        let mut size =
+            Family::gauge("gpfs_disk_size_bytes", "GPFS disk size in bytes.")
+                .with_unit("bytes");
 
+        for (fs, disks) in self {
             for disk in &disks.0 {
-                let status = match disk.availability {
-                    Availability::Up => 0,
-                    _ => 1,
-                };
-
-                writeln!(
-                    output,
-                    "gpfs_disk_availability{{name=\"{}\",fs=\"{}\",pool=\"{}\",availability=\"{}\"}} {}",
-                    disk.nsd_name,
-                    fs,
-                    disk.storage_pool,
-                    disk.availability,
-                    status,
-                )?;
+                let labels = [
+                    ("name", disk.nsd_name.as_str()),
+                    ("fs", fs),
+                    ("pool", &disk.storage_pool),
+                    ("failure_group", &disk.failure_group),
+                ];
+
+                let not_up = !matches!(disk.availability, Availability::Up);
+                let not_ready = !matches!(disk.status, Status::Ready);
+
+                let availability_label = disk.availability.to_string();
+                let status_label = disk.status.to_string();
+
+                availability.sample(
+                    &[&labels[..], &[("availability", &availability_label)]]
+                        .concat(),
+                    not_up,
+                );
+                status.sample(
+                    &[&labels[..], &[("status", &status_label)]].concat(),
+                    not_ready,
+                );
+                size.sample(&labels, disk.size_kb.saturating_mul(1024));
             }
         }
 
-        Ok(())
+        vec![availability, status, size]
     }
 }
 
@@ -311,10 +423,19 @@ mod tests {
             disks.next(),
             Some(Disk {
                 nsd_name: "disk1".into(),
+                driver_type: "nsd".into(),
+                sector_size: 512,
+                failure_group: "1".into(),
                 is_metadata: true,
                 is_objectdata: false,
+                status: Status::Ready,
                 availability: Availability::Up,
+                id: 42,
                 storage_pool: "system".into(),
+                remarks: None,
+                size_kb: 1_869_606_912,
+                thin_disk_type: None,
+                replica_type: None,
             })
         );
 
@@ -322,10 +443,19 @@ mod tests {
             disks.next(),
             Some(Disk {
                 nsd_name: "disk2".into(),
+                driver_type: "nsd".into(),
+                sector_size: 512,
+                failure_group: "1".into(),
                 is_metadata: false,
                 is_objectdata: true,
+                status: Status::Ready,
                 availability: Availability::Down,
+                id: 43,
                 storage_pool: "nvme".into(),
+                remarks: None,
+                size_kb: 1_869_606_912,
+                thin_disk_type: None,
+                replica_type: None,
             })
         );
 
@@ -333,10 +463,19 @@ mod tests {
             disks.next(),
             Some(Disk {
                 nsd_name: "disk3".into(),
+                driver_type: "nsd".into(),
+                sector_size: 512,
+                failure_group: "1".into(),
                 is_metadata: false,
                 is_objectdata: true,
+                status: Status::Ready,
                 availability: Availability::Recovering,
+                id: 44,
                 storage_pool: "nlsas".into(),
+                remarks: None,
+                size_kb: 1_869_606_912,
+                thin_disk_type: None,
+                replica_type: None,
             })
         );
 
@@ -344,10 +483,19 @@ mod tests {
             disks.next(),
             Some(Disk {
                 nsd_name: "disk4".into(),
+                driver_type: "nsd".into(),
+                sector_size: 512,
+                failure_group: "1".into(),
                 is_metadata: false,
                 is_objectdata: true,
+                status: Status::Ready,
                 availability: Availability::Unrecovered,
+                id: 45,
                 storage_pool: "nlsas".into(),
+                remarks: None,
+                size_kb: 1_869_606_912,
+                thin_disk_type: None,
+                replica_type: None,
             })
         );
 
@@ -355,47 +503,84 @@ mod tests {
     }
 
     #[test]
-    fn prometheus() {
-        let disks = vec![
-            Disk {
-                nsd_name: "disk1".into(),
-                is_metadata: true,
-                is_objectdata: false,
-                availability: Availability::Up,
-                storage_pool: "system".into(),
-            },
-            Disk {
-                nsd_name: "disk2".into(),
-                is_metadata: false,
-                is_objectdata: true,
-                availability: Availability::Down,
-                storage_pool: "nvme".into(),
-            },
+    fn parse_full() {
+        let input = include_str!("disk-full-example.in");
+
+        let disks = Disks::from_reader(input.as_bytes()).unwrap();
+        assert_eq!(disks.0.len(), 4);
+        assert_eq!(disks.0[0].remarks, Some("desc".into()));
+
+        assert_eq!(
+            disks.0[2],
             Disk {
                 nsd_name: "disk3".into(),
+                driver_type: "nsd".into(),
+                sector_size: 4096,
+                failure_group: "2".into(),
                 is_metadata: false,
                 is_objectdata: true,
+                status: Status::Suspended,
                 availability: Availability::Recovering,
+                id: 44,
                 storage_pool: "nlsas".into(),
-            },
-            Disk {
-                nsd_name: "disk4".into(),
-                is_metadata: false,
-                is_objectdata: true,
-                availability: Availability::Unrecovered,
-                storage_pool: "nlsas".into(),
-            },
-        ];
+                remarks: None,
+                size_kb: 1_869_606_912,
+                thin_disk_type: None,
+                replica_type: None,
+            }
+        );
+    }
+
+    #[test]
+    fn prometheus() {
+        let input = include_str!("disk-full-example.in");
+        let disks = Disks::from_reader(input.as_bytes()).unwrap();
 
         let mut all_disks = HashMap::new();
-        all_disks.insert(String::from("gpfs1"), Disks(disks));
+        all_disks.insert(String::from("gpfs1"), disks);
 
         let mut output = vec![];
         all_disks.to_prom(&mut output).unwrap();
 
         let metrics = std::str::from_utf8(output.as_slice()).unwrap();
 
-        let expected = include_str!("disk-example.prom");
+        let expected = include_str!("disk-full-example.prom");
         assert_eq!(metrics, expected);
     }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde() {
+        let input = include_str!("disk-full-example.in");
+        let disks = Disks::from_reader(input.as_bytes()).unwrap();
+
+        let json = serde_json::to_string(&disks).unwrap();
+        assert!(json.contains(r#""availability":"recovering""#));
+
+        let parsed: Disks = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, disks);
+
+        for availability in [
+            Availability::Recovering,
+            Availability::Unknown("foo".into()),
+        ] {
+            let json = serde_json::to_string(&availability).unwrap();
+            assert_eq!(json, format!("\"{availability}\""));
+
+            let parsed: Availability = serde_json::from_str(&json).unwrap();
+            assert_eq!(parsed, availability);
+        }
+
+        for status in [
+            Status::ToBeEmptied,
+            Status::BeingEmptied,
+            Status::Unknown("foo".into()),
+        ] {
+            let json = serde_json::to_string(&status).unwrap();
+            assert_eq!(json, format!("\"{status}\""));
+
+            let parsed: Status = serde_json::from_str(&json).unwrap();
+            assert_eq!(parsed, status);
+        }
+    }
 }
@@ -0,0 +1,253 @@
+//! Error types.
+
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::run::CommandError;
+
+/// A specialized [`Result`](std::result::Result) type for this library.
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// The error type of this library.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// A command could not be found.
+    CommandNotFound {
+        /// The program that could not be found.
+        command: String,
+    },
+
+    /// A command could not be started or waited on.
+    Spawn {
+        /// The command line.
+        command: String,
+
+        /// The underlying error.
+        source: io::Error,
+    },
+
+    /// A command was not successful.
+    Command(CommandError),
+
+    /// A command was killed because it did not finish in time.
+    Timeout {
+        /// The command line.
+        command: String,
+
+        /// The timeout that expired.
+        timeout: Duration,
+    },
+
+    /// A line of command output or of a file is malformed.
+    Syntax {
+        /// The line number, starting at 1.
+        line: usize,
+
+        /// A description of what is wrong.
+        message: String,
+    },
+
+    /// A `-Y` record appeared before its `HEADER` line.
+    MissingHeader {
+        /// The line number of the record, starting at 1.
+        line: usize,
+
+        /// The command of the record, e.g. `mmlsdisk`.
+        command: String,
+
+        /// The section of the record, which may be empty.
+        section: String,
+    },
+
+    /// A required field is missing.
+    MissingField {
+        /// The line number, starting at 1.
+        line: usize,
+
+        /// The name of the field.
+        field: String,
+    },
+
+    /// The value of a field could not be parsed.
+    Parse {
+        /// The line number, starting at 1.
+        line: usize,
+
+        /// The name of the field.
+        field: String,
+
+        /// The offending value.
+        value: String,
+
+        /// Why parsing failed.
+        message: String,
+    },
+
+    /// A value is not valid for its kind, e.g. an unknown enum value.
+    InvalidValue {
+        /// The kind of value, e.g. `quota type`.
+        kind: &'static str,
+
+        /// The offending value.
+        value: String,
+    },
+
+    /// Something that was expected does not exist.
+    NotFound(String),
+
+    /// An I/O error on a file or directory.
+    File {
+        /// The path of the file or directory.
+        path: PathBuf,
+
+        /// The underlying error.
+        source: io::Error,
+    },
+
+    /// Any other I/O error, e.g. when reading command output or writing
+    /// metrics.
+    Io(io::Error),
+}
+
+impl Error {
+    /// Returns a closure that wraps an [`io::Error`] on `path`.
+    pub(crate) fn file(
+        path: impl Into<PathBuf>,
+    ) -> impl FnOnce(io::Error) -> Self {
+        |source| Self::File {
+            path: path.into(),
+            source,
+        }
+    }
+
+    /// Returns `true` if the error is likely transient, i.e. retrying later
+    /// may succeed.
+    ///
+    /// Timeouts are always considered transient. For unsuccessful commands,
+    /// see [`CommandError::is_transient`].
+    #[must_use]
+    pub fn is_transient(&self) -> bool {
+        match self {
+            Self::Command(error) => error.is_transient(),
+            Self::Timeout { .. } => true,
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::CommandNotFound { command } => {
+                write!(f, "command not found: {command}")
+            }
+            Self::Spawn { command, .. } => {
+                write!(f, "error running {command}")
+            }
+            Self::Command(error) => error.fmt(f),
+            Self::Timeout { command, timeout } => {
+                write!(f, "{command} timed out after {timeout:?}")
+            }
+            Self::Syntax { line, message } => {
+                write!(f, "line {line}: {message}")
+            }
+            Self::MissingHeader {
+                line,
+                command,
+                section,
+            } => {
+                write!(f, "line {line}: no HEADER for {command}:{section}")
+            }
+            Self::MissingField { line, field } => {
+                write!(f, "line {line}: no {field} field")
+            }
+            Self::Parse {
+                line,
+                field,
+                value,
+                message,
+            } => {
+                write!(f, "line {line}: invalid {field} value {value:?}: ")?;
+                write!(f, "{message}")
+            }
+            Self::InvalidValue { kind, value } => {
+                write!(f, "invalid {kind}: {value}")
+            }
+            Self::NotFound(what) => write!(f, "{what} not found"),
+            Self::File { path, .. } => {
+                write!(f, "I/O error on {}", path.display())
+            }
+            Self::Io(error) => error.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Spawn { source, .. } | Self::File { source, .. } => {
+                Some(source)
+            }
+            Self::Io(error) => error.source(),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(error: io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+impl From<CommandError> for Error {
+    fn from(error: CommandError) -> Self {
+        Self::Command(error)
+    }
+}
+
+// ----------------------------------------------------------------------------
+// tests
+// ----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn display() {
+        let error = Error::Parse {
+            line: 3,
+            field: "blockSize".into(),
+            value: "foo".into(),
+            message: "invalid digit found in string".into(),
+        };
+
+        assert_eq!(
+            error.to_string(),
+            "line 3: invalid blockSize value \"foo\": \
+             invalid digit found in string"
+        );
+
+        let error = Error::MissingField {
+            line: 2,
+            field: "manager".into(),
+        };
+
+        assert_eq!(error.to_string(), "line 2: no manager field");
+    }
+
+    #[test]
+    fn source() {
+        let error = Error::file("/run/cache")(io::Error::from(
+            io::ErrorKind::PermissionDenied,
+        ));
+
+        assert_eq!(error.to_string(), "I/O error on /run/cache");
+        assert!(std::error::Error::source(&error).is_some());
+        assert!(!error.is_transient());
+    }
+}
@@ -1,21 +1,25 @@
 //! `mmlsfileset` parsing.
 
-use std::io::{BufRead, Write};
-use std::process::Command;
+use std::io::BufRead;
 
-use anyhow::{Context, Result, anyhow};
+use crate::{Error, Result};
 
-use crate::prom::ToText;
+use crate::parse::{FromRecord, Record};
+use crate::prom::{Family, ToMetrics};
+use crate::run::{Cmd, Runner};
 use crate::util::MMBool;
 
 /// A fileset.
 #[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Fileset {
     name: String,
     filesystem_name: String,
     is_inode_space_owner: bool,
     max_inodes: u64,
     alloc_inodes: u64,
+    path: String,
+    created: String,
     comment: Option<String>,
 }
 
@@ -50,6 +54,18 @@ impl Fileset {
         self.alloc_inodes
     }
 
+    /// Returns the junction path.
+    #[must_use]
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Returns the creation date as printed by `mmlsfileset`.
+    #[must_use]
+    pub fn created(&self) -> &str {
+        &self.created
+    }
+
     /// Optionally returns the comment.
     #[must_use]
     pub const fn comment(&self) -> Option<&String> {
@@ -57,41 +73,28 @@ impl Fileset {
     }
 }
 
-impl ToText for Vec<Fileset> {
-    fn to_prom(&self, output: &mut impl Write) -> Result<()> {
-        writeln!(
-            output,
-            "# HELP gpfs_fileset_max_inodes GPFS fileset maximum inodes"
-        )?;
-        writeln!(output, "# TYPE gpfs_fileset_max_inodes gauge")?;
+impl ToMetrics for Vec<Fileset> {
+    fn to_metrics(&self) -> Vec<Family> {
+        let mut max_inodes = Family::gauge(
+            "gpfs_fileset_max_inodes",
+            "GPFS fileset maximum inodes",
+        );
+        let mut alloc_inodes = Family::gauge(
+            "gpfs_fileset_alloc_inodes",
+            "GPFS fileset allocated inodes",
+        );
 
         for fileset in self.iter().filter(|f| f.is_inode_space_owner()) {
-            writeln!(
-                output,
-                "gpfs_fileset_max_inodes{{fs=\"{}\",fileset=\"{}\"}} {}",
-                fileset.filesystem_name(),
-                fileset.name(),
-                fileset.max_inodes(),
-            )?;
-        }
+            let labels = [
+                ("fs", fileset.filesystem_name()),
+                ("fileset", fileset.name()),
+            ];
 
-        writeln!(
-            output,
-            "# HELP gpfs_fileset_alloc_inodes GPFS fileset allocated inodes"
-        )?;
-        writeln!(output, "# TYPE gpfs_fileset_alloc_inodes gauge")?;
-
-        for fileset in self.iter().filter(|f| f.is_inode_space_owner()) {
-            writeln!(
-                output,
-                "gpfs_fileset_alloc_inodes{{fs=\"{}\",fileset=\"{}\"}} {}",
-                fileset.filesystem_name(),
-                fileset.name(),
-                fileset.alloc_inodes(),
-            )?;
+            max_inodes.sample(&labels, fileset.max_inodes());
+            alloc_inodes.sample(&labels, fileset.alloc_inodes());
         }
 
-        Ok(())
+        vec![max_inodes, alloc_inodes]
     }
 }
 
@@ -101,14 +104,10 @@ impl ToText for Vec<Fileset> {
 ///
 /// Returns an error if running `mmlsfileset` fails or if parsing its output
 /// fails.
-pub fn filesets(fs: &str) -> Result<Vec<Fileset>> {
-    let mut cmd = Command::new("mmlsfileset");
-    cmd.arg(fs);
-    cmd.arg("-Y");
+pub fn filesets(runner: &dyn Runner, fs: &str) -> Result<Vec<Fileset>> {
+    let cmd = Cmd::new("mmlsfileset").arg(fs).arg("-Y");
 
-    let output = cmd
-        .output()
-        .with_context(|| format!("error running: {cmd:?}"))?;
+    let output = runner.run_checked(&cmd)?;
 
     let filesets = from_reader(output.stdout.as_slice())?;
 
@@ -125,20 +124,19 @@ pub fn filesets(fs: &str) -> Result<Vec<Fileset>> {
 /// Returns an error if running `mmlsfileset` fails or if parsing its output
 /// fails. Also, if `mmlsfileset` returns no fileset at all, an error is
 /// returned.
-pub fn fileset(fs: &str, fileset: &str) -> Result<Fileset> {
-    let mut cmd = Command::new("mmlsfileset");
-    cmd.arg(fs);
-    cmd.arg(fileset);
-    cmd.arg("-Y");
+pub fn fileset(
+    runner: &dyn Runner,
+    fs: &str,
+    fileset: &str,
+) -> Result<Fileset> {
+    let cmd = Cmd::new("mmlsfileset").arg(fs).arg(fileset).arg("-Y");
 
-    let output = cmd
-        .output()
-        .with_context(|| format!("error running: {cmd:?}"))?;
+    let output = runner.run_checked(&cmd)?;
 
     let filesets = from_reader(output.stdout.as_slice())?;
 
     let Some(fileset) = filesets.into_iter().next() else {
-        return Err(anyhow!("no fileset returned"));
+        return Err(Error::NotFound(format!("fileset {fs}:{fileset}")));
     };
 
     Ok(fileset)
@@ -148,96 +146,37 @@ pub fn fileset(fs: &str, fileset: &str) -> Result<Fileset> {
 // boiler-platy parsing
 // ----------------------------------------------------------------------------
 
-#[derive(Debug, Default)]
-struct Index {
-    filesystem_name: Option<usize>,
-    fileset_name: Option<usize>,
-    is_inode_space_owner: Option<usize>,
-    max_inodes: Option<usize>,
-    alloc_inodes: Option<usize>,
-    comment: Option<usize>,
-}
-
 fn from_reader<Input: BufRead>(input: Input) -> Result<Vec<Fileset>> {
-    let mut index = Index::default();
-    let mut fs = vec![];
-
-    for line in input.lines() {
-        let line = line?;
-
-        let tokens = line.split(':').collect::<Vec<_>>();
-
-        if tokens[2] == "HEADER" {
-            index = Index::default();
-            header_to_index(&tokens, &mut index);
-        } else {
-            let entry = from_tokens(&tokens, &index)?;
-            fs.push(entry);
-        }
-    }
-
-    Ok(fs)
+    crate::parse::from_reader(input)
 }
 
-fn from_tokens(tokens: &[&str], index: &Index) -> Result<Fileset> {
-    let filesystem_name_index = index
-        .filesystem_name
-        .ok_or_else(|| anyhow!("no filesystemName index"))?;
-    let filesystem_name = tokens[filesystem_name_index].into();
-
-    let fileset_name_index = index
-        .fileset_name
-        .ok_or_else(|| anyhow!("no filesetName index"))?;
-    let fileset_name = tokens[fileset_name_index].into();
-
-    let is_inode_space_owner_index = index
-        .is_inode_space_owner
-        .ok_or_else(|| anyhow!("no isInodeSpaceOwner index"))?;
-    let is_inode_space_owner = tokens[is_inode_space_owner_index]
-        .parse::<MMBool>()
-        .with_context(|| "parsing isInodeSpaceOwner value")?
-        .as_bool();
-
-    let max_inodes_index = index
-        .max_inodes
-        .ok_or_else(|| anyhow!("no maxInodes index"))?;
-    let max_inodes = tokens[max_inodes_index]
-        .parse()
-        .with_context(|| "parsing maxInodes value")?;
-
-    let alloc_inodes_index = index
-        .alloc_inodes
-        .ok_or_else(|| anyhow!("no allocInodes index"))?;
-    let alloc_inodes = tokens[alloc_inodes_index]
-        .parse()
-        .with_context(|| "parsing allocInodes value")?;
-
-    let comment_index =
-        index.comment.ok_or_else(|| anyhow!("no comment index"))?;
-    let comment = tokens[comment_index].replace("%3A", ":");
-    let comment = Some(comment).filter(|s| !s.is_empty());
-
-    Ok(Fileset {
-        name: fileset_name,
-        filesystem_name,
-        is_inode_space_owner,
-        max_inodes,
-        alloc_inodes,
-        comment,
-    })
-}
-
-fn header_to_index(tokens: &[&str], index: &mut Index) {
-    for (i, token) in tokens.iter().enumerate() {
-        match *token {
-            "filesystemName" => index.filesystem_name = Some(i),
-            "filesetName" => index.fileset_name = Some(i),
-            "isInodeSpaceOwner" => index.is_inode_space_owner = Some(i),
-            "maxInodes" => index.max_inodes = Some(i),
-            "allocInodes" => index.alloc_inodes = Some(i),
-            "comment" => index.comment = Some(i),
-            _ => {}
-        }
+impl FromRecord for Fileset {
+    fn from_record(record: &Record) -> Result<Self> {
+        let filesystem_name = record.get("filesystemName")?.into();
+        let name = record.get("filesetName")?.into();
+
+        let is_inode_space_owner =
+            record.parse::<MMBool>("isInodeSpaceOwner")?.as_bool();
+
+        let max_inodes = record.parse("maxInodes")?;
+        let alloc_inodes = record.parse("allocInodes")?;
+
+        let path = record.get("path")?.into();
+        let created = record.get("created")?.into();
+
+        let comment = record.get("comment")?;
+        let comment = Some(comment).filter(|s| !s.is_empty()).map(Into::into);
+
+        Ok(Self {
+            name,
+            filesystem_name,
+            is_inode_space_owner,
+            max_inodes,
+            alloc_inodes,
+            path,
+            created,
+            comment,
+        })
     }
 }
 
@@ -264,6 +203,8 @@ mod tests {
                 is_inode_space_owner: true,
                 max_inodes: 20_971_520,
                 alloc_inodes: 5_251_072,
+                path: "/gpfs1/public".into(),
+                created: "Fri Oct 29 14:18:40 2021".into(),
                 comment: None,
             })
         );
@@ -276,6 +217,8 @@ mod tests {
                 is_inode_space_owner: true,
                 max_inodes: 295_313_408,
                 alloc_inodes: 260_063_232,
+                path: "/gpfs1/work".into(),
+                created: "Fri Oct 29 14:07:35 2021".into(),
                 comment: None,
             })
         );
@@ -288,6 +231,8 @@ mod tests {
                 is_inode_space_owner: true,
                 max_inodes: 20_000_768,
                 alloc_inodes: 1_032_192,
+                path: "/gpfs1/data/foo".into(),
+                created: "Tue Dec  5 10:44:05 2023".into(),
                 comment: Some("end of project: 2026-11".into()),
             })
         );
@@ -300,6 +245,8 @@ mod tests {
                 is_inode_space_owner: true,
                 max_inodes: 20_971_520,
                 alloc_inodes: 5_251_072,
+                path: "/gpfs1/data/db".into(),
+                created: "Fri Oct 29 13:47:02 2021".into(),
                 comment: Some("end of project: 2042-12".into()),
             })
         );
@@ -312,6 +259,8 @@ mod tests {
                 is_inode_space_owner: false,
                 max_inodes: 0,
                 alloc_inodes: 0,
+                path: "/gpfs1/data/db/foo".into(),
+                created: "Mon Mar 18 13:41:34 2024".into(),
                 comment: Some("end of project: 2030-12".into()),
             })
         );
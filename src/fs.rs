@@ -1,54 +1,94 @@
 //! `mmlsfs` parsing.
 
+use std::collections::HashMap;
+use std::fmt::{self, Display};
 use std::io::BufRead;
-use std::process::Command;
+use std::str::FromStr;
 
-use anyhow::{Context, Result, anyhow};
+use crate::prom::{Family, ToMetrics};
+use crate::run::{Cmd, Runner};
+use crate::util::MMBool;
+use crate::{Error, Result};
 
 /// Returns the file system names.
 ///
 /// # Errors
 ///
 /// Returns an error if running `mmlsfs` fails or if parsing its output fails.
-pub fn names() -> Result<Vec<String>> {
-    let mut cmd = Command::new("mmlsfs");
-    cmd.args(["all", "-Y", "-B"]);
+pub fn names(runner: &dyn Runner) -> Result<Vec<String>> {
+    let cmd = Cmd::new("mmlsfs").args(["all", "-Y", "-B"]);
 
-    let output = cmd
-        .output()
-        .with_context(|| format!("error running: {cmd:?}"))?;
+    let output = runner.run_checked(&cmd)?;
 
-    let data = Filesystems::from_reader(output.stdout.as_slice())?;
-
-    let names = data.0.into_iter().map(|fs| fs.name).collect();
+    let names = Fields::from_reader(output.stdout.as_slice())?
+        .into_iter()
+        .map(|fields| fields.name)
+        .collect();
 
     Ok(names)
 }
 
+/// Returns all attributes of all file systems.
+///
+/// # Errors
+///
+/// Returns an error if running `mmlsfs` fails or if parsing its output fails.
+pub fn filesystems(runner: &dyn Runner) -> Result<Filesystems> {
+    let cmd = Cmd::new("mmlsfs").args(["all", "-Y"]);
+
+    let output = runner.run_checked(&cmd)?;
+
+    Filesystems::from_reader(output.stdout.as_slice())
+}
+
+/// Returns all attributes of a file system.
+///
+/// # Errors
+///
+/// Returns an error if running `mmlsfs` fails or if parsing its output fails.
+pub fn filesystem(runner: &dyn Runner, fs_name: &str) -> Result<Filesystem> {
+    let cmd = Cmd::new("mmlsfs").arg(fs_name).arg("-Y");
+
+    let output = runner.run_checked(&cmd)?;
+
+    Filesystems::from_reader(output.stdout.as_slice())?
+        .into_iter()
+        .next()
+        .ok_or_else(|| Error::NotFound(format!("file system {fs_name}")))
+}
+
+/// Returns the replication settings of a file system.
+///
+/// # Errors
+///
+/// Returns an error if running `mmlsfs` fails or if parsing its output fails.
+pub fn replication(runner: &dyn Runner, fs_name: &str) -> Result<Replication> {
+    let cmd = Cmd::new("mmlsfs")
+        .arg(fs_name)
+        .args(["-Y", "-m", "-M", "-r", "-R"]);
+
+    let output = runner.run_checked(&cmd)?;
+
+    Replication::from_reader(output.stdout.as_slice())
+}
+
 /// Parsed file systems.
 #[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Filesystems(Vec<Filesystem>);
 
 impl Filesystems {
     fn from_reader<Input: BufRead>(input: Input) -> Result<Self> {
-        let mut index = Index::default();
-        let mut fs = Self::default();
-
-        for line in input.lines() {
-            let line = line?;
-
-            let tokens = line.split(':').collect::<Vec<_>>();
-
-            if tokens[2] == "HEADER" {
-                index = Index::default();
-                header_to_index(&tokens, &mut index);
-            } else {
-                let entry = Filesystem::from_tokens(&tokens, &index)?;
-                fs.0.push(entry);
-            }
-        }
+        Fields::from_reader(input)?
+            .iter()
+            .map(Filesystem::from_fields)
+            .collect::<Result<_>>()
+            .map(Self)
+    }
 
-        Ok(fs)
+    /// Returns an [`Iterator`] over the file systems.
+    pub fn iter(&self) -> std::slice::Iter<'_, Filesystem> {
+        self.0.iter()
     }
 }
 
@@ -61,10 +101,37 @@ impl IntoIterator for Filesystems {
     }
 }
 
+impl<'a> IntoIterator for &'a Filesystems {
+    type Item = &'a Filesystem;
+    type IntoIter = std::slice::Iter<'a, Filesystem>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
 /// File system data.
 #[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Filesystem {
     name: String,
+    block_size: u64,
+    min_fragment_size: u64,
+    inode_size: u64,
+    indirect_block_size: u64,
+    replication: Replication,
+    quotas_accounting: QuotaTypes,
+    quotas_enforced: QuotaTypes,
+    default_quotas: QuotaTypes,
+    per_fileset_quotas: Option<bool>,
+    fileset_df: Option<bool>,
+    version: String,
+    original_version: Option<String>,
+    create_time: String,
+    num_nodes: u64,
+    max_inodes: u64,
+    mount_point: String,
+    automount: Automount,
 }
 
 impl Filesystem {
@@ -73,33 +140,474 @@ impl Filesystem {
     pub fn name(&self) -> &str {
         &self.name
     }
+
+    /// Returns the block size in bytes.
+    #[must_use]
+    pub const fn block_size(&self) -> u64 {
+        self.block_size
+    }
+
+    /// Returns the minimum fragment (subblock) size in bytes.
+    #[must_use]
+    pub const fn min_fragment_size(&self) -> u64 {
+        self.min_fragment_size
+    }
+
+    /// Returns the inode size in bytes.
+    #[must_use]
+    pub const fn inode_size(&self) -> u64 {
+        self.inode_size
+    }
+
+    /// Returns the indirect block size in bytes.
+    #[must_use]
+    pub const fn indirect_block_size(&self) -> u64 {
+        self.indirect_block_size
+    }
+
+    /// Returns the replication settings.
+    #[must_use]
+    pub const fn replication(&self) -> Replication {
+        self.replication
+    }
+
+    /// Returns the quota types with accounting enabled.
+    #[must_use]
+    pub const fn quotas_accounting(&self) -> QuotaTypes {
+        self.quotas_accounting
+    }
+
+    /// Returns the enforced quota types.
+    #[must_use]
+    pub const fn quotas_enforced(&self) -> QuotaTypes {
+        self.quotas_enforced
+    }
+
+    /// Returns the quota types with default quotas enabled.
+    #[must_use]
+    pub const fn default_quotas(&self) -> QuotaTypes {
+        self.default_quotas
+    }
+
+    /// Returns `true` if user and group quotas are enforced per fileset.
+    ///
+    /// Returns `None` if the release doesn't report it.
+    #[must_use]
+    pub const fn per_fileset_quotas(&self) -> Option<bool> {
+        self.per_fileset_quotas
+    }
+
+    /// Returns `true` if `df` reports fileset quotas, i.e. `--filesetdf`.
+    ///
+    /// Returns `None` if the release doesn't report it.
+    #[must_use]
+    pub const fn fileset_df(&self) -> Option<bool> {
+        self.fileset_df
+    }
+
+    /// Returns the current file system version, e.g. `33.00 (5.1.9.0)`.
+    #[must_use]
+    pub fn version(&self) -> &str {
+        &self.version
+    }
+
+    /// Returns the file system version it was created with.
+    ///
+    /// Returns `None` if the release doesn't report it.
+    #[must_use]
+    pub fn original_version(&self) -> Option<&str> {
+        self.original_version.as_deref()
+    }
+
+    /// Returns the creation time as printed by `mmlsfs`.
+    #[must_use]
+    pub fn create_time(&self) -> &str {
+        &self.create_time
+    }
+
+    /// Returns the estimated number of nodes that mount the file system.
+    #[must_use]
+    pub const fn num_nodes(&self) -> u64 {
+        self.num_nodes
+    }
+
+    /// Returns the maximum number of inodes.
+    #[must_use]
+    pub const fn max_inodes(&self) -> u64 {
+        self.max_inodes
+    }
+
+    /// Returns the default mount point.
+    #[must_use]
+    pub fn mount_point(&self) -> &str {
+        &self.mount_point
+    }
+
+    /// Returns when the file system is mounted automatically.
+    #[must_use]
+    pub const fn automount(&self) -> Automount {
+        self.automount
+    }
+
+    /// Parses the attributes of a file system.
+    ///
+    /// Attributes that older releases don't report are optional, so that a
+    /// single missing one doesn't fail all file systems.
+    fn from_fields(fields: &Fields) -> Result<Self> {
+        Ok(Self {
+            name: fields.name.clone(),
+            block_size: fields.parse("blockSize")?,
+            min_fragment_size: fields.parse("minFragmentSize")?,
+            inode_size: fields.parse("inodeSize")?,
+            indirect_block_size: fields.parse("indirectBlockSize")?,
+            replication: Replication::from_fields(fields)?,
+            quotas_accounting: fields.parse("quotasAccountingEnabled")?,
+            quotas_enforced: fields.parse("quotasEnforced")?,
+            default_quotas: fields.parse("defaultQuotasEnabled")?,
+            per_fileset_quotas: fields
+                .parse_opt::<MMBool>("perfilesetQuotas")?
+                .map(|quotas| quotas.as_bool()),
+            fileset_df: fields
+                .parse_opt::<MMBool>("filesetdfEnabled")?
+                .map(|df| df.as_bool()),
+            version: fields.get("filesystemVersion")?.into(),
+            original_version: fields
+                .get_opt("filesystemVersionOriginal")
+                .map(Into::into),
+            create_time: fields.get("create-time")?.into(),
+            num_nodes: fields.parse("numNodes")?,
+            max_inodes: fields.parse("maxNumberOfInodes")?,
+            mount_point: fields.get("defaultMountPoint")?.into(),
+            automount: fields.parse("automaticMountOption")?,
+        })
+    }
+}
+
+/// Replication settings of a file system.
+#[derive(
+    Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Default,
+)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Replication {
+    default_metadata: u64,
+    max_metadata: u64,
+    default_data: u64,
+    max_data: u64,
+}
+
+impl Replication {
+    /// Returns the default number of metadata replicas.
+    #[must_use]
+    pub const fn default_metadata(&self) -> u64 {
+        self.default_metadata
+    }
+
+    /// Returns the maximum number of metadata replicas.
+    #[must_use]
+    pub const fn max_metadata(&self) -> u64 {
+        self.max_metadata
+    }
+
+    /// Returns the default number of data replicas.
+    #[must_use]
+    pub const fn default_data(&self) -> u64 {
+        self.default_data
+    }
+
+    /// Returns the maximum number of data replicas.
+    #[must_use]
+    pub const fn max_data(&self) -> u64 {
+        self.max_data
+    }
+
+    pub(crate) fn from_reader<Input: BufRead>(input: Input) -> Result<Self> {
+        let fields = Fields::from_reader(input)?;
+
+        let fields = fields
+            .first()
+            .ok_or_else(|| Error::NotFound("mmlsfs file system".into()))?;
+
+        Self::from_fields(fields)
+    }
+
+    fn from_fields(fields: &Fields) -> Result<Self> {
+        Ok(Self {
+            default_metadata: fields.parse("defaultMetadataReplicas")?,
+            max_metadata: fields.parse("maxMetadataReplicas")?,
+            default_data: fields.parse("defaultDataReplicas")?,
+            max_data: fields.parse("maxDataReplicas")?,
+        })
+    }
+}
+
+/// Quota types, e.g. the ones that are enforced.
+#[derive(
+    Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Default,
+)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct QuotaTypes {
+    user: bool,
+    group: bool,
+    fileset: bool,
+}
+
+impl QuotaTypes {
+    /// Returns `true` for user quotas.
+    #[must_use]
+    pub const fn user(&self) -> bool {
+        self.user
+    }
+
+    /// Returns `true` for group quotas.
+    #[must_use]
+    pub const fn group(&self) -> bool {
+        self.group
+    }
+
+    /// Returns `true` for fileset quotas.
+    #[must_use]
+    pub const fn fileset(&self) -> bool {
+        self.fileset
+    }
+}
+
+impl Display for QuotaTypes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let types = [
+            (self.user, "user"),
+            (self.group, "group"),
+            (self.fileset, "fileset"),
+        ]
+        .into_iter()
+        .filter_map(|(enabled, name)| enabled.then_some(name))
+        .collect::<Vec<_>>();
+
+        if types.is_empty() {
+            write!(f, "none")
+        } else {
+            write!(f, "{}", types.join(";"))
+        }
+    }
+}
+
+impl FromStr for QuotaTypes {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut types = Self::default();
+
+        for name in s.split(';') {
+            match name {
+                "none" | "" => {}
+                "user" => types.user = true,
+                "group" => types.group = true,
+                "fileset" => types.fileset = true,
+                unknown => {
+                    return Err(Error::InvalidValue {
+                        kind: "quota type",
+                        value: unknown.into(),
+                    });
+                }
+            }
+        }
+
+        Ok(types)
+    }
+}
+
+/// When a file system is mounted automatically.
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum Automount {
+    /// Mounted when the daemon starts.
+    Yes,
+
+    /// Not mounted automatically.
+    No,
+
+    /// Mounted on first access.
+    #[cfg_attr(feature = "serde", serde(rename = "automount"))]
+    OnAccess,
+}
+
+impl Display for Automount {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            Self::Yes => "yes",
+            Self::No => "no",
+            Self::OnAccess => "automount",
+        };
+
+        write!(f, "{s}")
+    }
+}
+
+impl FromStr for Automount {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "yes" => Ok(Self::Yes),
+            "no" => Ok(Self::No),
+            "automount" => Ok(Self::OnAccess),
+            unknown => Err(Error::InvalidValue {
+                kind: "automount option",
+                value: unknown.into(),
+            }),
+        }
+    }
 }
 
 // ----------------------------------------------------------------------------
 // boiler-platy parsing
 // ----------------------------------------------------------------------------
 
-impl Filesystem {
-    fn from_tokens(tokens: &[&str], index: &Index) -> Result<Self> {
-        let name_index = index
-            .name
-            .ok_or_else(|| anyhow!("no filesystem name index"))?;
-        let name = tokens[name_index].into();
+/// The `fieldName` and `data` pairs of a single file system.
+///
+/// Unlike most `-Y` output, `mmlsfs` prints one record per attribute instead
+/// of one record per file system.
+struct Fields {
+    name: String,
+    line: usize,
+    values: HashMap<String, (usize, String)>,
+}
+
+impl Fields {
+    /// Groups the records by file system, in order of appearance.
+    fn from_reader<Input: BufRead>(input: Input) -> Result<Vec<Self>> {
+        let mut all: Vec<Self> = vec![];
+
+        for record in crate::parse::records(input) {
+            let record = record?;
+
+            let name = record.get("deviceName")?;
+            let field = record.get("fieldName")?;
+            let data = record.get("data")?;
+
+            let index = all
+                .iter()
+                .position(|fields| fields.name == name)
+                .unwrap_or_else(|| {
+                    all.push(Self {
+                        name: name.into(),
+                        line: 0,
+                        values: HashMap::new(),
+                    });
+
+                    all.len() - 1
+                });
+
+            let fields = &mut all[index];
+            fields.line = record.line();
+            fields
+                .values
+                .insert(field.into(), (record.line(), data.into()));
+        }
 
-        Ok(Self { name })
+        Ok(all)
+    }
+
+    /// Returns the value of `field`.
+    ///
+    /// A missing field is reported at the last record of the file system.
+    fn get(&self, field: &str) -> Result<&str> {
+        self.values
+            .get(field)
+            .map(|(_, value)| value.as_str())
+            .ok_or_else(|| Error::MissingField {
+                line: self.line,
+                field: field.into(),
+            })
+    }
+
+    /// Returns the value of `field`, if it exists.
+    fn get_opt(&self, field: &str) -> Option<&str> {
+        self.values.get(field).map(|(_, value)| value.as_str())
     }
-}
 
-#[derive(Debug, Default)]
-struct Index {
-    name: Option<usize>,
+    fn parse<T>(&self, field: &str) -> Result<T>
+    where
+        T: FromStr,
+        T::Err: Display,
+    {
+        self.parse_opt(field)?.ok_or_else(|| Error::MissingField {
+            line: self.line,
+            field: field.into(),
+        })
+    }
+
+    /// Parses the value of `field`, if it exists.
+    fn parse_opt<T>(&self, field: &str) -> Result<Option<T>>
+    where
+        T: FromStr,
+        T::Err: Display,
+    {
+        let Some((line, value)) = self.values.get(field) else {
+            return Ok(None);
+        };
+
+        value
+            .parse()
+            .map(Some)
+            .map_err(|error: T::Err| Error::Parse {
+                line: *line,
+                field: field.into(),
+                value: value.clone(),
+                message: error.to_string(),
+            })
+    }
 }
 
-fn header_to_index(tokens: &[&str], index: &mut Index) {
-    for (i, token) in tokens.iter().enumerate() {
-        if *token == "deviceName" {
-            index.name = Some(i);
+// ----------------------------------------------------------------------------
+// prometheus
+// ----------------------------------------------------------------------------
+
+impl ToMetrics for Filesystems {
+    fn to_metrics(&self) -> Vec<Family> {
+        let mut info = Family::gauge("gpfs_fs_info", "GPFS file system info.");
+        let mut max_inodes = Family::gauge(
+            "gpfs_fs_max_inodes",
+            "GPFS file system maximum inodes.",
+        );
+        let mut data_replicas = Family::gauge(
+            "gpfs_fs_data_replicas",
+            "GPFS file system default data replicas.",
+        );
+        let mut metadata_replicas = Family::gauge(
+            "gpfs_fs_metadata_replicas",
+            "GPFS file system default metadata replicas.",
+        );
+
+        for fs in self {
+            let labels = [("fs", fs.name())];
+
+            // empty, i.e. no label, if the release doesn't report it
+            let fileset_df = fs
+                .fileset_df()
+                .map_or_else(String::new, |df| df.to_string());
+
+            info.sample(
+                &[
+                    ("fs", fs.name()),
+                    ("version", fs.version()),
+                    ("mount_point", fs.mount_point()),
+                    ("automount", &fs.automount().to_string()),
+                    ("block_size", &fs.block_size().to_string()),
+                    ("inode_size", &fs.inode_size().to_string()),
+                    ("quotas_enforced", &fs.quotas_enforced().to_string()),
+                    ("fileset_df", &fileset_df),
+                ],
+                1,
+            );
+
+            max_inodes.sample(&labels, fs.max_inodes());
+            data_replicas.sample(&labels, fs.replication().default_data());
+            metadata_replicas
+                .sample(&labels, fs.replication().default_metadata());
         }
+
+        vec![info, max_inodes, data_replicas, metadata_replicas]
     }
 }
 
@@ -110,28 +618,169 @@ fn header_to_index(tokens: &[&str], index: &mut Index) {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::prom::ToText;
 
     #[test]
-    fn parse() {
+    fn names() {
         let input = include_str!("fs-example.in");
 
+        let names = Fields::from_reader(input.as_bytes())
+            .unwrap()
+            .into_iter()
+            .map(|fields| fields.name)
+            .collect::<Vec<_>>();
+
+        assert_eq!(names, ["gpfs1", "gpfs2"]);
+    }
+
+    #[test]
+    fn parse() {
+        let input = include_str!("fs-full-example.in");
+
         let fs = Filesystems::from_reader(input.as_bytes()).unwrap();
         let mut fs = fs.0.into_iter();
 
         assert_eq!(
             fs.next(),
             Some(Filesystem {
-                name: "gpfs1".into()
+                name: "gpfs1".into(),
+                block_size: 8_388_608,
+                min_fragment_size: 8192,
+                inode_size: 4096,
+                indirect_block_size: 32768,
+                replication: Replication {
+                    default_metadata: 2,
+                    max_metadata: 3,
+                    default_data: 1,
+                    max_data: 3,
+                },
+                quotas_accounting: QuotaTypes {
+                    user: true,
+                    group: true,
+                    fileset: true,
+                },
+                quotas_enforced: QuotaTypes {
+                    user: true,
+                    group: true,
+                    fileset: true,
+                },
+                default_quotas: QuotaTypes::default(),
+                per_fileset_quotas: Some(true),
+                fileset_df: Some(true),
+                version: "33.00 (5.1.9.0)".into(),
+                original_version: Some("23.00 (5.0.5.0)".into()),
+                create_time: "Fri Oct 29 12:13:52 2021".into(),
+                num_nodes: 32,
+                max_inodes: 536_870_912,
+                mount_point: "/gpfs1".into(),
+                automount: Automount::Yes,
             })
         );
 
-        assert_eq!(
-            fs.next(),
-            Some(Filesystem {
-                name: "gpfs2".into()
+        let gpfs2 = fs.next().unwrap();
+        assert_eq!(gpfs2.name(), "gpfs2");
+        assert_eq!(gpfs2.block_size(), 524_288);
+        assert_eq!(gpfs2.quotas_enforced(), QuotaTypes::default());
+        assert_eq!(gpfs2.fileset_df(), Some(false));
+        assert_eq!(gpfs2.mount_point(), "/gpfs/scratch");
+        assert_eq!(gpfs2.automount(), Automount::OnAccess);
+
+        assert_eq!(fs.next(), None);
+    }
+
+    #[test]
+    fn parse_missing_field() {
+        let input = include_str!("fs-example.in");
+
+        let error = Filesystems::from_reader(input.as_bytes()).unwrap_err();
+
+        assert!(matches!(
+            error,
+            Error::MissingField { line: 2, ref field }
+                if field == "minFragmentSize"
+        ));
+    }
+
+    #[test]
+    fn parse_optional_fields() {
+        // older releases don't report these
+        let input = include_str!("fs-full-example.in")
+            .lines()
+            .filter(|line| {
+                !line.contains(":perfilesetQuotas:")
+                    && !line.contains(":filesetdfEnabled:")
+                    && !line.contains(":filesystemVersionOriginal:")
             })
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        let fs = Filesystems::from_reader(input.as_bytes()).unwrap();
+        assert_eq!(fs.0.len(), 2);
+
+        let gpfs1 = &fs.0[0];
+        assert_eq!(gpfs1.per_fileset_quotas(), None);
+        assert_eq!(gpfs1.fileset_df(), None);
+        assert_eq!(gpfs1.original_version(), None);
+        assert_eq!(gpfs1.version(), "33.00 (5.1.9.0)");
+    }
+
+    #[test]
+    fn quota_types() {
+        let types = "user;fileset".parse::<QuotaTypes>().unwrap();
+        assert!(types.user() && !types.group() && types.fileset());
+        assert_eq!(types.to_string(), "user;fileset");
+
+        let none = "none".parse::<QuotaTypes>().unwrap();
+        assert_eq!(none, QuotaTypes::default());
+        assert_eq!(none.to_string(), "none");
+
+        assert!("usr".parse::<QuotaTypes>().is_err());
+    }
+
+    #[test]
+    fn prometheus() {
+        let input = include_str!("fs-full-example.in");
+        let fs = Filesystems::from_reader(input.as_bytes()).unwrap();
+
+        let mut output = vec![];
+        fs.to_prom(&mut output).unwrap();
+
+        let metrics = std::str::from_utf8(output.as_slice()).unwrap();
+
+        let expected = include_str!("fs-example.prom");
+        assert_eq!(metrics, expected);
+    }
+
+    #[test]
+    fn replication() {
+        let input = include_str!("fs-replication-example.in");
+
+        let replication = Replication::from_reader(input.as_bytes()).unwrap();
+
+        assert_eq!(
+            replication,
+            Replication {
+                default_metadata: 2,
+                max_metadata: 3,
+                default_data: 1,
+                max_data: 3,
+            }
         );
+    }
 
-        assert_eq!(fs.next(), None);
+    #[test]
+    fn replication_missing_field() {
+        let input = "\
+mmlsfs::HEADER:version:reserved:reserved:deviceName:fieldName:data:remarks:
+mmlsfs::0:1:::gpfs1:defaultMetadataReplicas:2::
+";
+
+        let error = Replication::from_reader(input.as_bytes()).unwrap_err();
+
+        assert!(matches!(
+            error,
+            Error::MissingField { line: 2, ref field }
+                if field == "maxMetadataReplicas"
+        ));
     }
 }
@@ -0,0 +1,681 @@
+//! `mmhealth` parsing.
+
+use std::fmt::Display;
+use std::io::BufRead;
+use std::str::FromStr;
+
+use crate::Result;
+
+use crate::parse::{FromRecord, Record};
+use crate::prom::{Family, ToMetrics};
+use crate::run::{Cmd, Runner};
+use crate::util::serde_via_str;
+
+/// Returns the health of the components of the local node.
+///
+/// # Errors
+///
+/// Returns an error if running `mmhealth` fails or if parsing its output fails.
+pub fn node(runner: &dyn Runner) -> Result<NodeHealth> {
+    let cmd = Cmd::new("mmhealth").args(["node", "show", "-Y"]);
+
+    let output = runner.run_checked(&cmd)?;
+
+    NodeHealth::from_reader(output.stdout.as_slice())
+}
+
+/// Returns the health of the components of all nodes, summarized per
+/// component.
+///
+/// # Errors
+///
+/// Returns an error if running `mmhealth` fails or if parsing its output fails.
+pub fn cluster(runner: &dyn Runner) -> Result<ClusterHealth> {
+    let cmd = Cmd::new("mmhealth").args(["cluster", "show", "-Y"]);
+
+    let output = runner.run_checked(&cmd)?;
+
+    ClusterHealth::from_reader(output.stdout.as_slice())
+}
+
+// ----------------------------------------------------------------------------
+// node
+// ----------------------------------------------------------------------------
+
+/// Health of the components of a node.
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct NodeHealth {
+    states: Vec<ComponentState>,
+    events: Vec<Event>,
+}
+
+impl NodeHealth {
+    /// Returns the states of the components and their entities.
+    #[must_use]
+    pub fn states(&self) -> &[ComponentState] {
+        &self.states
+    }
+
+    /// Returns the active events.
+    #[must_use]
+    pub fn events(&self) -> &[Event] {
+        &self.events
+    }
+
+    /// Returns the active events that are not hidden.
+    pub fn visible_events(&self) -> impl Iterator<Item = &Event> {
+        self.events.iter().filter(|event| !event.hidden)
+    }
+
+    fn from_reader<Input: BufRead>(input: Input) -> Result<Self> {
+        let mut health = Self::default();
+
+        for record in crate::parse::records(input) {
+            let record = record?;
+
+            match record.section() {
+                "State" => {
+                    health.states.push(ComponentState::from_record(&record)?);
+                }
+                "Event" => {
+                    health.events.push(Event::from_record(&record)?);
+                }
+                _ => {}
+            }
+        }
+
+        Ok(health)
+    }
+}
+
+/// State of an entity of a component, e.g. a network interface of the
+/// `NETWORK` component.
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ComponentState {
+    node: String,
+    component: String,
+    entity_name: String,
+    entity_type: String,
+    state: HealthState,
+    last_change: String,
+}
+
+impl ComponentState {
+    /// Returns the node name.
+    #[must_use]
+    pub fn node(&self) -> &str {
+        &self.node
+    }
+
+    /// Returns the component, e.g. `NETWORK`.
+    #[must_use]
+    pub fn component(&self) -> &str {
+        &self.component
+    }
+
+    /// Returns the entity name, e.g. `ib0`.
+    #[must_use]
+    pub fn entity_name(&self) -> &str {
+        &self.entity_name
+    }
+
+    /// Returns the entity type, e.g. `NIC`.
+    #[must_use]
+    pub fn entity_type(&self) -> &str {
+        &self.entity_type
+    }
+
+    /// Returns the state.
+    #[must_use]
+    pub const fn state(&self) -> &HealthState {
+        &self.state
+    }
+
+    /// Returns when the state last changed.
+    #[must_use]
+    pub fn last_change(&self) -> &str {
+        &self.last_change
+    }
+}
+
+/// Active health event.
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Event {
+    node: String,
+    component: String,
+    entity_name: String,
+    entity_type: String,
+    name: String,
+    arguments: String,
+    active_since: String,
+    identifier: String,
+    hidden: bool,
+    severity: Option<String>,
+}
+
+impl Event {
+    /// Returns the node name.
+    #[must_use]
+    pub fn node(&self) -> &str {
+        &self.node
+    }
+
+    /// Returns the component, e.g. `NETWORK`.
+    #[must_use]
+    pub fn component(&self) -> &str {
+        &self.component
+    }
+
+    /// Returns the entity name, e.g. `ib0`.
+    #[must_use]
+    pub fn entity_name(&self) -> &str {
+        &self.entity_name
+    }
+
+    /// Returns the entity type, e.g. `NIC`.
+    #[must_use]
+    pub fn entity_type(&self) -> &str {
+        &self.entity_type
+    }
+
+    /// Returns the event name, e.g. `ib_rdma_nic_down`.
+    #[must_use]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the event arguments.
+    #[must_use]
+    pub fn arguments(&self) -> &str {
+        &self.arguments
+    }
+
+    /// Returns since when the event is active.
+    #[must_use]
+    pub fn active_since(&self) -> &str {
+        &self.active_since
+    }
+
+    /// Returns the identifier that distinguishes multiple events of the same
+    /// name and entity, e.g. `gpfs1/system`.
+    #[must_use]
+    pub fn identifier(&self) -> &str {
+        &self.identifier
+    }
+
+    /// Returns `true` if the event was hidden with `mmhealth event hide`.
+    #[must_use]
+    pub const fn is_hidden(&self) -> bool {
+        self.hidden
+    }
+
+    /// Returns the severity, e.g. `WARNING`, if reported by `mmhealth`.
+    #[must_use]
+    pub fn severity(&self) -> Option<&str> {
+        self.severity.as_deref()
+    }
+}
+
+/// Health state of a component or entity.
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+#[non_exhaustive]
+pub enum HealthState {
+    /// Working as expected.
+    Healthy,
+
+    /// Working, but with problems, e.g. reduced redundancy.
+    Degraded,
+
+    /// Not working.
+    Failed,
+
+    /// Not working because a component it depends on is not working.
+    Depend,
+
+    /// Stopped on purpose.
+    Stopped,
+
+    /// Suspended on purpose.
+    Suspended,
+
+    /// Not monitored.
+    Disabled,
+
+    /// Being checked, e.g. after startup.
+    Checking,
+
+    /// Working, but with configuration tips.
+    Tips,
+
+    /// Unknown state.
+    Unknown(String),
+}
+
+impl HealthState {
+    const ALL: [&'static str; 10] = [
+        "healthy",
+        "degraded",
+        "failed",
+        "depend",
+        "stopped",
+        "suspended",
+        "disabled",
+        "checking",
+        "tips",
+        "unknown",
+    ];
+
+    /// Returns the state name, with all unknown states named `unknown`.
+    const fn name(&self) -> &'static str {
+        match self {
+            Self::Healthy => "healthy",
+            Self::Degraded => "degraded",
+            Self::Failed => "failed",
+            Self::Depend => "depend",
+            Self::Stopped => "stopped",
+            Self::Suspended => "suspended",
+            Self::Disabled => "disabled",
+            Self::Checking => "checking",
+            Self::Tips => "tips",
+            Self::Unknown(_) => "unknown",
+        }
+    }
+}
+
+impl Display for HealthState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            Self::Unknown(s) => s.as_str(),
+            state => state.name(),
+        };
+
+        write!(f, "{s}")
+    }
+}
+
+impl FromStr for HealthState {
+    type Err = crate::Error;
+
+    /// Parses states case-insensitively, i.e. both the upper case ones of
+    /// `mmhealth` and the displayed lower case ones.
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "healthy" => Ok(Self::Healthy),
+            "degraded" => Ok(Self::Degraded),
+            "failed" => Ok(Self::Failed),
+            "depend" => Ok(Self::Depend),
+            "stopped" => Ok(Self::Stopped),
+            "suspended" => Ok(Self::Suspended),
+            "disabled" => Ok(Self::Disabled),
+            "checking" => Ok(Self::Checking),
+            "tips" => Ok(Self::Tips),
+            unknown => Ok(Self::Unknown(unknown.into())),
+        }
+    }
+}
+
+serde_via_str!(HealthState);
+
+// ----------------------------------------------------------------------------
+// cluster
+// ----------------------------------------------------------------------------
+
+/// Health of all nodes, summarized per component.
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ClusterHealth(Vec<Summary>);
+
+impl ClusterHealth {
+    fn from_reader<Input: BufRead>(input: Input) -> Result<Self> {
+        let mut health = Self::default();
+
+        for record in crate::parse::records(input) {
+            let record = record?;
+
+            if record.section() == "Summary" {
+                health.0.push(Summary::from_record(&record)?);
+            }
+        }
+
+        Ok(health)
+    }
+
+    /// Returns the component summaries.
+    #[must_use]
+    pub fn summaries(&self) -> &[Summary] {
+        &self.0
+    }
+}
+
+/// Number of nodes per state of a component.
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Summary {
+    component: String,
+    total: u64,
+    failed: u64,
+    degraded: u64,
+    healthy: u64,
+    other: u64,
+}
+
+impl Summary {
+    /// Returns the component, e.g. `NETWORK`.
+    #[must_use]
+    pub fn component(&self) -> &str {
+        &self.component
+    }
+
+    /// Returns the number of nodes with this component.
+    #[must_use]
+    pub const fn total(&self) -> u64 {
+        self.total
+    }
+
+    /// Returns the number of nodes where the component failed.
+    #[must_use]
+    pub const fn failed(&self) -> u64 {
+        self.failed
+    }
+
+    /// Returns the number of nodes where the component is degraded.
+    #[must_use]
+    pub const fn degraded(&self) -> u64 {
+        self.degraded
+    }
+
+    /// Returns the number of nodes where the component is healthy.
+    #[must_use]
+    pub const fn healthy(&self) -> u64 {
+        self.healthy
+    }
+
+    /// Returns the number of nodes where the component is in any other
+    /// state, e.g. `TIPS`.
+    #[must_use]
+    pub const fn other(&self) -> u64 {
+        self.other
+    }
+}
+
+// ----------------------------------------------------------------------------
+// prometheus
+// ----------------------------------------------------------------------------
+
+impl ToMetrics for NodeHealth {
+    fn to_metrics(&self) -> Vec<Family> {
+        let mut state = Family::gauge(
+            "gpfs_health_component_state",
+            "GPFS health state of component entity.",
+        );
+
+        for component in &self.states {
+            for name in HealthState::ALL {
+                state.sample(
+                    &[
+                        ("component", &component.component),
+                        ("entity", &component.entity_name),
+                        ("state", name),
+                    ],
+                    component.state.name() == name,
+                );
+            }
+        }
+
+        let mut event = Family::gauge(
+            "gpfs_health_event",
+            "GPFS health number of active events.",
+        );
+
+        // events are usually distinct by identifier, but count them anyway
+        let mut events: Vec<([&str; 5], usize)> = vec![];
+
+        for e in self.visible_events() {
+            let labels = [
+                &e.component,
+                &e.entity_name,
+                &e.name,
+                &e.identifier,
+                e.severity().unwrap_or_default(),
+            ];
+
+            match events.iter_mut().find(|(other, _)| *other == labels) {
+                Some((_, count)) => *count += 1,
+                None => events.push((labels, 1)),
+            }
+        }
+
+        for ([component, entity, name, identifier, severity], count) in events
+        {
+            event.sample(
+                &[
+                    ("component", component),
+                    ("entity", entity),
+                    ("event", name),
+                    ("identifier", identifier),
+                    ("severity", severity),
+                ],
+                count,
+            );
+        }
+
+        vec![state, event]
+    }
+}
+
+impl ToMetrics for ClusterHealth {
+    fn to_metrics(&self) -> Vec<Family> {
+        let mut nodes = Family::gauge(
+            "gpfs_health_cluster_nodes",
+            "GPFS health number of nodes per component state.",
+        );
+
+        for summary in &self.0 {
+            for (state, value) in [
+                ("failed", summary.failed),
+                ("degraded", summary.degraded),
+                ("healthy", summary.healthy),
+                ("other", summary.other),
+            ] {
+                nodes.sample(
+                    &[("component", &summary.component), ("state", state)],
+                    value,
+                );
+            }
+        }
+
+        vec![nodes]
+    }
+}
+
+// ----------------------------------------------------------------------------
+// boiler-platy parsing
+// ----------------------------------------------------------------------------
+
+impl FromRecord for ComponentState {
+    fn from_record(record: &Record) -> Result<Self> {
+        let node = record.get("node")?.into();
+        let component = record.get("component")?.into();
+        let entity_name = record.get("entityname")?.into();
+        let entity_type = record.get("entitytype")?.into();
+        let state = record.parse("status")?;
+        let last_change = record.get("laststatuschange")?.into();
+
+        Ok(Self {
+            node,
+            component,
+            entity_name,
+            entity_type,
+            state,
+            last_change,
+        })
+    }
+}
+
+impl FromRecord for Event {
+    fn from_record(record: &Record) -> Result<Self> {
+        let node = record.get("node")?.into();
+        let component = record.get("component")?.into();
+        let entity_name = record.get("entityname")?.into();
+        let entity_type = record.get("entitytype")?.into();
+        let name = record.get("event")?.into();
+        let arguments = record.get("arguments")?.into();
+        let active_since = record.get("activesince")?.into();
+        let identifier = record.get("identifier")?.into();
+        let hidden = record.get("ishidden")? == "yes";
+
+        // not reported by all releases
+        let severity = record
+            .get_opt("severity")
+            .filter(|severity| !severity.is_empty())
+            .map(Into::into);
+
+        Ok(Self {
+            node,
+            component,
+            entity_name,
+            entity_type,
+            name,
+            arguments,
+            active_since,
+            identifier,
+            hidden,
+            severity,
+        })
+    }
+}
+
+impl FromRecord for Summary {
+    fn from_record(record: &Record) -> Result<Self> {
+        let component = record.get("component")?.into();
+        let total = record.parse("total")?;
+        let failed = record.parse("failed")?;
+        let degraded = record.parse("degraded")?;
+        let healthy = record.parse("healthy")?;
+        let other = record.parse("other")?;
+
+        Ok(Self {
+            component,
+            total,
+            failed,
+            degraded,
+            healthy,
+            other,
+        })
+    }
+}
+
+// ----------------------------------------------------------------------------
+// tests
+// ----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prom::ToText;
+
+    #[test]
+    fn parse_node() {
+        let input = include_str!("health-node-example.in");
+
+        let health = NodeHealth::from_reader(input.as_bytes()).unwrap();
+        assert_eq!(health.states().len(), 7);
+        assert_eq!(health.events().len(), 4);
+        assert_eq!(health.visible_events().count(), 3);
+
+        assert_eq!(
+            health.states()[4],
+            ComponentState {
+                node: "filer1.example.org".into(),
+                component: "NETWORK".into(),
+                entity_name: "ib1".into(),
+                entity_type: "NIC".into(),
+                state: HealthState::Failed,
+                last_change: "2026-10-14 09:12:45.123456 CEST".into(),
+            }
+        );
+
+        let event = &health.events()[0];
+        assert_eq!(event.name(), "ib_rdma_nic_down");
+        assert_eq!(event.arguments(), "ib1");
+        assert_eq!(event.identifier(), "ib1");
+        assert_eq!(event.severity(), Some("ERROR"));
+        assert!(!event.is_hidden());
+
+        // same event and entity, distinct by identifier
+        assert_eq!(health.events()[1].name(), health.events()[2].name());
+        assert_eq!(health.events()[2].identifier(), "gpfs2/system");
+
+        assert!(health.events()[3].is_hidden());
+    }
+
+    #[test]
+    fn parse_node_without_severity() {
+        let input = "\
+mmhealth:Event:HEADER:version:reserved:reserved:node:component:entityname:entitytype:event:arguments:activesince:identifier:ishidden:
+mmhealth:Event:0:1:::node001:GPFS:node001:NODE:gpfs_pagepool_small::2026-10-01 08%3A00%3A12.654321 CEST:gpfs_pagepool_small:no:
+";
+
+        let health = NodeHealth::from_reader(input.as_bytes()).unwrap();
+        assert_eq!(health.events()[0].severity(), None);
+    }
+
+    #[test]
+    fn parse_cluster() {
+        let input = include_str!("health-cluster-example.in");
+
+        let health = ClusterHealth::from_reader(input.as_bytes()).unwrap();
+        assert_eq!(health.summaries().len(), 4);
+
+        assert_eq!(
+            health.summaries()[0],
+            Summary {
+                component: "NODE".into(),
+                total: 6,
+                failed: 1,
+                degraded: 1,
+                healthy: 4,
+                other: 0,
+            }
+        );
+    }
+
+    #[test]
+    fn prometheus() {
+        let input = include_str!("health-node-example.in");
+        let health = NodeHealth::from_reader(input.as_bytes()).unwrap();
+
+        let mut output = vec![];
+        health.to_prom(&mut output).unwrap();
+
+        let metrics = std::str::from_utf8(output.as_slice()).unwrap();
+
+        let expected = include_str!("health-example.prom");
+        assert_eq!(metrics, expected);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde() {
+        for (value, name) in [
+            (HealthState::Healthy, "healthy"),
+            (HealthState::Tips, "tips"),
+            (HealthState::Unknown("foo".into()), "foo"),
+        ] {
+            assert_eq!(value.to_string(), name);
+
+            let json = serde_json::to_string(&value).unwrap();
+            assert_eq!(json, format!("\"{name}\""));
+
+            let parsed: HealthState = serde_json::from_str(&json).unwrap();
+            assert_eq!(parsed, value);
+        }
+
+        let parsed: HealthState =
+            serde_json::from_str("\"DEGRADED\"").unwrap();
+        assert_eq!(parsed, HealthState::Degraded);
+    }
+}
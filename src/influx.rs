@@ -0,0 +1,162 @@
+//! `InfluxDB` line protocol.
+//!
+//! Metric families are written the way Telegraf's Prometheus input writes
+//! them: the metric name is the measurement, labels are tags and the value is
+//! a float field named after the metric type, i.e. `gauge` or `counter`. This
+//! way, switching from scraping the Prometheus output with Telegraf to writing
+//! line protocol directly doesn't change the resulting series.
+//!
+//! ```
+//! use std::time::{Duration, UNIX_EPOCH};
+//!
+//! use mmoxi::influx::render;
+//! use mmoxi::prom::Family;
+//!
+//! let mut inodes = Family::gauge("gpfs_fileset_max_inodes", "max inodes");
+//! inodes.sample(&[("fs", "gpfs1"), ("fileset", "a b")], 1024_u64);
+//!
+//! let timestamp = UNIX_EPOCH + Duration::from_secs(1);
+//!
+//! let mut output = vec![];
+//! render(&[inodes], timestamp, &mut output).unwrap();
+//!
+//! assert_eq!(
+//!     String::from_utf8(output).unwrap(),
+//!     "gpfs_fileset_max_inodes,fs=gpfs1,fileset=a\\ b gauge=1024 \
+//!      1000000000\n",
+//! );
+//! ```
+
+use std::io::Write;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::Result;
+use crate::prom::{Family, Kind, ToMetrics, Value};
+
+/// Convert `Data` to `InfluxDB` line protocol.
+pub trait ToLineProtocol {
+    /// Converts the `Data` to `InfluxDB` line protocol with all points at
+    /// `timestamp`.
+    ///
+    /// # Errors
+    ///
+    /// This function uses [`writeln`] to write to `output`. It can only fail
+    /// if any of these [`writeln`] fails.
+    fn to_line_protocol(
+        &self,
+        timestamp: SystemTime,
+        output: &mut impl Write,
+    ) -> Result<()>;
+}
+
+impl<T: ToMetrics + ?Sized> ToLineProtocol for T {
+    fn to_line_protocol(
+        &self,
+        timestamp: SystemTime,
+        output: &mut impl Write,
+    ) -> Result<()> {
+        render(&self.to_metrics(), timestamp, output)
+    }
+}
+
+/// Writes `families` in `InfluxDB` line protocol with all points at
+/// `timestamp`.
+///
+/// Empty label values are omitted, because line protocol doesn't allow empty
+/// tag values. Samples that are not finite are skipped, because line protocol
+/// can't represent them.
+///
+/// # Errors
+///
+/// Returns an error if writing to `output` fails.
+pub fn render(
+    families: &[Family],
+    timestamp: SystemTime,
+    output: &mut impl Write,
+) -> Result<()> {
+    let timestamp = timestamp
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |timestamp| timestamp.as_nanos());
+
+    for family in families {
+        let field = match family.kind() {
+            Kind::Gauge => "gauge",
+            Kind::Counter => "counter",
+        };
+
+        for sample in family.samples() {
+            if let Value::Float(value) = sample.value() {
+                if !value.is_finite() {
+                    continue;
+                }
+            }
+
+            write!(output, "{}", escape(family.name(), false))?;
+
+            for (name, value) in sample.labels() {
+                if !value.is_empty() {
+                    write!(output, ",{name}={}", escape(value, true))?;
+                }
+            }
+
+            writeln!(output, " {field}={} {timestamp}", sample.value())?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Escapes measurements, or tag values if `tag` is set.
+fn escape(s: &str, tag: bool) -> String {
+    let mut escaped = String::with_capacity(s.len());
+
+    for c in s.chars() {
+        match c {
+            ',' | ' ' | '\\' => escaped.push('\\'),
+            '=' if tag => escaped.push('\\'),
+            '\n' => {
+                escaped.push_str("\\n");
+                continue;
+            }
+            _ => {}
+        }
+
+        escaped.push(c);
+    }
+
+    escaped
+}
+
+// ----------------------------------------------------------------------------
+// tests
+// ----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn line_protocol() {
+        let mut bytes = Family::counter("pool_bytes", "bytes");
+        bytes.sample(&[("fs", "gpfs1"), ("fileset", "")], 2_u64);
+
+        let mut ratio = Family::gauge("ratio", "ratio");
+        ratio.sample(&[("name", "a,b=c")], 0.5);
+        ratio.sample(&[("name", "nan")], f64::NAN);
+
+        let timestamp = UNIX_EPOCH + Duration::from_nanos(42);
+
+        let mut output = vec![];
+        render(&[bytes, ratio], timestamp, &mut output).unwrap();
+
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "\
+pool_bytes,fs=gpfs1 counter=2 42
+ratio,name=a\\,b\\=c gauge=0.5 42
+"
+        );
+    }
+}
@@ -4,19 +4,30 @@
 #![deny(clippy::all, missing_docs)]
 #![warn(clippy::pedantic, clippy::nursery, clippy::cargo)]
 
+pub mod cluster;
 pub mod df;
 pub mod diag;
 pub mod disk;
+pub mod error;
 pub mod fileset;
 pub mod fs;
+pub mod health;
+pub mod influx;
 pub mod mgr;
+pub mod mmpmon;
+pub mod mount;
 pub mod nmon;
 pub mod nsd;
+pub mod parse;
 pub mod policy;
 pub mod pool;
 pub mod prom;
 pub mod quota;
+pub mod replication;
+pub mod run;
 pub mod state;
 pub mod sysfs;
 pub mod user;
 pub mod util;
+
+pub use error::{Error, Result};
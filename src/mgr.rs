@@ -1,30 +1,45 @@
 //! `mmlsmgr` parsing.
 
-use std::io::{BufRead, Write};
-use std::process::Command;
+use std::io::BufRead;
 
-use anyhow::{Context, Result, anyhow};
+use crate::Result;
+
+use crate::parse::{FromRecord, Record};
+use crate::prom::{Family, ToMetrics};
+use crate::run::{Cmd, Runner};
 
 /// Returns the cluster and filesystem managers.
 ///
 /// # Errors
 ///
 /// Returns an error if running `mmlsmgr` fails or if parsing its output fails.
-pub fn get() -> Result<Manager> {
-    let mut cmd = Command::new("mmlsmgr");
-    cmd.arg("-Y");
+pub fn get(runner: &dyn Runner) -> Result<Manager> {
+    let cmd = Cmd::new("mmlsmgr").arg("-Y");
 
-    let output = cmd
-        .output()
-        .with_context(|| format!("error running: {cmd:?}"))?;
+    let output = runner.run_checked(&cmd)?;
 
     let manager = Manager::from_reader(output.stdout.as_slice())?;
 
     Ok(manager)
 }
 
+/// Returns the manager roles of the local node.
+///
+/// # Errors
+///
+/// Returns an error if determining the local node name fails or if fetching
+/// the managers fails.
+pub fn local_roles(runner: &dyn Runner) -> Result<Roles> {
+    let node = crate::state::local_node_name(runner)?;
+
+    let manager = get(runner)?;
+
+    Ok(Roles { node, manager })
+}
+
 /// List of cluster and file system managers.
 #[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Manager {
     cluster: ClusterManager,
     fs: Vec<FSManager>,
@@ -46,31 +61,20 @@ impl Manager {
 
 impl Manager {
     fn from_reader<Input: BufRead>(input: Input) -> Result<Self> {
-        let mut cluster_index = ClusterIndex::default();
-        let mut fs_index = FSIndex::default();
         let mut manager = Self::default();
 
-        for line in input.lines() {
-            let line = line?;
+        for record in crate::parse::records(input) {
+            let record = record?;
 
-            let tokens = line.split(':').collect::<Vec<_>>();
-
-            if tokens[1] == "clusterManager" {
-                if tokens[2] == "HEADER" {
-                    cluster_index = ClusterIndex::default();
-                    cluster_index.with_tokens(&tokens);
-                } else {
-                    manager.cluster =
-                        ClusterManager::from_tokens(&tokens, &cluster_index)?;
+            match record.section() {
+                "clusterManager" => {
+                    manager.cluster = ClusterManager::from_record(&record)?;
                 }
-            } else if tokens[1] == "filesystemManager" {
-                if tokens[2] == "HEADER" {
-                    fs_index = FSIndex::default();
-                    fs_index.with_tokens(&tokens);
-                } else {
-                    let fs = FSManager::from_tokens(&tokens, &fs_index)?;
+                "filesystemManager" => {
+                    let fs = FSManager::from_record(&record)?;
                     manager.fs.push(fs);
                 }
+                _ => {}
             }
         }
 
@@ -78,8 +82,45 @@ impl Manager {
     }
 }
 
+/// Manager roles of a node.
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Roles {
+    node: String,
+    manager: Manager,
+}
+
+impl Roles {
+    /// Returns the node name.
+    #[must_use]
+    pub fn node(&self) -> &str {
+        &self.node
+    }
+
+    /// Returns the cluster and file system managers.
+    #[must_use]
+    pub const fn manager(&self) -> &Manager {
+        &self.manager
+    }
+
+    /// Returns `true` if the node is the cluster manager.
+    #[must_use]
+    pub fn is_cluster_manager(&self) -> bool {
+        self.node == self.manager.cluster.name
+    }
+
+    /// Returns `true` if the node is the file system manager of `fs`.
+    #[must_use]
+    pub fn is_fs_manager(&self, fs: &str) -> bool {
+        self.manager.fs.iter().any(|manager| {
+            manager.fs_name == fs && manager.manager_name == self.node
+        })
+    }
+}
+
 /// Cluster manager.
 #[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ClusterManager {
     name: String,
 }
@@ -94,6 +135,7 @@ impl ClusterManager {
 
 /// Filesystem manager.
 #[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct FSManager {
     fs_name: String,
     manager_name: String,
@@ -124,113 +166,53 @@ impl FSManager {
 // boiler-platy parsing
 // ----------------------------------------------------------------------------
 
-impl ClusterManager {
-    fn from_tokens(tokens: &[&str], index: &ClusterIndex) -> Result<Self> {
-        let manager_index =
-            index.manager.ok_or_else(|| anyhow!("no manager index"))?;
-        let manager = tokens[manager_index].into();
+impl FromRecord for ClusterManager {
+    fn from_record(record: &Record) -> Result<Self> {
+        let name = record.get("manager")?.into();
 
-        Ok(Self { name: manager })
+        Ok(Self { name })
     }
 }
 
-impl FSManager {
-    fn from_tokens(tokens: &[&str], index: &FSIndex) -> Result<Self> {
-        let manager_index =
-            index.manager.ok_or_else(|| anyhow!("no manager index"))?;
-        let name = tokens[manager_index].into();
-
-        let fs_index =
-            index.fs.ok_or_else(|| anyhow!("no filesystem index"))?;
-        let fs = tokens[fs_index].into();
-
-        let ip_index =
-            index.ip.ok_or_else(|| anyhow!("no managerIP index"))?;
-        let ip = tokens[ip_index].into();
+impl FromRecord for FSManager {
+    fn from_record(record: &Record) -> Result<Self> {
+        let fs_name = record.get("filesystem")?.into();
+        let manager_name = record.get("manager")?.into();
+        let manager_ip = record.get("managerIP")?.into();
 
         Ok(Self {
-            fs_name: fs,
-            manager_name: name,
-            manager_ip: ip,
+            fs_name,
+            manager_name,
+            manager_ip,
         })
     }
 }
 
-#[derive(Debug, Default)]
-struct ClusterIndex {
-    manager: Option<usize>,
-}
-
-impl ClusterIndex {
-    fn with_tokens(&mut self, tokens: &[&str]) {
-        for (i, token) in tokens.iter().enumerate() {
-            if *token == "manager" {
-                self.manager = Some(i);
-            }
-        }
-    }
-}
-
-#[derive(Debug, Default)]
-struct FSIndex {
-    fs: Option<usize>,
-    manager: Option<usize>,
-    ip: Option<usize>,
-}
-
-impl FSIndex {
-    fn with_tokens(&mut self, tokens: &[&str]) {
-        for (i, token) in tokens.iter().enumerate() {
-            match *token {
-                "filesystem" => self.fs = Some(i),
-                "manager" => self.manager = Some(i),
-                "managerIP" => self.ip = Some(i),
-                _ => {}
-            }
-        }
-    }
-}
-
 // ----------------------------------------------------------------------------
 // prometheus
 // ----------------------------------------------------------------------------
 
-impl crate::prom::ToText for Manager {
-    fn to_prom(&self, output: &mut impl Write) -> Result<()> {
-        let self_node = crate::state::local_node_name()
-            .context("getting self node name failed")?;
-
-        let cluster_manager_state = i32::from(self_node == self.cluster.name);
-
-        writeln!(
-            output,
-            "# HELP gpfs_cluster_manager_state GPFS cluster manager state."
-        )?;
-
-        writeln!(output, "# TYPE gpfs_cluster_manager_state gauge")?;
-
-        writeln!(
-            output,
-            "gpfs_cluster_manager_state {cluster_manager_state}",
-        )?;
-
-        for fs_managers in &self.fs {
-            let fs_state = i32::from(self_node == fs_managers.manager_name);
+impl ToMetrics for Roles {
+    fn to_metrics(&self) -> Vec<Family> {
+        let mut cluster_manager = Family::gauge(
+            "gpfs_cluster_manager_state",
+            "GPFS cluster manager state.",
+        );
+        cluster_manager.sample(&[], self.is_cluster_manager());
 
-            writeln!(
-                output,
-                "# HELP gpfs_filesystem_manager_state GPFS filesystem manager state."
-            )?;
-            writeln!(output, "# TYPE gpfs_filesystem_manager_state gauge")?;
+        let mut fs_manager = Family::gauge(
+            "gpfs_filesystem_manager_state",
+            "GPFS filesystem manager state.",
+        );
 
-            writeln!(
-                output,
-                "gpfs_filesystem_manager_state{{fs=\"{}\"}} {}",
-                fs_managers.fs_name, fs_state,
-            )?;
+        for fs_managers in &self.manager.fs {
+            fs_manager.sample(
+                &[("fs", &fs_managers.fs_name)],
+                self.node == fs_managers.manager_name,
+            );
         }
 
-        Ok(())
+        vec![cluster_manager, fs_manager]
     }
 }
 
@@ -269,4 +251,20 @@ mod tests {
             }
         );
     }
+
+    #[test]
+    fn roles() {
+        let input = include_str!("mgr-example.in");
+
+        let manager = Manager::from_reader(input.as_bytes()).unwrap();
+
+        let roles = Roles {
+            node: "filer2".into(),
+            manager,
+        };
+
+        assert!(!roles.is_cluster_manager());
+        assert!(roles.is_fs_manager("gpfs1"));
+        assert!(!roles.is_fs_manager("gpfs2"));
+    }
 }
@@ -0,0 +1,846 @@
+//! `mmpmon` parsing.
+//!
+//! `mmpmon` reads requests from stdin and, with `-p`, responds with one line
+//! per response in a keyed format, e.g.:
+//!
+//! ```text
+//! _io_s_ _n_ 10.0.0.1 _nn_ filer1 _rc_ 0 _t_ 1792144800 _tu_ 106012 _br_ 0 ...
+//! ```
+//!
+//! Unlike [`crate::prom::pool_block_device_metrics`], which only sees the
+//! block devices of NSD servers, these counters cover the I/O of the GPFS
+//! clients, too.
+
+use std::io::BufRead;
+use std::str::FromStr;
+
+use crate::diag::Direction;
+use crate::prom::{Family, ToMetrics};
+use crate::run::{Cmd, Runner};
+use crate::{Error, Result};
+
+/// Returns the I/O counters per file system and per node.
+///
+/// Without `nodes`, only the local node is reported. Otherwise, the node
+/// list is replaced with `nodes` first, so their counters are reported
+/// instead.
+///
+/// # Errors
+///
+/// Returns an error if running `mmpmon` fails, if replacing the node list
+/// fails, if the requests fail on all nodes or if parsing its output fails.
+/// The responses of single failed nodes are skipped.
+pub fn io(runner: &dyn Runner, nodes: &[String]) -> Result<Io> {
+    let cmd = Cmd::new("mmpmon")
+        .args(["-p", "-s"])
+        .stdin(requests(nodes, "fs_io_s\nio_s\n"));
+
+    let output = runner.run_checked(&cmd)?;
+
+    Io::from_reader(output.stdout.as_slice())
+}
+
+/// Returns the request histograms per node.
+///
+/// Without `nodes`, only the local node is reported. Otherwise, the node
+/// list is replaced with `nodes` first, like with [`io`].
+///
+/// The histograms need to be enabled with the `rhist on` request first,
+/// e.g. with `echo rhist on | mmpmon`, which has a small performance impact.
+///
+/// # Errors
+///
+/// Returns an error if running `mmpmon` fails, if replacing the node list
+/// fails, if the request fails on all nodes, e.g. because the histograms are
+/// not enabled, or if parsing its output fails. The responses of single
+/// failed nodes are skipped.
+pub fn rhist(
+    runner: &dyn Runner,
+    nodes: &[String],
+) -> Result<Vec<RequestHistogram>> {
+    let cmd = Cmd::new("mmpmon")
+        .args(["-p", "-s"])
+        .stdin(requests(nodes, "rhist s\n"));
+
+    let output = runner.run_checked(&cmd)?;
+
+    RequestHistogram::from_reader(output.stdout.as_slice())
+}
+
+/// Returns the `requests`, preceded by replacing the node list with `nodes`
+/// unless there are none.
+fn requests(nodes: &[String], requests: &str) -> String {
+    if nodes.is_empty() {
+        requests.into()
+    } else {
+        format!("nlist new {}\n{requests}", nodes.join(" "))
+    }
+}
+
+/// I/O counters per file system and per node.
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Io {
+    fs: Vec<FsIo>,
+    nodes: Vec<NodeIo>,
+}
+
+impl Io {
+    /// Returns the counters per file system and node, from `fs_io_s`.
+    #[must_use]
+    pub fn fs(&self) -> &[FsIo] {
+        &self.fs
+    }
+
+    /// Returns the counters per node, from `io_s`.
+    #[must_use]
+    pub fn nodes(&self) -> &[NodeIo] {
+        &self.nodes
+    }
+
+    fn from_reader<Input: BufRead>(input: Input) -> Result<Self> {
+        let mut io = Self::default();
+
+        for line in responses(input)? {
+            match line.kind() {
+                "fs_io_s" => io.fs.push(FsIo {
+                    node: line.get("nn")?.into(),
+                    ip: line.get("n")?.into(),
+                    cluster: line.get("cl")?.into(),
+                    fs: line.get("fs")?.into(),
+                    disks: line.parse("d")?,
+                    counters: Counters::from_line(&line)?,
+                }),
+                "io_s" => io.nodes.push(NodeIo {
+                    node: line.get("nn")?.into(),
+                    ip: line.get("n")?.into(),
+                    counters: Counters::from_line(&line)?,
+                }),
+                _ => {}
+            }
+        }
+
+        Ok(io)
+    }
+}
+
+/// I/O counters of a file system on a node.
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FsIo {
+    node: String,
+    ip: String,
+    cluster: String,
+    fs: String,
+    disks: u64,
+    counters: Counters,
+}
+
+impl FsIo {
+    /// Returns the node name.
+    #[must_use]
+    pub fn node(&self) -> &str {
+        &self.node
+    }
+
+    /// Returns the IP address of the node.
+    #[must_use]
+    pub fn ip(&self) -> &str {
+        &self.ip
+    }
+
+    /// Returns the name of the cluster that owns the file system.
+    #[must_use]
+    pub fn cluster(&self) -> &str {
+        &self.cluster
+    }
+
+    /// Returns the file system name.
+    #[must_use]
+    pub fn fs(&self) -> &str {
+        &self.fs
+    }
+
+    /// Returns the number of disks of the file system.
+    #[must_use]
+    pub const fn disks(&self) -> u64 {
+        self.disks
+    }
+
+    /// Returns the counters.
+    #[must_use]
+    pub const fn counters(&self) -> &Counters {
+        &self.counters
+    }
+}
+
+/// I/O counters of a node, summed over all file systems.
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct NodeIo {
+    node: String,
+    ip: String,
+    counters: Counters,
+}
+
+impl NodeIo {
+    /// Returns the node name.
+    #[must_use]
+    pub fn node(&self) -> &str {
+        &self.node
+    }
+
+    /// Returns the IP address of the node.
+    #[must_use]
+    pub fn ip(&self) -> &str {
+        &self.ip
+    }
+
+    /// Returns the counters.
+    #[must_use]
+    pub const fn counters(&self) -> &Counters {
+        &self.counters
+    }
+}
+
+/// I/O counters since the GPFS daemon started or the counters were reset.
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Counters {
+    bytes_read: u64,
+    bytes_written: u64,
+    opens: u64,
+    closes: u64,
+    reads: u64,
+    writes: u64,
+    readdirs: u64,
+    inode_updates: u64,
+}
+
+impl Counters {
+    /// Returns the number of bytes read, including from cache.
+    #[must_use]
+    pub const fn bytes_read(&self) -> u64 {
+        self.bytes_read
+    }
+
+    /// Returns the number of bytes written, including to cache.
+    #[must_use]
+    pub const fn bytes_written(&self) -> u64 {
+        self.bytes_written
+    }
+
+    /// Returns the number of open and create requests.
+    #[must_use]
+    pub const fn opens(&self) -> u64 {
+        self.opens
+    }
+
+    /// Returns the number of close requests.
+    #[must_use]
+    pub const fn closes(&self) -> u64 {
+        self.closes
+    }
+
+    /// Returns the number of read requests.
+    #[must_use]
+    pub const fn reads(&self) -> u64 {
+        self.reads
+    }
+
+    /// Returns the number of write requests.
+    #[must_use]
+    pub const fn writes(&self) -> u64 {
+        self.writes
+    }
+
+    /// Returns the number of readdir requests.
+    #[must_use]
+    pub const fn readdirs(&self) -> u64 {
+        self.readdirs
+    }
+
+    /// Returns the number of inode updates.
+    #[must_use]
+    pub const fn inode_updates(&self) -> u64 {
+        self.inode_updates
+    }
+
+    /// Returns the values in the order of [`COUNTERS`].
+    const fn values(&self) -> [u64; 8] {
+        [
+            self.bytes_read,
+            self.bytes_written,
+            self.opens,
+            self.closes,
+            self.reads,
+            self.writes,
+            self.readdirs,
+            self.inode_updates,
+        ]
+    }
+
+    fn from_line(line: &Line) -> Result<Self> {
+        Ok(Self {
+            bytes_read: line.parse("br")?,
+            bytes_written: line.parse("bw")?,
+            opens: line.parse("oc")?,
+            closes: line.parse("cc")?,
+            reads: line.parse("rdc")?,
+            writes: line.parse("wc")?,
+            readdirs: line.parse("dir")?,
+            inode_updates: line.parse("iu")?,
+        })
+    }
+}
+
+/// Request histogram of a node, i.e. the number of requests per size range
+/// and, within that, per latency range.
+#[derive(Clone, PartialEq, PartialOrd, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RequestHistogram {
+    node: String,
+    ip: String,
+    direction: Direction,
+    sizes: Vec<SizeRange>,
+}
+
+impl RequestHistogram {
+    /// Returns the node name.
+    #[must_use]
+    pub fn node(&self) -> &str {
+        &self.node
+    }
+
+    /// Returns the IP address of the node.
+    #[must_use]
+    pub fn ip(&self) -> &str {
+        &self.ip
+    }
+
+    /// Returns whether these are read or write requests.
+    #[must_use]
+    pub const fn direction(&self) -> Direction {
+        self.direction
+    }
+
+    /// Returns the size ranges with at least one request.
+    #[must_use]
+    pub fn sizes(&self) -> &[SizeRange] {
+        &self.sizes
+    }
+
+    fn from_reader<Input: BufRead>(input: Input) -> Result<Vec<Self>> {
+        let mut histograms: Vec<Self> = vec![];
+
+        for line in responses(input)? {
+            match line.kind() {
+                "rhist" => {
+                    let direction = match line.get("k")? {
+                        "r" => Direction::Read,
+                        "w" => Direction::Write,
+                        kind => {
+                            return Err(Error::InvalidValue {
+                                kind: "request histogram kind",
+                                value: kind.into(),
+                            });
+                        }
+                    };
+
+                    histograms.push(Self {
+                        node: line.get("nn")?.into(),
+                        ip: line.get("n")?.into(),
+                        direction,
+                        sizes: vec![],
+                    });
+                }
+                "R" => {
+                    let [min, max] = line.range("R")?;
+
+                    let histogram =
+                        histograms.last_mut().ok_or_else(|| {
+                            line.syntax("size range before rhist response")
+                        })?;
+
+                    histogram.sizes.push(SizeRange {
+                        min,
+                        max,
+                        count: line.parse("NR")?,
+                        latencies: vec![],
+                    });
+                }
+                "L" => {
+                    let [min_ms, max_ms] = line.range("L")?;
+
+                    let size = histograms
+                        .last_mut()
+                        .and_then(|histogram| histogram.sizes.last_mut())
+                        .ok_or_else(|| {
+                            line.syntax("latency range before size range")
+                        })?;
+
+                    size.latencies.push(LatencyRange {
+                        min_ms,
+                        max_ms,
+                        count: line.parse("NL")?,
+                    });
+                }
+                _ => {}
+            }
+        }
+
+        Ok(histograms)
+    }
+}
+
+/// Request size range in bytes.
+#[derive(Clone, PartialEq, PartialOrd, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SizeRange {
+    min: u64,
+    max: u64,
+    count: u64,
+    latencies: Vec<LatencyRange>,
+}
+
+impl SizeRange {
+    /// Returns the minimum request size in bytes.
+    #[must_use]
+    pub const fn min(&self) -> u64 {
+        self.min
+    }
+
+    /// Returns the maximum request size in bytes.
+    #[must_use]
+    pub const fn max(&self) -> u64 {
+        self.max
+    }
+
+    /// Returns the number of requests.
+    #[must_use]
+    pub const fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// Returns the latency ranges with at least one request.
+    #[must_use]
+    pub fn latencies(&self) -> &[LatencyRange] {
+        &self.latencies
+    }
+}
+
+/// Request latency range in milliseconds.
+#[derive(Copy, Clone, PartialEq, PartialOrd, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LatencyRange {
+    min_ms: f64,
+    max_ms: f64,
+    count: u64,
+}
+
+impl LatencyRange {
+    /// Returns the minimum latency in milliseconds.
+    #[must_use]
+    pub const fn min_ms(&self) -> f64 {
+        self.min_ms
+    }
+
+    /// Returns the maximum latency in milliseconds.
+    #[must_use]
+    pub const fn max_ms(&self) -> f64 {
+        self.max_ms
+    }
+
+    /// Returns the number of requests.
+    #[must_use]
+    pub const fn count(&self) -> u64 {
+        self.count
+    }
+}
+
+// ----------------------------------------------------------------------------
+// prometheus
+// ----------------------------------------------------------------------------
+
+/// Counter names and descriptions, in the order of [`Counters::values`].
+const COUNTERS: [(&str, &str); 8] = [
+    ("read_bytes", "bytes read"),
+    ("written_bytes", "bytes written"),
+    ("opens", "open requests"),
+    ("closes", "close requests"),
+    ("reads", "read requests"),
+    ("writes", "write requests"),
+    ("readdirs", "readdir requests"),
+    ("inode_updates", "inode updates"),
+];
+
+impl ToMetrics for Io {
+    fn to_metrics(&self) -> Vec<Family> {
+        let mut fs = counter_families("gpfs_fs", "GPFS file system");
+
+        for io in &self.fs {
+            let labels = [("fs", io.fs.as_str()), ("node", &io.node)];
+
+            for (family, value) in fs.iter_mut().zip(io.counters.values()) {
+                family.sample(&labels, value);
+            }
+        }
+
+        let mut nodes = counter_families("gpfs_node", "GPFS node");
+
+        for io in &self.nodes {
+            let labels = [("node", io.node.as_str())];
+
+            for (family, value) in nodes.iter_mut().zip(io.counters.values()) {
+                family.sample(&labels, value);
+            }
+        }
+
+        fs.into_iter().chain(nodes).collect()
+    }
+}
+
+fn counter_families(prefix: &str, subject: &str) -> [Family; 8] {
+    COUNTERS.map(|(name, help)| {
+        let family = Family::counter(
+            format!("{prefix}_{name}_total"),
+            format!("{subject} {help}."),
+        );
+
+        if name.ends_with("_bytes") {
+            family.with_unit("bytes")
+        } else {
+            family
+        }
+    })
+}
+
+// ----------------------------------------------------------------------------
+// boiler-platy parsing
+// ----------------------------------------------------------------------------
+
+/// A response line, i.e. keys like `_nn_`, each followed by its values. The
+/// first key is the kind of response, e.g. `_io_s_`.
+struct Line {
+    number: usize,
+    fields: Vec<(String, Vec<String>)>,
+}
+
+impl Line {
+    fn kind(&self) -> &str {
+        &self.fields[0].0
+    }
+
+    fn values(&self, key: &str) -> Result<&[String]> {
+        self.fields
+            .iter()
+            .find(|(name, _)| name == key)
+            .map(|(_, values)| values.as_slice())
+            .ok_or_else(|| Error::MissingField {
+                line: self.number,
+                field: key.into(),
+            })
+    }
+
+    fn get(&self, key: &str) -> Result<&str> {
+        self.values(key)?
+            .first()
+            .map(String::as_str)
+            .ok_or_else(|| Error::MissingField {
+                line: self.number,
+                field: key.into(),
+            })
+    }
+
+    fn parse<T>(&self, key: &str) -> Result<T>
+    where
+        T: FromStr,
+        T::Err: std::fmt::Display,
+    {
+        let value = self.get(key)?;
+
+        self.parse_value(key, value)
+    }
+
+    /// Parses a range, i.e. a key with two values like `_R_ 0 255`.
+    fn range<T>(&self, key: &str) -> Result<[T; 2]>
+    where
+        T: FromStr,
+        T::Err: std::fmt::Display,
+    {
+        match self.values(key)? {
+            [min, max] => {
+                Ok([self.parse_value(key, min)?, self.parse_value(key, max)?])
+            }
+            _ => Err(self.syntax(&format!("expected range for _{key}_"))),
+        }
+    }
+
+    fn parse_value<T>(&self, key: &str, value: &str) -> Result<T>
+    where
+        T: FromStr,
+        T::Err: std::fmt::Display,
+    {
+        value.parse().map_err(|error: T::Err| Error::Parse {
+            line: self.number,
+            field: key.into(),
+            value: value.into(),
+            message: error.to_string(),
+        })
+    }
+
+    fn syntax(&self, message: &str) -> Error {
+        Error::Syntax {
+            line: self.number,
+            message: message.into(),
+        }
+    }
+
+    fn from_str(number: usize, s: &str) -> Result<Self> {
+        let mut fields: Vec<(String, Vec<String>)> = vec![];
+
+        for token in s.split_whitespace() {
+            let key = token
+                .strip_prefix('_')
+                .and_then(|token| token.strip_suffix('_'))
+                .filter(|key| !key.is_empty());
+
+            if let Some(key) = key {
+                fields.push((key.into(), vec![]));
+            } else if let Some((_, values)) = fields.last_mut() {
+                values.push(token.into());
+            } else {
+                return Err(Error::Syntax {
+                    line: number,
+                    message: format!("value {token} without key"),
+                });
+            }
+        }
+
+        Ok(Self { number, fields })
+    }
+
+    /// Returns an error if this is a failed response, i.e. it reports a
+    /// non-zero return code.
+    fn check(&self) -> Result<()> {
+        match self.get("rc") {
+            Ok(rc) if rc != "0" => Err(Error::InvalidValue {
+                kind: "mmpmon return code",
+                value: format!("{rc} for {} request", self.kind()),
+            }),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Returns the non-empty response lines.
+fn lines<Input: BufRead>(input: Input) -> impl Iterator<Item = Result<Line>> {
+    input
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| {
+            line.as_ref().map_or(true, |line| !line.trim().is_empty())
+        })
+        .map(|(index, line)| Line::from_str(index + 1, &line?))
+}
+
+/// Returns the lines of the successful responses.
+///
+/// With a node list, every node responds separately and the responses of
+/// single nodes may fail, e.g. because they are down. These are skipped,
+/// including their detail lines like `_R_`, which don't have a return code.
+/// Only a failed `nlist` request or no successful response at all are
+/// errors.
+fn responses<Input: BufRead>(input: Input) -> Result<Vec<Line>> {
+    let mut responses = vec![];
+    let mut failure = None;
+    let mut succeeded = false;
+    let mut skipping = false;
+
+    for line in lines(input) {
+        let line = line?;
+
+        if line.get("rc").is_err() {
+            if !skipping {
+                responses.push(line);
+            }
+
+            continue;
+        }
+
+        match line.check() {
+            Ok(()) => {
+                succeeded |= line.kind() != "nlist";
+                skipping = false;
+                responses.push(line);
+            }
+            Err(error) if line.kind() == "nlist" => return Err(error),
+            Err(error) => {
+                failure.get_or_insert(error);
+                skipping = true;
+            }
+        }
+    }
+
+    match failure {
+        Some(error) if !succeeded => Err(error),
+        _ => Ok(responses),
+    }
+}
+
+// ----------------------------------------------------------------------------
+// tests
+// ----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prom::ToText;
+    use crate::run::Replay;
+
+    #[test]
+    fn parse() {
+        let input = include_str!("mmpmon-example.in");
+
+        let io = Io::from_reader(input.as_bytes()).unwrap();
+        assert_eq!(io.fs().len(), 3);
+        assert_eq!(io.nodes().len(), 2);
+
+        let fs = &io.fs()[2];
+        assert_eq!(fs.node(), "node001");
+        assert_eq!(fs.ip(), "10.0.1.1");
+        assert_eq!(fs.cluster(), "storage.example.org");
+        assert_eq!(fs.fs(), "gpfs1");
+        assert_eq!(fs.disks(), 4);
+
+        assert_eq!(
+            *fs.counters(),
+            Counters {
+                bytes_read: 1_073_741_824,
+                bytes_written: 52_428_800,
+                opens: 2201,
+                closes: 2198,
+                reads: 8192,
+                writes: 400,
+                readdirs: 31,
+                inode_updates: 118,
+            }
+        );
+
+        assert_eq!(io.nodes()[0].node(), "filer1");
+        assert_eq!(io.nodes()[0].counters().writes(), 300);
+    }
+
+    #[test]
+    fn return_code() {
+        let input = "_fs_io_s_ _n_ 10.0.0.1 _nn_ filer1 _rc_ 1 _t_ 1 _tu_ 2\n";
+
+        assert!(matches!(
+            Io::from_reader(input.as_bytes()),
+            Err(Error::InvalidValue { .. })
+        ));
+
+        assert!(matches!(
+            Io::from_reader(&b"0 _n_ 10.0.0.1\n"[..]),
+            Err(Error::Syntax { line: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn return_code_mixed() {
+        let input = include_str!("mmpmon-rc-example.in");
+
+        let io = Io::from_reader(input.as_bytes()).unwrap();
+        assert_eq!(io.fs().len(), 1);
+        assert_eq!(io.fs()[0].node(), "filer1");
+        assert_eq!(io.nodes().len(), 1);
+        assert_eq!(io.nodes()[0].node(), "filer1");
+
+        // the detail lines of a failed response are skipped, too
+        let input = "_rhist_ _n_ 10.0.1.1 _nn_ node001 _req_ s _rc_ 1 _k_ r\n\
+                     _R_ 0 255 _NR_ 3\n\
+                     _rhist_ _n_ 10.0.0.1 _nn_ filer1 _req_ s _rc_ 0 _k_ r\n";
+
+        let histograms =
+            RequestHistogram::from_reader(input.as_bytes()).unwrap();
+        assert_eq!(histograms.len(), 1);
+        assert_eq!(histograms[0].node(), "filer1");
+        assert!(histograms[0].sizes().is_empty());
+
+        // a failed node list is an error, even if other requests succeed
+        let input = "_nlist_ _n_ 10.0.0.1 _nn_ filer1 _req_ new _rc_ 1\n\
+                     _io_s_ _n_ 10.0.0.1 _nn_ filer1 _rc_ 0 _br_ 0\n";
+
+        assert!(matches!(
+            Io::from_reader(input.as_bytes()),
+            Err(Error::InvalidValue { .. })
+        ));
+    }
+
+    #[test]
+    fn requests() {
+        let input = include_str!("mmpmon-example.in");
+
+        let cmd = Cmd::new("mmpmon")
+            .args(["-p", "-s"])
+            .stdin("nlist new filer1 node001\nfs_io_s\nio_s\n");
+        let runner = Replay::new().with(cmd, input);
+
+        let nodes = ["filer1".to_owned(), "node001".to_owned()];
+        let io = io(&runner, &nodes).unwrap();
+        assert_eq!(io.nodes().len(), 2);
+
+        let input = include_str!("mmpmon-rhist-example.in");
+
+        let local = Cmd::new("mmpmon").args(["-p", "-s"]).stdin("rhist s\n");
+        let filer1 = Cmd::new("mmpmon")
+            .args(["-p", "-s"])
+            .stdin("nlist new filer1\nrhist s\n");
+        let runner = Replay::new().with(local, input).with(filer1, input);
+
+        assert_eq!(rhist(&runner, &[]).unwrap().len(), 2);
+        assert_eq!(rhist(&runner, &["filer1".to_owned()]).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn prometheus() {
+        let input = include_str!("mmpmon-example.in");
+        let io = Io::from_reader(input.as_bytes()).unwrap();
+
+        let mut output = vec![];
+        io.to_prom(&mut output).unwrap();
+
+        let metrics = std::str::from_utf8(output.as_slice()).unwrap();
+
+        let expected = include_str!("mmpmon-example.prom");
+        assert_eq!(metrics, expected);
+    }
+
+    #[test]
+    fn request_histogram() {
+        let input = include_str!("mmpmon-rhist-example.in");
+
+        let histograms =
+            RequestHistogram::from_reader(input.as_bytes()).unwrap();
+        assert_eq!(histograms.len(), 2);
+
+        let read = &histograms[0];
+        assert_eq!(read.node(), "filer1");
+        assert_eq!(read.direction(), Direction::Read);
+        assert_eq!(read.sizes().len(), 2);
+
+        let size = &read.sizes()[1];
+        assert_eq!(size.min(), 262_144);
+        assert_eq!(size.max(), 524_287);
+        assert_eq!(size.count(), 12);
+        assert_eq!(size.latencies().len(), 2);
+
+        let latency = size.latencies()[1];
+        assert!((latency.min_ms() - 10.001).abs() < f64::EPSILON);
+        assert!((latency.max_ms() - 30.0).abs() < f64::EPSILON);
+        assert_eq!(latency.count(), 2);
+
+        assert_eq!(histograms[1].direction(), Direction::Write);
+        assert_eq!(histograms[1].sizes()[0].latencies()[0].count(), 4);
+    }
+}
@@ -0,0 +1,359 @@
+//! `mmlsmount` parsing.
+
+use std::collections::BTreeSet;
+use std::io::BufRead;
+
+use crate::Result;
+use crate::prom::{Family, ToMetrics};
+use crate::run::{Cmd, Runner};
+
+/// Returns the nodes that have each file system mounted, including nodes of
+/// remote clusters.
+///
+/// # Errors
+///
+/// Returns an error if running `mmlsmount` fails or if parsing its output
+/// fails.
+pub fn mounts(runner: &dyn Runner) -> Result<Vec<Mounts>> {
+    let cmd = Cmd::new("mmlsmount").args(["all", "-L", "-Y"]);
+
+    let output = runner.run_checked(&cmd)?;
+
+    Mounts::from_reader(output.stdout.as_slice())
+}
+
+/// The nodes that have a file system mounted.
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Mounts {
+    fs_name: String,
+    real_name: String,
+    owning_cluster: String,
+    nodes: Vec<Node>,
+}
+
+impl Mounts {
+    /// Returns the local file system name.
+    #[must_use]
+    pub fn fs_name(&self) -> &str {
+        &self.fs_name
+    }
+
+    /// Returns the file system name in the owning cluster.
+    #[must_use]
+    pub fn real_name(&self) -> &str {
+        &self.real_name
+    }
+
+    /// Returns the name of the cluster that owns the file system.
+    #[must_use]
+    pub fn owning_cluster(&self) -> &str {
+        &self.owning_cluster
+    }
+
+    /// Returns all nodes that have the file system mounted.
+    #[must_use]
+    pub fn nodes(&self) -> &[Node] {
+        &self.nodes
+    }
+
+    /// Returns the nodes of the owning cluster.
+    pub fn local_nodes(&self) -> impl Iterator<Item = &Node> {
+        self.nodes
+            .iter()
+            .filter(|node| node.cluster == self.owning_cluster)
+    }
+
+    /// Returns the nodes of remote clusters.
+    pub fn remote_nodes(&self) -> impl Iterator<Item = &Node> {
+        self.nodes
+            .iter()
+            .filter(|node| node.cluster != self.owning_cluster)
+    }
+
+    fn from_reader<Input: BufRead>(input: Input) -> Result<Vec<Self>> {
+        let mut all: Vec<Self> = vec![];
+
+        for record in crate::parse::records(input) {
+            let record = record?;
+
+            let fs_name = record.get("localDevName")?;
+
+            let position =
+                all.iter().position(|mounts| mounts.fs_name == fs_name);
+
+            let index = if let Some(index) = position {
+                index
+            } else {
+                all.push(Self {
+                    fs_name: fs_name.into(),
+                    real_name: record.get("realDevName")?.into(),
+                    owning_cluster: record.get("owningCluster")?.into(),
+                    nodes: vec![],
+                });
+
+                all.len() - 1
+            };
+
+            // file systems that are not mounted anywhere have an empty node
+            let name = record.get("nodeName")?;
+
+            if !name.is_empty() {
+                all[index].nodes.push(Node {
+                    name: name.into(),
+                    ip: record.get("nodeIP")?.into(),
+                    cluster: record.get("clusterName")?.into(),
+                });
+            }
+        }
+
+        Ok(all)
+    }
+}
+
+/// The mounts of all file systems and the nodes expected to mount them.
+///
+/// Nodes that have no file system mounted at all are not part of the output
+/// of `mmlsmount`. That's why the nodes of the local cluster are given
+/// separately, e.g. from [`crate::state::all`].
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ClusterMounts {
+    cluster: String,
+    nodes: Vec<String>,
+    mounts: Vec<Mounts>,
+}
+
+impl ClusterMounts {
+    /// Returns the mounts along with the names of all nodes of the local
+    /// `cluster`.
+    #[must_use]
+    pub const fn new(
+        cluster: String,
+        nodes: Vec<String>,
+        mounts: Vec<Mounts>,
+    ) -> Self {
+        Self {
+            cluster,
+            nodes,
+            mounts,
+        }
+    }
+
+    /// Returns the name of the local cluster.
+    #[must_use]
+    pub fn cluster(&self) -> &str {
+        &self.cluster
+    }
+
+    /// Returns the names of all nodes of the local cluster.
+    #[must_use]
+    pub fn nodes(&self) -> &[String] {
+        &self.nodes
+    }
+
+    /// Returns the mounts of all file systems.
+    #[must_use]
+    pub fn mounts(&self) -> &[Mounts] {
+        &self.mounts
+    }
+
+    /// Returns the cluster and name of all nodes of the local cluster and
+    /// all nodes of remote clusters that have a file system mounted.
+    fn all_nodes(&self) -> BTreeSet<(&str, &str)> {
+        let local = self
+            .nodes
+            .iter()
+            .map(|node| (self.cluster.as_str(), node.as_str()));
+
+        let mounted = self
+            .mounts
+            .iter()
+            .flat_map(|mounts| &mounts.nodes)
+            .map(|node| (node.cluster.as_str(), node.name.as_str()));
+
+        local.chain(mounted).collect()
+    }
+}
+
+/// A node that has a file system mounted.
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Node {
+    name: String,
+    ip: String,
+    cluster: String,
+}
+
+impl Node {
+    /// Returns the node name.
+    #[must_use]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the IP address.
+    #[must_use]
+    pub fn ip(&self) -> &str {
+        &self.ip
+    }
+
+    /// Returns the name of the cluster the node belongs to.
+    #[must_use]
+    pub fn cluster(&self) -> &str {
+        &self.cluster
+    }
+}
+
+// ----------------------------------------------------------------------------
+// prometheus
+// ----------------------------------------------------------------------------
+
+/// Nodes of the local cluster are always part of the output, with `0` for
+/// file systems they don't have mounted. Nodes of remote clusters are only
+/// known while they have at least one file system mounted, so a remote node
+/// that lost all of its mounts vanishes from `gpfs_fs_mounted` instead. To
+/// alert on these, too, use `absent()` for the expected remote nodes, e.g.
+/// `absent(gpfs_fs_mounted{fs="gpfs1",node="node001"} == 1)`.
+impl ToMetrics for ClusterMounts {
+    fn to_metrics(&self) -> Vec<Family> {
+        let mut mounted_nodes = Family::gauge(
+            "gpfs_fs_mounted_nodes",
+            "GPFS file system number of nodes of a cluster it is mounted on.",
+        );
+        let mut mounted = Family::gauge(
+            "gpfs_fs_mounted",
+            "GPFS file system is mounted on node.",
+        );
+
+        let all_nodes = self.all_nodes();
+
+        let clusters = all_nodes
+            .iter()
+            .map(|(cluster, _)| *cluster)
+            .collect::<BTreeSet<_>>();
+
+        for mounts in &self.mounts {
+            let fs = mounts.fs_name.as_str();
+
+            for cluster in &clusters {
+                let count = mounts
+                    .nodes
+                    .iter()
+                    .filter(|node| node.cluster == *cluster)
+                    .count();
+
+                mounted_nodes
+                    .sample(&[("fs", fs), ("cluster", cluster)], count);
+            }
+
+            for (cluster, node) in &all_nodes {
+                let is_mounted = mounts.nodes.iter().any(|mount| {
+                    mount.cluster == *cluster && mount.name == *node
+                });
+
+                mounted.sample(
+                    &[("fs", fs), ("cluster", cluster), ("node", node)],
+                    is_mounted,
+                );
+            }
+        }
+
+        vec![mounted_nodes, mounted]
+    }
+}
+
+// ----------------------------------------------------------------------------
+// tests
+// ----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prom::ToText;
+
+    #[test]
+    fn parse() {
+        let input = include_str!("mount-example.in");
+
+        let mounts = Mounts::from_reader(input.as_bytes()).unwrap();
+        assert_eq!(mounts.len(), 3);
+
+        let gpfs1 = &mounts[0];
+        assert_eq!(gpfs1.fs_name(), "gpfs1");
+        assert_eq!(gpfs1.real_name(), "gpfs1");
+        assert_eq!(gpfs1.owning_cluster(), "storage.example.org");
+        assert_eq!(
+            gpfs1.nodes()[1],
+            Node {
+                name: "node001".into(),
+                ip: "10.0.1.1".into(),
+                cluster: "compute.example.org".into(),
+            }
+        );
+
+        let local = gpfs1.local_nodes().map(Node::name).collect::<Vec<_>>();
+        assert_eq!(local, ["filer1"]);
+
+        let remote = gpfs1.remote_nodes().map(Node::name).collect::<Vec<_>>();
+        assert_eq!(remote, ["node001", "node002"]);
+
+        assert_eq!(mounts[1].nodes().len(), 2);
+
+        assert_eq!(mounts[2].fs_name(), "gpfs3");
+        assert!(mounts[2].nodes().is_empty());
+    }
+
+    #[test]
+    fn prometheus() {
+        let input = include_str!("mount-example.in");
+        let mounts = Mounts::from_reader(input.as_bytes()).unwrap();
+
+        // filer2 has no file system mounted and is not part of mmlsmount
+        let nodes = ["filer1", "filer2"].map(String::from).to_vec();
+        let mounts =
+            ClusterMounts::new("storage.example.org".into(), nodes, mounts);
+
+        let mut output = vec![];
+        mounts.to_prom(&mut output).unwrap();
+
+        let metrics = std::str::from_utf8(output.as_slice()).unwrap();
+
+        let expected = include_str!("mount-example.prom");
+        assert_eq!(metrics, expected);
+    }
+
+    #[test]
+    fn prometheus_remote_unmounted() {
+        let input = include_str!("mount-example.in");
+
+        // node002 unmounted gpfs1, its only file system
+        let input = input
+            .lines()
+            .filter(|line| !line.contains(":node002:"))
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        let mounts = Mounts::from_reader(input.as_bytes()).unwrap();
+        let nodes = vec!["filer1".into()];
+        let mounts =
+            ClusterMounts::new("storage.example.org".into(), nodes, mounts);
+
+        let metrics = mounts.to_metrics();
+        let mounted = metrics
+            .iter()
+            .find(|family| family.name() == "gpfs_fs_mounted")
+            .unwrap();
+
+        // unlike local nodes, it is absent instead of 0, see absent()
+        let node_names = mounted
+            .samples()
+            .iter()
+            .flat_map(crate::prom::Sample::labels)
+            .filter(|(name, _)| *name == "node")
+            .map(|(_, value)| value.as_str())
+            .collect::<BTreeSet<_>>();
+
+        assert_eq!(node_names, BTreeSet::from(["filer1", "node001"]));
+    }
+}
@@ -3,9 +3,9 @@
 use std::io::Write;
 use std::path::Path;
 
-use anyhow::{Context, Result};
-
+use crate::Result;
 use crate::nsd::{ByPool, Nsd};
+use crate::run::Runner;
 
 /// Returns the default local device cache path.
 pub const DEFAULT_DEVICE_CACHE: &str = "/run/mmlocal-nmon-cache";
@@ -24,6 +24,7 @@ pub const DEFAULT_DEVICE_CACHE: &str = "/run/mmlocal-nmon-cache";
 /// Returns an error if reading from the cache fails, if either of the used
 /// `mm*` commands fails, or if writing to the output stream fails.
 pub fn by_pool_cached<Cache, Output>(
+    runner: &dyn Runner,
     device_cache: Cache,
     force: bool,
     output: &mut Output,
@@ -34,8 +35,7 @@ where
 {
     let device_cache = device_cache.as_ref();
 
-    let pooled = crate::nsd::local_pooled(device_cache, force)
-        .context("fetching local NSD devices")?;
+    let pooled = crate::nsd::local_pooled(runner, device_cache, force)?;
 
     by_pool(pooled, output)?;
 
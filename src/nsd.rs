@@ -6,9 +6,10 @@ use std::ffi::OsStr;
 use std::fs::File;
 use std::io::{BufRead, BufReader, BufWriter, Write};
 use std::path::Path;
-use std::process::Command;
 
-use anyhow::{Context, Result, anyhow};
+use crate::parse::{FromRecord, Record};
+use crate::run::{Cmd, Runner};
+use crate::{Error, Result};
 
 /// Returns the default local device cache path.
 pub const DEFAULT_LOCAL_DEVICE_CACHE: &str = "/run/mmlocal-nsd-device-cache";
@@ -18,13 +19,10 @@ pub const DEFAULT_LOCAL_DEVICE_CACHE: &str = "/run/mmlocal-nsd-device-cache";
 /// # Errors
 ///
 /// Returns an error if running `mmlsnsd` fails or if parsing its output fails.
-pub fn all() -> Result<Nsds> {
-    let mut cmd = Command::new("mmlsnsd");
-    cmd.args(["-X", "-Y"]);
+pub fn all(runner: &dyn Runner) -> Result<Nsds> {
+    let cmd = Cmd::new("mmlsnsd").args(["-X", "-Y"]);
 
-    let output = cmd
-        .output()
-        .with_context(|| format!("error running: {cmd:?}"))?;
+    let output = runner.run_checked(&cmd)?;
 
     Nsds::from_reader(output.stdout.as_slice())
 }
@@ -38,11 +36,10 @@ pub fn all() -> Result<Nsds> {
 ///
 /// Returns an error if either fetching the local node name or fetching the NSD
 /// list fails.
-pub fn local() -> Result<Nsds> {
-    let node = crate::state::local_node_name()
-        .context("determining local node name")?;
+pub fn local(runner: &dyn Runner) -> Result<Nsds> {
+    let node = crate::state::local_node_name(runner)?;
 
-    let mut nsds = crate::nsd::all()?;
+    let mut nsds = crate::nsd::all(runner)?;
     nsds.0.retain(|nsd| nsd.server_list().contains(&node));
     nsds.0.shrink_to_fit();
 
@@ -59,44 +56,46 @@ pub fn local() -> Result<Nsds> {
 ///
 /// Returns an error if fetching the [`local`] NSDs fails, or if I/O to the
 /// cache file fails.
-pub fn local_cached<Cache>(cache: Cache, force: bool) -> Result<Nsds>
+pub fn local_cached<Cache>(
+    runner: &dyn Runner,
+    cache: Cache,
+    force: bool,
+) -> Result<Nsds>
 where
     Cache: AsRef<Path>,
 {
     let cache = cache.as_ref();
 
     if force || !cache.exists() {
-        write_cache(cache)
+        write_cache(runner, cache)
     } else {
-        read_cache(cache)
+        read_cache(runner, cache)
     }
 }
 
 /// Returns NSDs read from cache. Assumes cache exists.
-fn read_cache(cache: &Path) -> Result<Nsds> {
-    let node = crate::state::local_node_name()
-        .context("determining local node name")?;
+fn read_cache(runner: &dyn Runner, cache: &Path) -> Result<Nsds> {
+    let node = crate::state::local_node_name(runner)?;
 
-    let cache = File::open(cache)
-        .with_context(|| format!("opening cache file: {}", cache.display()))?;
+    let path = cache;
+    let cache = File::open(path).map_err(Error::file(path))?;
 
     let cache = BufReader::new(cache);
 
     let mut nsds = Nsds::default();
 
-    for line in cache.lines() {
-        let line = line?;
-        let mut tokens = line.split(':');
+    for (line, content) in (1..).zip(cache.lines()) {
+        let content = content.map_err(Error::file(path))?;
+        let mut tokens = content.split(':');
 
-        let name = tokens
-            .next()
-            .ok_or_else(|| anyhow!("no name token"))?
-            .into();
+        let missing = |field: &str| Error::MissingField {
+            line,
+            field: field.into(),
+        };
+
+        let name = tokens.next().ok_or_else(|| missing("name"))?.into();
         let server_list = vec![node.clone()];
-        let device = tokens
-            .next()
-            .ok_or_else(|| anyhow!("no device token"))?
-            .into();
+        let device = tokens.next().ok_or_else(|| missing("device"))?.into();
 
         let nsd = Nsd {
             name,
@@ -110,19 +109,21 @@ fn read_cache(cache: &Path) -> Result<Nsds> {
     Ok(nsds)
 }
 
-fn write_cache(cache: &Path) -> Result<Nsds> {
-    let nsds = crate::nsd::local().context("fetching local NSD devices")?;
+fn write_cache(runner: &dyn Runner, cache: &Path) -> Result<Nsds> {
+    let nsds = crate::nsd::local(runner)?;
 
-    let cache = File::create(cache).with_context(|| {
-        format!("creating cache file: {}", cache.display())
-    })?;
+    let path = cache;
+    let cache = File::create(path).map_err(Error::file(path))?;
 
     let mut cache = BufWriter::new(cache);
 
     for nsd in &nsds {
-        writeln!(cache, "{}:{}", nsd.name(), nsd.device())?;
+        writeln!(cache, "{}:{}", nsd.name(), nsd.device())
+            .map_err(Error::file(path))?;
     }
 
+    cache.flush().map_err(Error::file(path))?;
+
     Ok(nsds)
 }
 
@@ -132,12 +133,20 @@ fn write_cache(cache: &Path) -> Result<Nsds> {
 
 /// File system and pool tuple.
 #[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct FsPoolId {
     fs: String,
     pool: String,
 }
 
 impl FsPoolId {
+    pub(crate) fn new(fs: impl Into<String>, pool: impl Into<String>) -> Self {
+        Self {
+            fs: fs.into(),
+            pool: pool.into(),
+        }
+    }
+
     /// Returns the file system.
     #[must_use]
     pub fn fs(&self) -> &str {
@@ -170,64 +179,63 @@ impl IntoIterator for ByPool {
 ///
 /// Returns an error if either of the used `mm*` commands fails or if writing
 /// to the output stream fails.
-pub fn local_pooled<Cache>(device_cache: Cache, force: bool) -> Result<ByPool>
+pub fn local_pooled<Cache>(
+    runner: &dyn Runner,
+    device_cache: Cache,
+    force: bool,
+) -> Result<ByPool>
 where
     Cache: AsRef<Path>,
 {
-    let nsds = local_cached(device_cache, force)?.into_inner();
+    let nsds = local_cached(runner, device_cache, force)?.into_inner();
+
+    let pools = pool_ids(runner)?;
 
     let mut pooled = ByPool::default();
 
-    for fs in crate::fs::names()? {
-        let disks = crate::disk::disks(&fs)
-            .with_context(|| format!("fetching disks for file system {fs}"))?;
-
-        for nsd in &nsds {
-            if let Some(disk) =
-                disks.iter().find(|disk| disk.nsd_name() == nsd.name())
-            {
-                let id = FsPoolId {
-                    fs: (&fs).into(),
-                    pool: disk.pool().into(),
-                };
-
-                let devices = pooled.0.entry(id).or_default();
-                devices.0.push(nsd.clone());
-            }
+    for nsd in nsds {
+        if let Some(id) = pools.get(nsd.name()) {
+            pooled.0.entry(id.clone()).or_default().0.push(nsd);
         }
     }
 
     Ok(pooled)
 }
 
+/// Returns the file system and pool of every NSD that belongs to a file
+/// system.
+///
+/// # Errors
+///
+/// Returns an error if running `mmlsfs` or `mmlsdisk` fails or if parsing
+/// their output fails.
+pub fn pool_ids(runner: &dyn Runner) -> Result<HashMap<String, FsPoolId>> {
+    let mut ids = HashMap::new();
+
+    for fs in crate::fs::names(runner)? {
+        for disk in &crate::disk::disks(runner, &fs)? {
+            ids.insert(
+                disk.nsd_name().into(),
+                FsPoolId::new(&fs, disk.pool()),
+            );
+        }
+    }
+
+    Ok(ids)
+}
+
 // ----------------------------------------------------------------------------
 // data
 // ----------------------------------------------------------------------------
 
 /// Parsed NSDs.
 #[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Nsds(Vec<Nsd>);
 
 impl Nsds {
     fn from_reader<Input: BufRead>(input: Input) -> Result<Self> {
-        let mut index = Index::default();
-        let mut nsds = Self::default();
-
-        for line in input.lines() {
-            let line = line?;
-
-            let tokens = line.split(':').collect::<Vec<_>>();
-
-            if tokens[2] == "HEADER" {
-                index = Index::default();
-                header_to_index(&tokens, &mut index);
-            } else {
-                let entry = Nsd::from_tokens(&tokens, &index)?;
-                nsds.0.push(entry);
-            }
-        }
-
-        Ok(nsds)
+        crate::parse::from_reader(input).map(Self)
     }
 
     /// Returns an [`Iterator`] over the NSDs.
@@ -262,6 +270,7 @@ impl<'a> IntoIterator for &'a Nsds {
 
 /// NSD data.
 #[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Nsd {
     name: String,
     server_list: Vec<String>,
@@ -296,8 +305,9 @@ impl Nsd {
     pub fn device_name(&self) -> Result<Cow<'_, str>> {
         Path::new(&self.device)
             .file_name()
-            .ok_or_else(|| {
-                anyhow!("unable to get file name for device {}", self.device)
+            .ok_or_else(|| Error::InvalidValue {
+                kind: "device path",
+                value: self.device.clone(),
             })
             .map(OsStr::to_string_lossy)
     }
@@ -307,49 +317,26 @@ impl Nsd {
 // boiler-platy parsing
 // ----------------------------------------------------------------------------
 
-impl Nsd {
-    fn from_tokens(tokens: &[&str], index: &Index) -> Result<Self> {
-        let name_index =
-            index.name.ok_or_else(|| anyhow!("no disk name index"))?;
-        let name = tokens[name_index].into();
-
-        let server_list_index = index
-            .server_list
-            .ok_or_else(|| anyhow!("no server list index"))?;
-        let server_list = tokens[server_list_index];
-        let server_list = server_list.split(',').map(Into::into).collect();
-
-        let local_name_index = index
-            .local_name
-            .ok_or_else(|| anyhow!("no local disk name index"))?;
-        let local_name = tokens[local_name_index].into();
+impl FromRecord for Nsd {
+    fn from_record(record: &Record) -> Result<Self> {
+        let name = record.get("diskName")?.into();
+
+        let server_list = record
+            .get("serverList")?
+            .split(',')
+            .map(Into::into)
+            .collect();
+
+        let device = record.get("localDiskName")?.into();
 
         Ok(Self {
             name,
             server_list,
-            device: local_name,
+            device,
         })
     }
 }
 
-#[derive(Debug, Default)]
-struct Index {
-    name: Option<usize>,
-    server_list: Option<usize>,
-    local_name: Option<usize>,
-}
-
-fn header_to_index(tokens: &[&str], index: &mut Index) {
-    for (i, token) in tokens.iter().enumerate() {
-        match *token {
-            "diskName" => index.name = Some(i),
-            "serverList" => index.server_list = Some(i),
-            "localDiskName" => index.local_name = Some(i),
-            _ => {}
-        }
-    }
-}
-
 // ----------------------------------------------------------------------------
 // tests
 // ----------------------------------------------------------------------------
@@ -0,0 +1,476 @@
+//! Generic `mm* -Y` parsing.
+//!
+//! The machine-readable output of all `mm*` commands follows the same scheme.
+//! Every line is a list of colon-separated fields. The first field is the
+//! command, the second one is the section (which is empty for commands that
+//! only have one kind of output). If the third field is `HEADER`, the line
+//! declares the field names of all following records of that section:
+//!
+//! ```text
+//! mmlsmgr:clusterManager:HEADER:version:reserved:reserved:manager:
+//! mmlsmgr:clusterManager:0:1:::filer1:
+//! ```
+//!
+//! This module turns such a stream into [`Record`]s, which allow looking up
+//! values by field name. Types that can be created from a single record
+//! implement [`FromRecord`].
+//!
+//! Values containing characters that would clash with the format, like `:` in
+//! timestamps or `/` in paths, are percent-encoded by the `mm*` commands. All
+//! record values are [`percent_decode`]d.
+//!
+//! # Examples
+//!
+//! ```
+//! use mmoxi::parse::{FromRecord, Record};
+//!
+//! struct Manager {
+//!     name: String,
+//! }
+//!
+//! impl FromRecord for Manager {
+//!     fn from_record(record: &Record) -> mmoxi::Result<Self> {
+//!         let name = record.get("manager")?.into();
+//!         Ok(Self { name })
+//!     }
+//! }
+//!
+//! # fn main() -> mmoxi::Result<()> {
+//! let input = "\
+//! mmlsmgr:clusterManager:HEADER:version:reserved:reserved:manager:
+//! mmlsmgr:clusterManager:0:1:::filer1:
+//! ";
+//!
+//! let managers: Vec<Manager> = mmoxi::parse::from_reader(input.as_bytes())?;
+//! assert_eq!(managers[0].name, "filer1");
+//! # Ok(())
+//! # }
+//! ```
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::io::{BufRead, Lines};
+use std::rc::Rc;
+use std::str::FromStr;
+
+use crate::{Error, Result};
+
+/// Creates a value from a single `mm* -Y` record.
+pub trait FromRecord: Sized {
+    /// Creates a value from the given record.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a required field is missing or if its value can't
+    /// be parsed.
+    fn from_record(record: &Record) -> Result<Self>;
+}
+
+/// Parses all records of `input` to `T`.
+///
+/// # Errors
+///
+/// Returns an error if reading from `input` fails, if it isn't valid `-Y`
+/// output, or if any record can't be converted to `T`.
+pub fn from_reader<T, Input>(input: Input) -> Result<Vec<T>>
+where
+    T: FromRecord,
+    Input: BufRead,
+{
+    records(input)
+        .map(|record| record.and_then(|record| T::from_record(&record)))
+        .collect()
+}
+
+/// Returns an [`Iterator`] over the records of `input`.
+///
+/// Header lines are consumed by the iterator and are not returned. Empty lines
+/// and report banners starting with `***`, as printed by e.g. `mmrepquota`,
+/// are skipped.
+pub fn records<Input: BufRead>(input: Input) -> Records<Input> {
+    Records {
+        lines: input.lines(),
+        line: 0,
+        headers: HashMap::default(),
+    }
+}
+
+/// An [`Iterator`] over `mm* -Y` records.
+///
+/// This `struct` is created by the [`records`] function.
+pub struct Records<Input> {
+    lines: Lines<Input>,
+    line: usize,
+    headers: HashMap<(String, String), Rc<Header>>,
+}
+
+impl<Input: BufRead> Records<Input> {
+    fn parse_line(&mut self, line: &str) -> Result<Option<Record>> {
+        let tokens = line.split(':').collect::<Vec<_>>();
+
+        let [command, section, kind, ..] = tokens.as_slice() else {
+            return Err(Error::Syntax {
+                line: self.line,
+                message: format!("not a `-Y` line: {line}"),
+            });
+        };
+
+        let key = ((*command).to_owned(), (*section).to_owned());
+
+        if *kind == "HEADER" {
+            let fields = tokens
+                .iter()
+                .enumerate()
+                .skip(3)
+                .filter(|(_, name)| !name.is_empty())
+                .map(|(i, name)| ((*name).to_owned(), i))
+                .collect();
+
+            let header = Header {
+                command: key.0.clone(),
+                section: key.1.clone(),
+                fields,
+            };
+
+            self.headers.insert(key, Rc::new(header));
+
+            return Ok(None);
+        }
+
+        let Some(header) = self.headers.get(&key) else {
+            let (command, section) = key;
+
+            return Err(Error::MissingHeader {
+                line: self.line,
+                command,
+                section,
+            });
+        };
+
+        let record = Record {
+            header: Rc::clone(header),
+            line: self.line,
+            values: tokens
+                .into_iter()
+                .map(|value| percent_decode(value).into_owned())
+                .collect(),
+        };
+
+        Ok(Some(record))
+    }
+}
+
+impl<Input: BufRead> Iterator for Records<Input> {
+    type Item = Result<Record>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let line = match self.lines.next()? {
+                Ok(line) => line,
+                Err(error) => return Some(Err(error.into())),
+            };
+
+            self.line += 1;
+
+            if line.is_empty() || line.starts_with("***") {
+                continue;
+            }
+
+            match self.parse_line(&line) {
+                Ok(Some(record)) => return Some(Ok(record)),
+                Ok(None) => {}
+                Err(error) => return Some(Err(error)),
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Header {
+    command: String,
+    section: String,
+    fields: HashMap<String, usize>,
+}
+
+/// A single `mm* -Y` record.
+#[derive(Debug)]
+pub struct Record {
+    header: Rc<Header>,
+    line: usize,
+    values: Vec<String>,
+}
+
+impl Record {
+    /// Returns the command, e.g. `mmlsdisk`.
+    #[must_use]
+    pub fn command(&self) -> &str {
+        &self.header.command
+    }
+
+    /// Returns the section, which may be empty.
+    #[must_use]
+    pub fn section(&self) -> &str {
+        &self.header.section
+    }
+
+    /// Returns the line number of this record in the input, starting at 1.
+    #[must_use]
+    pub const fn line(&self) -> usize {
+        self.line
+    }
+
+    /// Returns the value of `field`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the header has no such field or if the record is
+    /// too short to contain it.
+    pub fn get(&self, field: &str) -> Result<&str> {
+        let index = self.header.fields.get(field).ok_or_else(|| {
+            Error::MissingField {
+                line: self.line,
+                field: field.into(),
+            }
+        })?;
+
+        self.values.get(*index).map(String::as_str).ok_or_else(|| {
+            Error::Syntax {
+                line: self.line,
+                message: format!("record too short for {field} field"),
+            }
+        })
+    }
+
+    /// Optionally returns the value of `field`.
+    ///
+    /// Returns `None` if the header has no such field or if the record is too
+    /// short to contain it.
+    #[must_use]
+    pub fn get_opt(&self, field: &str) -> Option<&str> {
+        self.header
+            .fields
+            .get(field)
+            .and_then(|index| self.values.get(*index))
+            .map(String::as_str)
+    }
+
+    /// Parses the value of `field` to `T`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the field can't be found (see [`Record::get`]) or
+    /// if parsing its value fails.
+    pub fn parse<T>(&self, field: &str) -> Result<T>
+    where
+        T: FromStr,
+        T::Err: Display,
+    {
+        let value = self.get(field)?;
+
+        value.parse().map_err(|error: T::Err| Error::Parse {
+            line: self.line,
+            field: field.into(),
+            value: value.into(),
+            message: error.to_string(),
+        })
+    }
+}
+
+/// Decodes a percent-encoded value, e.g. `%2Fgpfs1%2Fpublic` to
+/// `/gpfs1/public`.
+///
+/// Invalid escape sequences are kept as they are. If the decoded bytes are not
+/// valid UTF-8, invalid sequences are replaced with
+/// [`U+FFFD REPLACEMENT CHARACTER`][std::char::REPLACEMENT_CHARACTER].
+#[must_use]
+pub fn percent_decode(s: &str) -> Cow<'_, str> {
+    if !s.contains('%') {
+        return Cow::Borrowed(s);
+    }
+
+    let bytes = s.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let escaped = match bytes.get(i..i + 3) {
+            Some(&[b'%', hi, lo]) => hex_value(hi).zip(hex_value(lo)),
+            _ => None,
+        };
+
+        if let Some((hi, lo)) = escaped {
+            decoded.push((hi << 4) | lo);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+
+    match String::from_utf8(decoded) {
+        Ok(decoded) => Cow::Owned(decoded),
+        Err(error) => {
+            Cow::Owned(String::from_utf8_lossy(error.as_bytes()).into_owned())
+        }
+    }
+}
+
+const fn hex_value(c: u8) -> Option<u8> {
+    match c {
+        b'0'..=b'9' => Some(c - b'0'),
+        b'a'..=b'f' => Some(c - b'a' + 10),
+        b'A'..=b'F' => Some(c - b'A' + 10),
+        _ => None,
+    }
+}
+
+// ----------------------------------------------------------------------------
+// tests
+// ----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode() {
+        assert_eq!(percent_decode("gpfs1"), "gpfs1");
+        assert_eq!(percent_decode("%2Fgpfs1%2Fpublic"), "/gpfs1/public");
+        assert_eq!(percent_decode("14%3A18%3A40"), "14:18:40");
+        assert_eq!(percent_decode("%3a"), ":");
+        assert_eq!(percent_decode("100%25"), "100%");
+        assert_eq!(percent_decode("caf%C3%A9"), "café");
+    }
+
+    #[test]
+    fn decode_invalid() {
+        assert_eq!(percent_decode("100%"), "100%");
+        assert_eq!(percent_decode("%2"), "%2");
+        assert_eq!(percent_decode("%zz%2F"), "%zz/");
+        assert_eq!(percent_decode("%+1"), "%+1");
+        assert_eq!(percent_decode("%FF"), "\u{FFFD}");
+    }
+
+    #[test]
+    fn decode_records() {
+        let input = include_str!("diag-deadlock-example.in");
+
+        let record = records(input.as_bytes()).next().unwrap().unwrap();
+
+        assert_eq!(
+            record.get("waitStartTime").unwrap(),
+            "2025-08-25_06:11:57+0200"
+        );
+    }
+
+    #[test]
+    fn sections() {
+        let input = include_str!("mgr-example.in");
+
+        let records = records(input.as_bytes())
+            .collect::<Result<Vec<_>>>()
+            .unwrap();
+
+        assert_eq!(records.len(), 3);
+
+        assert_eq!(records[0].command(), "mmlsmgr");
+        assert_eq!(records[0].section(), "clusterManager");
+        assert_eq!(records[0].line(), 3);
+        assert_eq!(records[0].get("manager").unwrap(), "filer1");
+
+        assert_eq!(records[1].section(), "filesystemManager");
+        assert_eq!(records[1].get("filesystem").unwrap(), "gpfs1");
+        assert_eq!(records[1].get("manager").unwrap(), "filer2");
+        assert_eq!(records[1].get("managerIP").unwrap(), "10.10.21.2");
+
+        assert_eq!(records[2].get("filesystem").unwrap(), "gpfs2");
+    }
+
+    #[test]
+    fn missing_field() {
+        let input = "\
+mmlsmgr:clusterManager:HEADER:version:reserved:reserved:manager:
+mmlsmgr:clusterManager:0:1:::filer1:
+";
+
+        let record = records(input.as_bytes()).next().unwrap().unwrap();
+
+        assert!(matches!(
+            record.get("managerIP"),
+            Err(Error::MissingField { line: 2, field }) if field == "managerIP"
+        ));
+        assert_eq!(record.get_opt("managerIP"), None);
+    }
+
+    #[test]
+    fn short_record() {
+        let input = "\
+mmlsdisk::HEADER:version:reserved:reserved:nsdName:driverType:sectorSize:
+mmlsdisk::0:1:::disk1:
+";
+
+        let record = records(input.as_bytes()).next().unwrap().unwrap();
+
+        assert_eq!(record.get("nsdName").unwrap(), "disk1");
+        assert!(matches!(
+            record.get("sectorSize"),
+            Err(Error::Syntax { line: 2, .. })
+        ));
+        assert!(record.parse::<u64>("sectorSize").is_err());
+    }
+
+    #[test]
+    fn invalid_value() {
+        let input = "\
+mmlsdisk::HEADER:version:reserved:reserved:nsdName:sectorSize:
+mmlsdisk::0:1:::disk1:foo:
+";
+
+        let record = records(input.as_bytes()).next().unwrap().unwrap();
+
+        assert!(matches!(
+            record.parse::<u64>("sectorSize"),
+            Err(Error::Parse { line: 2, field, value, .. })
+                if field == "sectorSize" && value == "foo"
+        ));
+    }
+
+    #[test]
+    fn no_header() {
+        let input = "mmlsdisk::0:1:::disk1:\n";
+
+        let mut records = records(input.as_bytes());
+
+        assert!(matches!(
+            records.next().unwrap(),
+            Err(Error::MissingHeader { line: 1, command, .. })
+                if command == "mmlsdisk"
+        ));
+    }
+
+    #[test]
+    fn not_y_output() {
+        let input = "mmlsdisk: File system gpfs1 is not mounted.\n";
+
+        let mut records = records(input.as_bytes());
+
+        assert!(matches!(
+            records.next().unwrap(),
+            Err(Error::Syntax { line: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn skip_empty_and_banner() {
+        let input = include_str!("quota-example.in");
+
+        let records = records(input.as_bytes())
+            .collect::<Result<Vec<_>>>()
+            .unwrap();
+
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].get("quotaType").unwrap(), "FILESET");
+        assert_eq!(records[1].get("quotaType").unwrap(), "USR");
+    }
+}
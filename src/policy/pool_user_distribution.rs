@@ -1,18 +1,40 @@
 //! Pool-based user distribution.
 
 use std::collections::HashMap;
-use std::ffi::OsStr;
 use std::fs::File;
-use std::io::{self, BufRead, BufReader, Write};
+use std::io::{self, BufRead, BufReader};
 use std::ops::AddAssign;
-use std::path::Path;
-use std::process::{Command, Stdio};
+use std::path::PathBuf;
 
-use anyhow::{Context, Result, anyhow};
 use bstr::ByteSlice;
 use bstr::io::BufReadExt;
 use tempfile::{tempdir, tempdir_in};
 
+use crate::prom::{Family, ToMetrics};
+use crate::run::{Cmd, Runner};
+use crate::{Error, Result};
+
+/// Optional arguments for [`run`].
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Options {
+    /// Only consider files of this fileset.
+    pub fileset: Option<String>,
+
+    /// Nodes to run on, used for `mmapplypolicy -N`.
+    pub nodes: Option<String>,
+
+    /// Local work directory, used for `mmapplypolicy -s` and for the policy
+    /// output.
+    pub local_work_dir: Option<PathBuf>,
+
+    /// Global work directory, used for `mmapplypolicy -g`.
+    pub global_work_dir: Option<PathBuf>,
+
+    /// Scope, used for `mmapplypolicy --scope`.
+    pub scope: Option<String>,
+}
+
 /// Runs `mmapplypolicy` on a file system pool to find out how much file sizes
 /// users have.
 ///
@@ -23,19 +45,16 @@ use tempfile::{tempdir, tempdir_in};
 /// - running `mmapplypolicy`
 /// - parsing `mmapplypolicy` output
 pub fn run(
+    runner: &dyn Runner,
     device_or_dir: impl Into<String>,
     pool: impl Into<String>,
-    fileset: Option<impl AsRef<str>>,
-    nodes: Option<impl AsRef<OsStr>>,
-    local_work_dir: Option<impl AsRef<Path>>,
-    global_work_dir: Option<impl AsRef<Path>>,
-    scope: Option<impl AsRef<str>>,
+    options: &Options,
 ) -> Result<Data> {
     let device_or_dir = device_or_dir.into();
     let pool = pool.into();
 
-    let tmp = if let Some(ref local_work_dir) = local_work_dir {
-        tempdir_in(local_work_dir)?
+    let tmp = if let Some(ref local_work_dir) = options.local_work_dir {
+        tempdir_in(local_work_dir).map_err(Error::file(local_work_dir))?
     } else {
         tempdir()?
     };
@@ -43,53 +62,39 @@ pub fn run(
     let policy = tmp.path().join(".policy");
     let prefix = tmp.path().join("pool-scanner");
 
-    let mut file = File::create(&policy)?;
-    write_policy(&mut file, &pool, fileset)?;
-    file.sync_all()?;
+    let mut file = File::create(&policy).map_err(Error::file(&policy))?;
+    write_policy(&mut file, &pool, options.fileset.as_ref())
+        .and_then(|()| file.sync_all())
+        .map_err(Error::file(&policy))?;
 
-    let mut command = Command::new("mmapplypolicy");
-    command
+    let mut cmd = Cmd::new("mmapplypolicy")
         .arg(&device_or_dir)
-        .args([OsStr::new("-P"), policy.as_os_str()])
-        .args([OsStr::new("-f"), prefix.as_os_str()])
+        .args(["-P".into(), policy.to_string_lossy().into_owned()])
+        .args(["-f".into(), prefix.to_string_lossy().into_owned()])
         .args(["--choice-algorithm", "fast"])
         .args(["-I", "defer"])
         .args(["-L", "0"]);
 
-    if let Some(nodes) = nodes {
-        command.arg("-N").arg(nodes.as_ref());
+    if let Some(ref nodes) = options.nodes {
+        cmd = cmd.arg("-N").arg(nodes);
     }
 
-    if let Some(local_work_dir) = local_work_dir {
-        command.arg("-s").arg(local_work_dir.as_ref());
+    if let Some(ref local_work_dir) = options.local_work_dir {
+        cmd = cmd.arg("-s").arg(local_work_dir.to_string_lossy());
     }
 
-    if let Some(global_work_dir) = global_work_dir {
-        command.arg("-g").arg(global_work_dir.as_ref());
+    if let Some(ref global_work_dir) = options.global_work_dir {
+        cmd = cmd.arg("-g").arg(global_work_dir.to_string_lossy());
     }
 
-    if let Some(scope) = scope {
-        command.arg("--scope").arg(scope.as_ref());
+    if let Some(ref scope) = options.scope {
+        cmd = cmd.arg("--scope").arg(scope);
     }
 
-    let mut child = command.stdout(Stdio::null()).spawn().context(
-        "mmapplypolicy failed to start, make sure it's on your PATH",
-    )?;
-
-    let ecode = child
-        .wait()
-        .with_context(|| "failed waiting on mmapplypolicy")?;
-
-    if !ecode.success() {
-        return Err(anyhow!(
-            "mmapplypolicy was no success, exit code: {ecode}"
-        ));
-    }
+    runner.run_checked(&cmd)?;
 
     let list = tmp.path().join("pool-scanner.list.users");
-    let list = File::open(&list).with_context(|| {
-        format!("failed to open policy output: {}", list.display())
-    })?;
+    let list = File::open(&list).map_err(Error::file(&list))?;
     let list = BufReader::new(list);
 
     let mut raw = sum(list)?;
@@ -110,69 +115,41 @@ pub fn run(
 }
 
 /// The data structure returned by this module.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Data {
     device_or_dir: String,
     pool: String,
     raw: HashMap<String, Summary>,
 }
 
-impl crate::prom::ToText for Data {
-    fn to_prom(&self, output: &mut impl Write) -> Result<()> {
-        let device_or_dir = &self.device_or_dir;
-        let pool = &self.pool;
-
-        writeln!(
-            output,
-            "# HELP gpfs_pool_user_distribution_files GPFS pool files per user"
-        )?;
-
-        writeln!(output, "# TYPE gpfs_pool_user_distribution_files gauge")?;
-
-        for (user, data) in &self.raw {
-            writeln!(
-                output,
-                "gpfs_pool_user_distribution_files{{device_or_dir=\"{}\",pool=\"{}\",user=\"{}\"}} {}",
-                device_or_dir, pool, user, data.files,
-            )?;
-        }
-
-        writeln!(
-            output,
-            "# HELP gpfs_pool_user_distribution_file_size GPFS pool file size per user in bytes"
-        )?;
-
-        writeln!(
-            output,
-            "# TYPE gpfs_pool_user_distribution_file_size gauge"
-        )?;
-
-        for (user, data) in &self.raw {
-            writeln!(
-                output,
-                "gpfs_pool_user_distribution_file_size{{device_or_dir=\"{}\",pool=\"{}\",user=\"{}\"}} {}",
-                device_or_dir, pool, user, data.file_size,
-            )?;
-        }
-
-        writeln!(
-            output,
-            "# HELP gpfs_pool_user_distribution_allocated GPFS pool allocated storage per user in kilobytes"
-        )?;
-
-        writeln!(
-            output,
-            "# TYPE gpfs_pool_user_distribution_allocated gauge"
-        )?;
+impl ToMetrics for Data {
+    fn to_metrics(&self) -> Vec<Family> {
+        let mut files = Family::gauge(
+            "gpfs_pool_user_distribution_files",
+            "GPFS pool files per user",
+        );
+        let mut file_size = Family::gauge(
+            "gpfs_pool_user_distribution_file_size",
+            "GPFS pool file size per user in bytes",
+        );
+        let mut allocated = Family::gauge(
+            "gpfs_pool_user_distribution_allocated",
+            "GPFS pool allocated storage per user in kilobytes",
+        );
 
         for (user, data) in &self.raw {
-            writeln!(
-                output,
-                "gpfs_pool_user_distribution_allocated{{device_or_dir=\"{}\",pool=\"{}\",user=\"{}\"}} {}",
-                device_or_dir, pool, user, data.kb_allocated,
-            )?;
+            let labels = [
+                ("device_or_dir", self.device_or_dir.as_str()),
+                ("pool", &self.pool),
+                ("user", user),
+            ];
+
+            files.sample(&labels, data.files);
+            file_size.sample(&labels, data.file_size);
+            allocated.sample(&labels, data.kb_allocated);
         }
 
-        Ok(())
+        vec![files, file_size, allocated]
     }
 }
 
@@ -180,6 +157,7 @@ impl crate::prom::ToText for Data {
 #[derive(
     Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Default,
 )]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Summary {
     /// Returns the number of files.
     pub files: u64,
@@ -205,37 +183,43 @@ where
 {
     let mut user_sizes: HashMap<String, Summary> = HashMap::default();
 
-    for line in input.byte_lines() {
-        let line = line?;
+    for (line, content) in (1..).zip(input.byte_lines()) {
+        let content = content?;
+
+        let missing = |field: &str| Error::MissingField {
+            line,
+            field: field.into(),
+        };
 
-        let mut payload = line
+        let number = |value: &str, field: &str| {
+            value.parse::<u64>().map_err(|error| Error::Parse {
+                line,
+                field: field.into(),
+                value: value.into(),
+                message: error.to_string(),
+            })
+        };
+
+        let payload = content
             .splitn_str(6, " ")
             .nth(4)
-            .context("no payload field")?
-            .splitn_str(3, ":");
-
-        let user = payload.next().context("no USER_ID field in payload")?;
-        let user = user
-            .to_str()
-            .with_context(|| format!("not UTF-8: {user:?}"))?;
-
-        let file_size =
-            payload.next().context("no FILE_SIZE field in payload")?;
-        let file_size = file_size
-            .to_str()
-            .with_context(|| format!("not UTF-8: {file_size:?}"))?;
-        let file_size: u64 = file_size
-            .parse()
-            .with_context(|| format!("not a number: {file_size}"))?;
+            .ok_or_else(|| missing("payload"))?;
+        let payload = payload.to_str().map_err(|error| Error::Parse {
+            line,
+            field: "payload".into(),
+            value: payload.to_str_lossy().into_owned(),
+            message: error.to_string(),
+        })?;
+        let mut payload = payload.splitn(3, ':');
+
+        let user = payload.next().ok_or_else(|| missing("USER_ID"))?;
+
+        let file_size = payload.next().ok_or_else(|| missing("FILE_SIZE"))?;
+        let file_size = number(file_size, "FILE_SIZE")?;
 
         let kb_allocated =
-            payload.next().context("no KB_ALLOCATED field in payload")?;
-        let kb_allocated = kb_allocated
-            .to_str()
-            .with_context(|| format!("not UTF-8: {kb_allocated:?}"))?;
-        let kb_allocated: u64 = kb_allocated
-            .parse()
-            .with_context(|| format!("not a number: {kb_allocated}"))?;
+            payload.next().ok_or_else(|| missing("KB_ALLOCATED"))?;
+        let kb_allocated = number(kb_allocated, "KB_ALLOCATED")?;
 
         *user_sizes.entry(user.into()).or_default() += Summary {
             files: 1,
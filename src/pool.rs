@@ -3,11 +3,12 @@
 #![deny(clippy::all)]
 #![warn(clippy::pedantic, clippy::nursery, clippy::cargo)]
 
-use std::io::Write;
-use std::process::Command;
+use std::io;
 use std::str::FromStr;
 
-use anyhow::{Context, Result, anyhow};
+use crate::prom::{Family, ToMetrics, kb_to_bytes};
+use crate::run::{Cmd, Runner};
+use crate::{Error, Result};
 
 // ----------------------------------------------------------------------------
 // CLI interface
@@ -19,29 +20,20 @@ use anyhow::{Context, Result, anyhow};
 ///
 /// Returns an error if running `mmlspool` fails or if parsing its output
 /// fails.
-pub fn run(fs_name: &str) -> Result<Filesystem> {
-    let mut cmd = Command::new("mmlspool");
-    cmd.arg(fs_name);
-
-    let output = cmd
-        .output()
-        .with_context(|| format!("error running: {cmd:?}"))?;
-
-    if output.status.success() {
-        let output = String::from_utf8(output.stdout).with_context(|| {
-            format!("parsing {cmd:?} command output to UTF8")
-        })?;
-
-        let pools = parse_mmlspool_output(&output)
-            .context("parsing pools to internal data")?;
-
-        Ok(Filesystem {
-            name: fs_name.into(),
-            pools,
-        })
-    } else {
-        Err(anyhow!("error running: {cmd:?}"))
-    }
+pub fn run(runner: &dyn Runner, fs_name: &str) -> Result<Filesystem> {
+    let cmd = Cmd::new("mmlspool").arg(fs_name);
+
+    let output = runner.run_checked(&cmd)?;
+
+    let output = String::from_utf8(output.stdout)
+        .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+
+    let pools = parse_mmlspool_output(&output)?;
+
+    Ok(Filesystem {
+        name: fs_name.into(),
+        pools,
+    })
 }
 
 /// Runs `mmlspool` on all given filesystems, and returns the parsed output.
@@ -50,14 +42,17 @@ pub fn run(fs_name: &str) -> Result<Filesystem> {
 ///
 /// Returns an error if running `mmlspool` fails or if parsing its output
 /// fails.
-pub fn run_all<S>(fs_names: &[S]) -> Result<Vec<Filesystem>>
+pub fn run_all<S>(
+    runner: &dyn Runner,
+    fs_names: &[S],
+) -> Result<Vec<Filesystem>>
 where
     S: AsRef<str>,
 {
     let mut filesystems = Vec::with_capacity(fs_names.len());
 
     for fs in fs_names {
-        let filesystem = run(fs.as_ref())?;
+        let filesystem = run(runner, fs.as_ref())?;
         filesystems.push(filesystem);
     }
 
@@ -70,6 +65,7 @@ where
 
 /// A file system.
 #[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Filesystem {
     name: String,
     pools: Vec<Pool>,
@@ -93,6 +89,7 @@ impl Filesystem {
 #[derive(
     Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Default,
 )]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Size {
     total_kb: u64,
     free_kb: u64,
@@ -124,6 +121,7 @@ impl Size {
 
 /// A storage pool.
 #[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Pool {
     name: String,
     data: Option<Size>,
@@ -150,52 +148,50 @@ impl Pool {
     }
 }
 
-impl FromStr for Pool {
-    type Err = anyhow::Error;
+impl Pool {
+    fn from_line(line: usize, s: &str) -> Result<Self> {
+        let parse = |field: &str, value: &str| {
+            value.parse::<u64>().map_err(|error| Error::Parse {
+                line,
+                field: field.into(),
+                value: value.into(),
+                message: error.to_string(),
+            })
+        };
 
-    fn from_str(s: &str) -> Result<Self> {
         let tokens = s
             .split(' ')
             .filter(|token| !token.is_empty())
             .collect::<Vec<_>>();
 
-        let name = tokens[0].into();
+        let token = |index: usize| {
+            tokens.get(index).copied().ok_or_else(|| Error::Syntax {
+                line,
+                message: format!(
+                    "expected at least {} fields, found {}",
+                    index + 1,
+                    tokens.len()
+                ),
+            })
+        };
 
-        let data = if tokens[4] == "yes" {
-            let total_kb = tokens[6].parse::<u64>().with_context(|| {
-                format!("parsing data totalkb token {} to u64", tokens[6])
-            })?;
+        let name = token(0)?.into();
 
-            let free_kb = tokens[7].parse::<u64>().with_context(|| {
-                format!("parsing data freekb token {} to u64", tokens[7])
-            })?;
+        let data = if token(4)? == "yes" {
+            let total_kb = parse("data totalkb", token(6)?)?;
+            let free_kb = parse("data freekb", token(7)?)?;
 
             Some(Size { total_kb, free_kb })
         } else {
             None
         };
 
-        let meta = if tokens[5] == "yes" {
+        let meta = if token(5)? == "yes" {
             let (total_kb_token_id, free_kb_token_id) =
-                if tokens[8] == "(" { (10, 11) } else { (9, 10) };
-
-            let total_kb = tokens[total_kb_token_id]
-                .parse::<u64>()
-                .with_context(|| {
-                    format!(
-                        "parsing meta totalkb token {} to u64",
-                        tokens[total_kb_token_id]
-                    )
-                })?;
-
-            let free_kb = tokens[free_kb_token_id]
-                .parse::<u64>()
-                .with_context(|| {
-                    format!(
-                        "parsing meta freekb token {} to u64",
-                        tokens[free_kb_token_id]
-                    )
-                })?;
+                if token(8)? == "(" { (10, 11) } else { (9, 10) };
+
+            let total_kb = parse("meta totalkb", token(total_kb_token_id)?)?;
+            let free_kb = parse("meta freekb", token(free_kb_token_id)?)?;
 
             Some(Size { total_kb, free_kb })
         } else {
@@ -203,82 +199,79 @@ impl FromStr for Pool {
         };
 
         if data.is_none() && meta.is_none() {
-            Err(anyhow!("pool {name} contains neither data nor metadata"))
+            Err(Error::Syntax {
+                line,
+                message: format!(
+                    "pool {name} contains neither data nor metadata"
+                ),
+            })
         } else {
             Ok(Self { name, data, meta })
         }
     }
 }
 
+impl FromStr for Pool {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Self::from_line(1, s)
+    }
+}
+
 fn parse_mmlspool_output(s: &str) -> Result<Vec<Pool>> {
     let mut pools = Vec::with_capacity(16);
 
-    for line in s.lines().skip(2) {
-        let pool = line
-            .parse()
-            .with_context(|| format!("parsing pool line: {line}"))?;
-
+    for (line, content) in (1..).zip(s.lines()).skip(2) {
+        let pool = Pool::from_line(line, content)?;
         pools.push(pool);
     }
 
     Ok(pools)
 }
 
-impl crate::prom::ToText for Vec<Filesystem> {
-    fn to_prom(&self, output: &mut impl Write) -> Result<()> {
-        writeln!(
-            output,
-            "# HELP gpfs_fs_pool_total_kbytes GPFS pool size in kilobytes."
-        )?;
-        writeln!(output, "# TYPE gpfs_fs_pool_total_kbytes gauge")?;
-
-        for fs in self {
-            for pool in &fs.pools {
-                if let Some(size) = &pool.data {
-                    writeln!(
-                        output,
-                        "gpfs_fs_pool_total_kbytes{{fs=\"{}\",pool=\"{}\",type=\"data\"}} {}",
-                        fs.name, pool.name, size.total_kb
-                    )?;
-                }
-
-                if let Some(size) = &pool.meta {
-                    writeln!(
-                        output,
-                        "gpfs_fs_pool_total_kbytes{{fs=\"{}\",pool=\"{}\",type=\"meta\"}} {}",
-                        fs.name, pool.name, size.total_kb
-                    )?;
-                }
-            }
-        }
-
-        writeln!(
-            output,
-            "# HELP gpfs_fs_pool_free_kbytes GPFS pool free kilobytes."
-        )?;
-        writeln!(output, "# TYPE gpfs_fs_pool_free_kbytes gauge")?;
+impl ToMetrics for Vec<Filesystem> {
+    fn to_metrics(&self) -> Vec<Family> {
+        let mut total = Family::gauge(
+            "gpfs_fs_pool_total_kbytes",
+            "GPFS pool size in kilobytes.",
+        );
+        let mut free = Family::gauge(
+            "gpfs_fs_pool_free_kbytes",
+            "GPFS pool free kilobytes.",
+        );
+        let mut total_bytes = Family::gauge(
+            "gpfs_fs_pool_total_bytes",
+            "GPFS pool size in bytes.",
+        )
+        .with_unit("bytes");
+        let mut free_bytes =
+            Family::gauge("gpfs_fs_pool_free_bytes", "GPFS pool free bytes.")
+                .with_unit("bytes");
 
         for fs in self {
             for pool in &fs.pools {
-                if let Some(size) = &pool.data {
-                    writeln!(
-                        output,
-                        "gpfs_fs_pool_free_kbytes{{fs=\"{}\",pool=\"{}\",type=\"data\"}} {}",
-                        fs.name, pool.name, size.free_kb
-                    )?;
-                }
-
-                if let Some(size) = &pool.meta {
-                    writeln!(
-                        output,
-                        "gpfs_fs_pool_free_kbytes{{fs=\"{}\",pool=\"{}\",type=\"meta\"}} {}",
-                        fs.name, pool.name, size.free_kb
-                    )?;
+                let sizes = [("data", &pool.data), ("meta", &pool.meta)];
+
+                for (kind, size) in sizes {
+                    if let Some(size) = size {
+                        let labels = [
+                            ("fs", fs.name.as_str()),
+                            ("pool", &pool.name),
+                            ("type", kind),
+                        ];
+
+                        total.sample(&labels, size.total_kb);
+                        free.sample(&labels, size.free_kb);
+                        total_bytes
+                            .sample(&labels, kb_to_bytes(size.total_kb));
+                        free_bytes.sample(&labels, kb_to_bytes(size.free_kb));
+                    }
                 }
             }
         }
 
-        Ok(())
+        vec![total, free, total_bytes, free_bytes]
     }
 }
 
@@ -348,6 +341,19 @@ mod tests {
         );
     }
 
+    #[test]
+    fn parse_truncated() {
+        let error = "system 0 8 MB no yes 0 0 ( 0%)"
+            .parse::<Pool>()
+            .unwrap_err();
+        assert!(matches!(error, Error::Syntax { line: 1, .. }));
+
+        assert!(matches!(
+            "".parse::<Pool>(),
+            Err(Error::Syntax { line: 1, .. })
+        ));
+    }
+
     #[test]
     fn prometheus() {
         let fs = Filesystem {
@@ -381,11 +387,18 @@ mod tests {
         };
 
         let mut output = vec![];
-        vec![fs].to_prom(&mut output).unwrap();
+        vec![fs.clone()].to_prom(&mut output).unwrap();
 
         let metrics = std::str::from_utf8(output.as_slice()).unwrap();
 
         let expected = include_str!("pool-example.prom");
         assert_eq!(metrics, expected);
+
+        let mut output = vec![];
+        vec![fs].to_openmetrics(&mut output).unwrap();
+
+        let metrics = std::str::from_utf8(output.as_slice()).unwrap();
+        assert!(metrics.contains("# UNIT gpfs_fs_pool_total_bytes bytes\n"));
+        assert!(!metrics.contains("# UNIT gpfs_fs_pool_total_kbytes"));
     }
 }
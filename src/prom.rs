@@ -1,13 +1,37 @@
 //! Prometheus metrics.
+//!
+//! Data is converted to [`Family`]s via [`ToMetrics`], which are then rendered
+//! to valid text exposition format by [`ToText`]: label values and help texts
+//! are escaped and every metric family gets exactly one `# HELP` and `# TYPE`
+//! line.
+//!
+//! ```
+//! use mmoxi::prom::{Family, render};
+//!
+//! let mut inodes = Family::gauge("gpfs_fileset_max_inodes", "max inodes");
+//! inodes.sample(&[("fs", "gpfs1"), ("fileset", "a \"b\"")], 1024_u64);
+//!
+//! let mut output = vec![];
+//! render(&[inodes], &mut output).unwrap();
+//! let output = String::from_utf8(output).unwrap();
+//!
+//! assert_eq!(
+//!     output.lines().last(),
+//!     Some(r#"gpfs_fileset_max_inodes{fs="gpfs1",fileset="a \"b\""} 1024"#),
+//! );
+//! ```
 
 use std::collections::HashMap;
+use std::fmt;
 use std::io::Write;
 use std::path::Path;
-
-use anyhow::{Result, anyhow};
+use std::str::FromStr;
+use std::time::SystemTime;
 
 use crate::nsd::FsPoolId;
+use crate::run::Runner;
 use crate::sysfs;
+use crate::{Error, Result};
 
 /// Convert `Data` to Prometheus metrics.
 pub trait ToText {
@@ -18,21 +42,563 @@ pub trait ToText {
     /// This function uses [`writeln`] to write to `output`. It can only fail
     /// if any of these [`writeln`] fails.
     fn to_prom(&self, output: &mut impl Write) -> Result<()>;
+
+    /// Converts the `Data` to the `OpenMetrics` text format.
+    ///
+    /// # Errors
+    ///
+    /// This function uses [`writeln`] to write to `output`. It can only fail
+    /// if any of these [`writeln`] fails.
+    fn to_openmetrics(&self, output: &mut impl Write) -> Result<()>;
+}
+
+/// Convert `Data` to metric families.
+pub trait ToMetrics {
+    /// Returns the metric families of `Data`.
+    fn to_metrics(&self) -> Vec<Family>;
+}
+
+impl<T: ToMetrics + ?Sized> ToText for T {
+    fn to_prom(&self, output: &mut impl Write) -> Result<()> {
+        render(&self.to_metrics(), output)
+    }
+
+    fn to_openmetrics(&self, output: &mut impl Write) -> Result<()> {
+        render_openmetrics(&self.to_metrics(), output)
+    }
+}
+
+/// Exposition format.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Default)]
+pub enum Format {
+    /// Prometheus' classic text-based format, see [`render`].
+    #[default]
+    Text,
+
+    /// `OpenMetrics` text format, see [`render_openmetrics`].
+    OpenMetrics,
+
+    /// `InfluxDB` line protocol, see [`crate::influx::render`].
+    Influx,
+}
+
+impl Format {
+    /// Writes `families` in this format.
+    ///
+    /// Line protocol points get the current time as timestamp.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if writing to `output` fails.
+    pub fn render(
+        self,
+        families: &[Family],
+        output: &mut impl Write,
+    ) -> Result<()> {
+        match self {
+            Self::Text => render(families, output),
+            Self::OpenMetrics => render_openmetrics(families, output),
+            Self::Influx => {
+                crate::influx::render(families, SystemTime::now(), output)
+            }
+        }
+    }
+
+    /// Returns the HTTP content type of this format.
+    #[must_use]
+    pub const fn content_type(self) -> &'static str {
+        match self {
+            Self::Text => "text/plain; version=0.0.4; charset=utf-8",
+            Self::OpenMetrics => {
+                "application/openmetrics-text; version=1.0.0; charset=utf-8"
+            }
+            Self::Influx => "text/plain; charset=utf-8",
+        }
+    }
+}
+
+impl FromStr for Format {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "text" => Ok(Self::Text),
+            "openmetrics" => Ok(Self::OpenMetrics),
+            "influx" => Ok(Self::Influx),
+            _ => Err(Error::InvalidValue {
+                kind: "format",
+                value: s.into(),
+            }),
+        }
+    }
+}
+
+/// Writes `families` in Prometheus' text-based format.
+///
+/// Families with the same name are merged, so their `# HELP` and `# TYPE`
+/// lines are only written once. Units are ignored.
+///
+/// # Errors
+///
+/// Returns an error if writing to `output` fails.
+pub fn render(families: &[Family], output: &mut impl Write) -> Result<()> {
+    for (family, samples) in groups(families) {
+        let name = &family.name;
+
+        writeln!(output, "# HELP {name} {}", escape_help(&family.help))?;
+        writeln!(output, "# TYPE {name} {}", family.kind)?;
+
+        for sample in samples {
+            write_sample(output, name, sample)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Writes `families` in the `OpenMetrics` text format.
+///
+/// Families with the same name are merged like with [`render`]. Counter
+/// samples get the `_total` suffix. The unit is only written if the family
+/// name ends with it, so metric names are the same in both formats. The
+/// output is terminated by `# EOF`.
+///
+/// # Errors
+///
+/// Returns an error if writing to `output` fails.
+pub fn render_openmetrics(
+    families: &[Family],
+    output: &mut impl Write,
+) -> Result<()> {
+    for (family, samples) in groups(families) {
+        let mut name = family.name.as_str();
+
+        if family.kind == Kind::Counter {
+            name = name.strip_suffix("_total").unwrap_or(name);
+        }
+
+        writeln!(output, "# TYPE {name} {}", family.kind)?;
+
+        if let Some(unit) = &family.unit {
+            if name.ends_with(&format!("_{unit}")) {
+                writeln!(output, "# UNIT {name} {unit}")?;
+            }
+        }
+
+        writeln!(output, "# HELP {name} {}", escape_label(&family.help))?;
+
+        let sample_name = match family.kind {
+            Kind::Counter => format!("{name}_total"),
+            Kind::Gauge => name.to_owned(),
+        };
+
+        for sample in samples {
+            write_sample(output, &sample_name, sample)?;
+        }
+    }
+
+    writeln!(output, "# EOF")?;
+
+    Ok(())
+}
+
+/// Returns the `le` label value of a bucket upper bound.
+///
+/// `OpenMetrics` requires canonical numbers for bounds, so the value always
+/// has a decimal point or an exponent, e.g. `1.0` instead of `1`.
+///
+/// ```
+/// assert_eq!(mmoxi::prom::le(1.0), "1.0");
+/// assert_eq!(mmoxi::prom::le(0.0005), "0.0005");
+/// assert_eq!(mmoxi::prom::le(f64::INFINITY), "+Inf");
+/// ```
+#[must_use]
+pub fn le(bound: f64) -> String {
+    if bound.is_finite() {
+        // unlike display, debug formatting never drops the decimal point
+        format!("{bound:?}")
+    } else {
+        Value::Float(bound).to_string()
+    }
+}
+
+/// Converts kilobytes, as most `mm*` commands report sizes, to bytes.
+pub(crate) const fn kb_to_bytes(kb: u64) -> u64 {
+    kb.saturating_mul(1024)
+}
+
+/// Returns the families with all samples of the same name merged.
+fn groups(families: &[Family]) -> Vec<(&Family, Vec<&Sample>)> {
+    let mut groups: Vec<(&Family, Vec<&Sample>)> = vec![];
+
+    for family in families {
+        let group = groups.iter_mut().find(|(f, _)| f.name == family.name);
+
+        if let Some((_, samples)) = group {
+            samples.extend(&family.samples);
+        } else {
+            groups.push((family, family.samples.iter().collect()));
+        }
+    }
+
+    groups
+}
+
+fn write_sample(
+    output: &mut impl Write,
+    name: &str,
+    sample: &Sample,
+) -> Result<()> {
+    write!(output, "{name}")?;
+
+    if !sample.labels.is_empty() {
+        write!(output, "{{")?;
+
+        for (index, (name, value)) in sample.labels.iter().enumerate() {
+            if index > 0 {
+                write!(output, ",")?;
+            }
+
+            write!(output, "{name}=\"{}\"", escape_label(value))?;
+        }
+
+        write!(output, "}}")?;
+    }
+
+    writeln!(output, " {}", sample.value)?;
+
+    Ok(())
+}
+
+// ----------------------------------------------------------------------------
+// metric families
+// ----------------------------------------------------------------------------
+
+/// A metric family, i.e. all samples of a metric.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Family {
+    name: String,
+    help: String,
+    kind: Kind,
+    unit: Option<String>,
+    samples: Vec<Sample>,
+}
+
+impl Family {
+    /// Returns an empty gauge family.
+    pub fn gauge(name: impl Into<String>, help: impl Into<String>) -> Self {
+        Self::new(name, help, Kind::Gauge)
+    }
+
+    /// Returns an empty counter family.
+    pub fn counter(name: impl Into<String>, help: impl Into<String>) -> Self {
+        Self::new(name, help, Kind::Counter)
+    }
+
+    fn new(
+        name: impl Into<String>,
+        help: impl Into<String>,
+        kind: Kind,
+    ) -> Self {
+        let name = name.into();
+
+        debug_assert!(is_valid_name(&name), "invalid metric name: {name}");
+
+        Self {
+            name,
+            help: help.into(),
+            kind,
+            unit: None,
+            samples: vec![],
+        }
+    }
+
+    /// Sets the unit, e.g. `bytes` or `seconds`.
+    ///
+    /// The unit is only part of the `OpenMetrics` format and only if the
+    /// family name ends with it.
+    #[must_use]
+    pub fn with_unit(mut self, unit: impl Into<String>) -> Self {
+        self.unit = Some(unit.into());
+        self
+    }
+
+    /// Adds a sample.
+    ///
+    /// Label values are escaped when rendering, label names are not.
+    pub fn sample(
+        &mut self,
+        labels: &[(&'static str, &str)],
+        value: impl Into<Value>,
+    ) -> &mut Self {
+        let labels = labels
+            .iter()
+            .map(|(name, value)| {
+                debug_assert!(is_valid_name(name), "invalid label: {name}");
+                (*name, (*value).to_owned())
+            })
+            .collect();
+
+        self.samples.push(Sample {
+            labels,
+            value: value.into(),
+        });
+
+        self
+    }
+
+    /// Returns the metric name.
+    #[must_use]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the help text.
+    #[must_use]
+    pub fn help(&self) -> &str {
+        &self.help
+    }
+
+    /// Returns the metric type.
+    #[must_use]
+    pub const fn kind(&self) -> Kind {
+        self.kind
+    }
+
+    /// Returns the unit.
+    #[must_use]
+    pub fn unit(&self) -> Option<&str> {
+        self.unit.as_deref()
+    }
+
+    /// Returns the samples.
+    #[must_use]
+    pub fn samples(&self) -> &[Sample] {
+        &self.samples
+    }
+}
+
+/// Metric type.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub enum Kind {
+    /// A value that can go up and down.
+    Gauge,
+
+    /// A value that only ever goes up, except for resets.
+    Counter,
+}
+
+impl fmt::Display for Kind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Gauge => write!(f, "gauge"),
+            Self::Counter => write!(f, "counter"),
+        }
+    }
+}
+
+/// A single sample of a metric family.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Sample {
+    labels: Vec<(&'static str, String)>,
+    value: Value,
+}
+
+impl Sample {
+    /// Returns the label names and unescaped values.
+    #[must_use]
+    pub fn labels(&self) -> &[(&'static str, String)] {
+        &self.labels
+    }
+
+    /// Returns the value.
+    #[must_use]
+    pub const fn value(&self) -> Value {
+        self.value
+    }
+}
+
+/// Sample value.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Value {
+    /// Signed integer.
+    Int(i64),
+
+    /// Unsigned integer.
+    Uint(u64),
+
+    /// Floating point number.
+    Float(f64),
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Int(value) => write!(f, "{value}"),
+            Self::Uint(value) => write!(f, "{value}"),
+            Self::Float(value) if value.is_nan() => write!(f, "NaN"),
+            Self::Float(value) if value.is_infinite() => {
+                let sign = if value.is_sign_positive() { '+' } else { '-' };
+                write!(f, "{sign}Inf")
+            }
+            Self::Float(value) => write!(f, "{value}"),
+        }
+    }
 }
 
+impl From<bool> for Value {
+    fn from(value: bool) -> Self {
+        Self::Uint(u64::from(value))
+    }
+}
+
+impl From<i32> for Value {
+    fn from(value: i32) -> Self {
+        Self::Int(i64::from(value))
+    }
+}
+
+impl From<i64> for Value {
+    fn from(value: i64) -> Self {
+        Self::Int(value)
+    }
+}
+
+impl From<u32> for Value {
+    fn from(value: u32) -> Self {
+        Self::Uint(u64::from(value))
+    }
+}
+
+impl From<u64> for Value {
+    fn from(value: u64) -> Self {
+        Self::Uint(value)
+    }
+}
+
+impl From<usize> for Value {
+    fn from(value: usize) -> Self {
+        Self::Uint(value as u64)
+    }
+}
+
+impl From<f64> for Value {
+    fn from(value: f64) -> Self {
+        Self::Float(value)
+    }
+}
+
+/// Histogram with fixed bucket upper bounds.
+///
+/// Since it is a snapshot rather than cumulative over time, it is not a
+/// metric type of its own, see [`IoLatency`](crate::diag::IoLatency) for how
+/// its buckets are exported.
+///
+/// ```
+/// use mmoxi::prom::Histogram;
+///
+/// let mut histogram = Histogram::new([0.1, 1.0]);
+/// histogram.observe(0.05);
+/// histogram.observe(0.5);
+/// histogram.observe(5.0);
+///
+/// let buckets = histogram.buckets().collect::<Vec<_>>();
+/// assert_eq!(buckets, [(0.1, 1), (1.0, 2), (f64::INFINITY, 3)]);
+/// assert_eq!(histogram.count(), 3);
+/// ```
+#[derive(Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Histogram {
+    bounds: Vec<f64>,
+    counts: Vec<u64>,
+    sum: f64,
+}
+
+impl Histogram {
+    /// Returns an empty histogram with the given bucket upper bounds, which
+    /// must be sorted. The `+Inf` bucket is added implicitly.
+    pub fn new(bounds: impl Into<Vec<f64>>) -> Self {
+        let bounds = bounds.into();
+
+        debug_assert!(bounds.is_sorted(), "unsorted bounds: {bounds:?}");
+
+        Self {
+            counts: vec![0; bounds.len() + 1],
+            bounds,
+            sum: 0.0,
+        }
+    }
+
+    /// Adds an observation.
+    pub fn observe(&mut self, value: f64) {
+        let index = self.bounds.partition_point(|bound| *bound < value);
+
+        self.counts[index] += 1;
+        self.sum += value;
+    }
+
+    /// Returns the upper bounds with the cumulative counts, including the
+    /// `+Inf` bucket.
+    pub fn buckets(&self) -> impl Iterator<Item = (f64, u64)> + '_ {
+        self.bounds.iter().copied().chain([f64::INFINITY]).zip(
+            self.counts.iter().scan(0, |total, count| {
+                *total += count;
+                Some(*total)
+            }),
+        )
+    }
+
+    /// Returns the sum of all observations.
+    #[must_use]
+    pub const fn sum(&self) -> f64 {
+        self.sum
+    }
+
+    /// Returns the number of observations.
+    #[must_use]
+    pub fn count(&self) -> u64 {
+        self.counts.iter().sum()
+    }
+}
+
+fn is_valid_name(name: &str) -> bool {
+    let mut chars = name.chars();
+
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_' || c == ':')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == ':')
+}
+
+fn escape_help(help: &str) -> String {
+    help.replace('\\', "\\\\").replace('\n', "\\n")
+}
+
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+// ----------------------------------------------------------------------------
+// pool block device metrics
+// ----------------------------------------------------------------------------
+
 /// Returns block device metrics grouped by pool.
 ///
 /// # Errors
 ///
 /// Returns an error if fetching the NSDs or block device statistics fails.
 pub fn pool_block_device_metrics<Cache>(
+    runner: &dyn Runner,
     device_cache: Cache,
     force: bool,
 ) -> Result<PoolBlockDeviceMetrics>
 where
     Cache: AsRef<Path>,
 {
-    let pooled_nsds = crate::nsd::local_pooled(device_cache, force)?;
+    let pooled_nsds = crate::nsd::local_pooled(runner, device_cache, force)?;
 
     let stats = sysfs::block::stat_all()?;
 
@@ -45,7 +611,10 @@ where
             let device_name = nsd.device_name()?;
 
             let stat = stats.get(device_name.as_ref()).ok_or_else(|| {
-                anyhow!("no block device stat for device: {}", nsd.device())
+                Error::NotFound(format!(
+                    "block device stat for device {}",
+                    nsd.device()
+                ))
             })?;
 
             pool_stat_sum += *stat;
@@ -63,69 +632,132 @@ pub struct PoolBlockDeviceMetrics {
     inner: HashMap<FsPoolId, sysfs::block::Stat>,
 }
 
-impl ToText for PoolBlockDeviceMetrics {
-    fn to_prom(&self, output: &mut impl Write) -> Result<()> {
-        writeln!(
-            output,
-            "# HELP gpfs_pool_read_ios GPFS pool processed read I/Os"
-        )?;
-        writeln!(output, "# TYPE gpfs_pool_read_ios counter")?;
+impl ToMetrics for PoolBlockDeviceMetrics {
+    fn to_metrics(&self) -> Vec<Family> {
+        let mut read_ios = Family::counter(
+            "gpfs_pool_read_ios",
+            "GPFS pool processed read I/Os",
+        );
+        let mut read_bytes =
+            Family::counter("gpfs_pool_read_bytes", "GPFS pool read bytes")
+                .with_unit("bytes");
+        let mut write_ios = Family::counter(
+            "gpfs_pool_write_ios",
+            "GPFS pool processed write I/Os",
+        );
+        let mut write_bytes = Family::counter(
+            "gpfs_pool_write_bytes",
+            "GPFS pool written bytes",
+        )
+        .with_unit("bytes");
 
         for (id, stat) in &self.inner {
-            writeln!(
-                output,
-                "gpfs_pool_read_ios{{fs=\"{}\",pool=\"{}\"}} {}",
-                id.fs(),
-                id.pool(),
-                stat.read_ios
-            )?;
+            let labels = [("fs", id.fs()), ("pool", id.pool())];
+
+            read_ios.sample(&labels, stat.read_ios);
+            read_bytes.sample(&labels, stat.read_bytes());
+            write_ios.sample(&labels, stat.write_ios);
+            write_bytes.sample(&labels, stat.write_bytes());
         }
 
-        writeln!(output, "# HELP gpfs_pool_read_bytes GPFS pool read bytes")?;
-        writeln!(output, "# TYPE gpfs_pool_read_bytes counter")?;
+        vec![read_ios, read_bytes, write_ios, write_bytes]
+    }
+}
 
-        for (id, stat) in &self.inner {
-            writeln!(
-                output,
-                "gpfs_pool_read_bytes{{fs=\"{}\",pool=\"{}\"}} {}",
-                id.fs(),
-                id.pool(),
-                stat.read_bytes()
-            )?;
-        }
+// ----------------------------------------------------------------------------
+// tests
+// ----------------------------------------------------------------------------
 
-        writeln!(
-            output,
-            "# HELP gpfs_pool_write_ios GPFS pool processed write I/Os"
-        )?;
-        writeln!(output, "# TYPE gpfs_pool_write_ios counter")?;
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-        for (id, stat) in &self.inner {
-            writeln!(
-                output,
-                "gpfs_pool_write_ios{{fs=\"{}\",pool=\"{}\"}} {}",
-                id.fs(),
-                id.pool(),
-                stat.write_ios
-            )?;
-        }
+    #[test]
+    fn escaping() {
+        let mut family = Family::gauge("test", "back\\slash\nnewline");
+        family.sample(&[("name", "a\"b\\c\nd")], 1_u64);
 
-        writeln!(
-            output,
-            "# HELP gpfs_pool_write_bytes GPFS pool written bytes"
-        )?;
-        writeln!(output, "# TYPE gpfs_pool_write_bytes counter")?;
+        let mut output = vec![];
+        render(&[family], &mut output).unwrap();
 
-        for (id, stat) in &self.inner {
-            writeln!(
-                output,
-                "gpfs_pool_write_bytes{{fs=\"{}\",pool=\"{}\"}} {}",
-                id.fs(),
-                id.pool(),
-                stat.write_bytes()
-            )?;
-        }
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "\
+# HELP test back\\\\slash\\nnewline
+# TYPE test gauge
+test{name=\"a\\\"b\\\\c\\nd\"} 1
+"
+        );
+    }
+
+    #[test]
+    fn grouping() {
+        let mut gpfs1 = Family::gauge("test", "help");
+        gpfs1.sample(&[("fs", "gpfs1")], 1_u64);
+
+        let mut other = Family::counter("other_total", "help");
+        other.sample(&[], 2_u64);
+
+        let mut gpfs2 = Family::gauge("test", "help");
+        gpfs2.sample(&[("fs", "gpfs2")], -3_i64);
+
+        let mut output = vec![];
+        render(&[gpfs1, other, gpfs2], &mut output).unwrap();
+
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "\
+# HELP test help
+# TYPE test gauge
+test{fs=\"gpfs1\"} 1
+test{fs=\"gpfs2\"} -3
+# HELP other_total help
+# TYPE other_total counter
+other_total 2
+"
+        );
+    }
+
+    #[test]
+    fn openmetrics() {
+        let mut ios = Family::counter("test_ios", "I/Os");
+        ios.sample(&[("pool", "nvme")], 1_u64);
+
+        let mut bytes =
+            Family::counter("test_bytes_total", "bytes").with_unit("bytes");
+        bytes.sample(&[("pool", "nvme")], 2_u64);
+
+        let mut size = Family::gauge("test_size", "size").with_unit("kbytes");
+        size.sample(&[], 3_u64);
+
+        let mut output = vec![];
+        render_openmetrics(&[ios, bytes, size], &mut output).unwrap();
+
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "\
+# TYPE test_ios counter
+# HELP test_ios I/Os
+test_ios_total{pool=\"nvme\"} 1
+# TYPE test_bytes counter
+# UNIT test_bytes bytes
+# HELP test_bytes bytes
+test_bytes_total{pool=\"nvme\"} 2
+# TYPE test_size gauge
+# HELP test_size size
+test_size 3
+# EOF
+"
+        );
+    }
 
-        Ok(())
+    #[test]
+    fn values() {
+        assert_eq!(Value::from(true).to_string(), "1");
+        assert_eq!(Value::from(-1_i32).to_string(), "-1");
+        assert_eq!(Value::from(0.5).to_string(), "0.5");
+        assert_eq!(Value::from(f64::NAN).to_string(), "NaN");
+        assert_eq!(Value::from(f64::INFINITY).to_string(), "+Inf");
+        assert_eq!(Value::from(f64::NEG_INFINITY).to_string(), "-Inf");
     }
 }
@@ -18,15 +18,16 @@
 //! ```
 
 use std::fmt;
-use std::io::{BufRead, Write};
+use std::io::BufRead;
 use std::str::FromStr;
 
-use anyhow::{Context, Result, anyhow};
-
-use crate::prom::ToText;
+use crate::parse::{FromRecord, Record};
+use crate::prom::{Family, ToMetrics, kb_to_bytes};
+use crate::{Error, Result};
 
 /// Parsed quota entries.
 #[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Data {
     entries: Vec<Entry>,
 }
@@ -38,28 +39,8 @@ impl Data {
     ///
     /// Reading from input or parsing it.
     pub fn from_reader<Input: BufRead>(input: Input) -> Result<Self> {
-        let mut index = Index::default();
-        let mut data = Self::default();
-
-        for line in input.lines() {
-            let line = line?;
-
-            if line.starts_with("***") {
-                continue;
-            }
-
-            let tokens = line.split(':').collect::<Vec<_>>();
-
-            if tokens[2] == "HEADER" {
-                index = Index::default();
-                header_to_index(&tokens, &mut index);
-            } else {
-                let entry = Entry::from_tokens(&tokens, &index)?;
-                data.entries.push(entry);
-            }
-        }
-
-        Ok(data)
+        let entries = crate::parse::from_reader(input)?;
+        Ok(Self { entries })
     }
 
     /// Returns the entries.
@@ -69,28 +50,89 @@ impl Data {
     }
 }
 
-impl ToText for Data {
-    fn to_prom(&self, output: &mut impl Write) -> Result<()> {
+impl ToMetrics for Data {
+    fn to_metrics(&self) -> Vec<Family> {
         if self.entries.is_empty() {
-            return Ok(());
+            return vec![];
         }
 
-        prom_block_usage(&self.entries, output)?;
-        prom_block_quota(&self.entries, output)?;
-        prom_block_limit(&self.entries, output)?;
-        prom_block_in_doubt(&self.entries, output)?;
+        let gauge = |name: &str, help: &str| {
+            Family::gauge(
+                format!("gpfs_quota_{name}_kbytes"),
+                format!("GPFS quota {help} in kilobytes."),
+            )
+        };
 
-        prom_files_usage(&self.entries, output)?;
-        prom_files_quota(&self.entries, output)?;
-        prom_files_limit(&self.entries, output)?;
-        prom_files_in_doubt(&self.entries, output)?;
+        let bytes = |name: &str, help: &str| {
+            Family::gauge(
+                format!("gpfs_quota_{name}_bytes"),
+                format!("GPFS quota {help} in bytes."),
+            )
+            .with_unit("bytes")
+        };
+
+        let mut block_usage = gauge("block_usage", "block usage");
+        let mut block_quota = gauge("block_quota", "block quota");
+        let mut block_limit = gauge("block_limit", "block limit");
+        let mut block_in_doubt = gauge("block_in_doubt", "block in doubt");
+        let mut files_usage = gauge("files_usage", "block usage");
+        let mut files_quota = gauge("files_quota", "block quota");
+        let mut files_limit = gauge("files_limit", "block limit");
+        let mut files_in_doubt = gauge("files_in_doubt", "block in doubt");
+        let mut block_usage_bytes = bytes("block_usage", "block usage");
+        let mut block_quota_bytes = bytes("block_quota", "block quota");
+        let mut block_limit_bytes = bytes("block_limit", "block limit");
+        let mut block_in_doubt_bytes =
+            bytes("block_in_doubt", "block in doubt");
+
+        for entry in &self.entries {
+            let quota_type = entry.quota_type.to_string();
+            let id = entry.id.to_string();
+
+            let labels = [
+                ("fs", entry.fs_name.as_str()),
+                ("type", &quota_type),
+                ("id", &id),
+                ("name", &entry.name),
+                ("fileset", &entry.fileset_name),
+            ];
+
+            block_usage.sample(&labels, entry.block.usage);
+            block_quota.sample(&labels, entry.block.quota);
+            block_limit.sample(&labels, entry.block.limit);
+            block_in_doubt.sample(&labels, entry.block.in_doubt);
+            files_usage.sample(&labels, entry.files.usage);
+            files_quota.sample(&labels, entry.files.quota);
+            files_limit.sample(&labels, entry.files.limit);
+            files_in_doubt.sample(&labels, entry.files.in_doubt);
+            block_usage_bytes
+                .sample(&labels, entry.block.usage.saturating_mul(1024));
+            block_quota_bytes.sample(&labels, kb_to_bytes(entry.block.quota));
+            block_limit_bytes.sample(&labels, kb_to_bytes(entry.block.limit));
+            block_in_doubt_bytes
+                .sample(&labels, kb_to_bytes(entry.block.in_doubt));
+        }
 
-        Ok(())
+        vec![
+            block_usage,
+            block_quota,
+            block_limit,
+            block_in_doubt,
+            files_usage,
+            files_quota,
+            files_limit,
+            files_in_doubt,
+            block_usage_bytes,
+            block_quota_bytes,
+            block_limit_bytes,
+            block_in_doubt_bytes,
+        ]
     }
 }
 
 /// Parsed quota entry.
 #[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Entry {
     fs_name: String,
     quota_type: Type,
@@ -150,6 +192,7 @@ impl Entry {
 #[derive(
     Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Default,
 )]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Metrics {
     usage: i64,
     quota: u64,
@@ -185,6 +228,8 @@ impl Metrics {
 
 /// Quota type.
 #[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
 pub enum Type {
     /// Fileset quota.
     Fileset,
@@ -197,14 +242,17 @@ pub enum Type {
 }
 
 impl FromStr for Type {
-    type Err = anyhow::Error;
+    type Err = Error;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s {
             "FILESET" => Ok(Self::Fileset),
             "GRP" => Ok(Self::Group),
             "USR" => Ok(Self::User),
-            unknown => Err(anyhow!("unknown quota type: {unknown}")),
+            unknown => Err(Error::InvalidValue {
+                kind: "quota type",
+                value: unknown.into(),
+            }),
         }
     }
 }
@@ -225,348 +273,41 @@ impl fmt::Display for Type {
 // boiler-platy parsing
 // ----------------------------------------------------------------------------
 
-impl Entry {
-    fn from_tokens(tokens: &[&str], index: &Index) -> Result<Self> {
-        let fs_name_index = index
-            .fs_name
-            .ok_or_else(|| anyhow!("no filesystem name index"))?;
-        let fs_name = tokens[fs_name_index].into();
-
-        let quota_type_index = index
-            .quota_type
-            .ok_or_else(|| anyhow!("no quota type index"))?;
-        let quota_type = tokens[quota_type_index].parse::<Type>()?;
-
-        let id_index = index.id.ok_or_else(|| anyhow!("no id index"))?;
-        let id = tokens[id_index];
-        let id = id.parse().with_context(|| format!("invalid id: {id}"))?;
-
-        let name_index = index.name.ok_or_else(|| anyhow!("no name index"))?;
-        let name = tokens[name_index].into();
-
-        let block_usage_index = index
-            .block_usage
-            .ok_or_else(|| anyhow!("no block usage index"))?;
-        let block_usage = tokens[block_usage_index];
-        let block_usage = block_usage
-            .parse()
-            .with_context(|| format!("invalid block usage: {block_usage}"))?;
-
-        let block_quota_index = index
-            .block_quota
-            .ok_or_else(|| anyhow!("no block quota index"))?;
-        let block_quota = tokens[block_quota_index];
-        let block_quota = block_quota
-            .parse()
-            .with_context(|| format!("invalid block quota: {block_quota}"))?;
-
-        let block_limit_index = index
-            .block_limit
-            .ok_or_else(|| anyhow!("no block limit index"))?;
-        let block_limit = tokens[block_limit_index];
-        let block_limit = block_limit
-            .parse()
-            .with_context(|| format!("invalid block limit: {block_limit}"))?;
-
-        let block_in_doubt_index = index
-            .block_in_doubt
-            .ok_or_else(|| anyhow!("no block in doubt index"))?;
-        let block_in_doubt = tokens[block_in_doubt_index];
-        let block_in_doubt = block_in_doubt.parse().with_context(|| {
-            format!("invalid block in doubt: {block_in_doubt}")
-        })?;
-
-        let files_usage_index = index
-            .files_usage
-            .ok_or_else(|| anyhow!("no files usage index"))?;
-        let files_usage = tokens[files_usage_index];
-        let files_usage = files_usage
-            .parse()
-            .with_context(|| format!("invalid files usage: {files_usage}"))?;
-
-        let files_quota_index = index
-            .files_quota
-            .ok_or_else(|| anyhow!("no files quota index"))?;
-        let files_quota = tokens[files_quota_index];
-        let files_quota = files_quota
-            .parse()
-            .with_context(|| format!("invalid files quota: {files_quota}"))?;
-
-        let files_limit_index = index
-            .files_limit
-            .ok_or_else(|| anyhow!("no files limit index"))?;
-        let files_limit = tokens[files_limit_index];
-        let files_limit = files_limit
-            .parse()
-            .with_context(|| format!("invalid files limit: {files_limit}"))?;
-
-        let files_in_doubt_index = index
-            .files_in_doubt
-            .ok_or_else(|| anyhow!("no files in doubt index"))?;
-        let files_in_doubt = tokens[files_in_doubt_index];
-        let files_in_doubt = files_in_doubt.parse().with_context(|| {
-            format!("invalid files in doubt: {files_in_doubt}")
-        })?;
-
-        let fileset_name_index = index
-            .fileset_name
-            .ok_or_else(|| anyhow!("no fileset name index"))?;
-        let fileset_name = tokens[fileset_name_index].into();
+impl FromRecord for Entry {
+    fn from_record(record: &Record) -> Result<Self> {
+        let fs_name = record.get("filesystemName")?.into();
+        let quota_type = record.parse("quotaType")?;
+        let id = record.parse("id")?;
+        let name = record.get("name")?.into();
+
+        let block = Metrics {
+            usage: record.parse("blockUsage")?,
+            quota: record.parse("blockQuota")?,
+            limit: record.parse("blockLimit")?,
+            in_doubt: record.parse("blockInDoubt")?,
+        };
+
+        let files = Metrics {
+            usage: record.parse("filesUsage")?,
+            quota: record.parse("filesQuota")?,
+            limit: record.parse("filesLimit")?,
+            in_doubt: record.parse("filesInDoubt")?,
+        };
+
+        let fileset_name = record.get("filesetname")?.into();
 
         Ok(Self {
             fs_name,
             quota_type,
             id,
             name,
-            block: Metrics {
-                usage: block_usage,
-                quota: block_quota,
-                limit: block_limit,
-                in_doubt: block_in_doubt,
-            },
-            files: Metrics {
-                usage: files_usage,
-                quota: files_quota,
-                limit: files_limit,
-                in_doubt: files_in_doubt,
-            },
+            block,
+            files,
             fileset_name,
         })
     }
 }
 
-#[derive(Debug, Default)]
-struct Index {
-    fs_name: Option<usize>,
-    quota_type: Option<usize>,
-    id: Option<usize>,
-    name: Option<usize>,
-    block_usage: Option<usize>,
-    block_quota: Option<usize>,
-    block_limit: Option<usize>,
-    block_in_doubt: Option<usize>,
-    files_usage: Option<usize>,
-    files_quota: Option<usize>,
-    files_limit: Option<usize>,
-    files_in_doubt: Option<usize>,
-    fileset_name: Option<usize>,
-}
-
-fn header_to_index(tokens: &[&str], index: &mut Index) {
-    for (i, token) in tokens.iter().enumerate() {
-        match *token {
-            "filesystemName" => index.fs_name = Some(i),
-            "quotaType" => index.quota_type = Some(i),
-            "id" => index.id = Some(i),
-            "name" => index.name = Some(i),
-            "blockUsage" => index.block_usage = Some(i),
-            "blockQuota" => index.block_quota = Some(i),
-            "blockLimit" => index.block_limit = Some(i),
-            "blockInDoubt" => index.block_in_doubt = Some(i),
-            "filesUsage" => index.files_usage = Some(i),
-            "filesQuota" => index.files_quota = Some(i),
-            "filesLimit" => index.files_limit = Some(i),
-            "filesInDoubt" => index.files_in_doubt = Some(i),
-            "filesetname" => index.fileset_name = Some(i),
-            _ => {}
-        }
-    }
-}
-
-// ----------------------------------------------------------------------------
-// boiler-platy prometheus output
-// ----------------------------------------------------------------------------
-
-fn prom_block_usage<O: Write>(data: &[Entry], output: &mut O) -> Result<()> {
-    writeln!(
-        output,
-        "# HELP gpfs_quota_block_usage_kbytes GPFS quota block usage in kilobytes."
-    )?;
-    writeln!(output, "# TYPE gpfs_quota_block_usage_kbytes gauge")?;
-
-    for data in data {
-        writeln!(
-            output,
-            "gpfs_quota_block_usage_kbytes{{fs=\"{}\",type=\"{}\",id=\"{}\",name=\"{}\",fileset=\"{}\"}} {}",
-            data.fs_name,
-            data.quota_type,
-            data.id,
-            data.name,
-            data.fileset_name,
-            data.block.usage,
-        )?;
-    }
-
-    Ok(())
-}
-
-fn prom_block_quota<O: Write>(data: &[Entry], output: &mut O) -> Result<()> {
-    writeln!(
-        output,
-        "# HELP gpfs_quota_block_quota_kbytes GPFS quota block quota in kilobytes."
-    )?;
-    writeln!(output, "# TYPE gpfs_quota_block_quota_kbytes gauge")?;
-
-    for data in data {
-        writeln!(
-            output,
-            "gpfs_quota_block_quota_kbytes{{fs=\"{}\",type=\"{}\",id=\"{}\",name=\"{}\",fileset=\"{}\"}} {}",
-            data.fs_name,
-            data.quota_type,
-            data.id,
-            data.name,
-            data.fileset_name,
-            data.block.quota,
-        )?;
-    }
-
-    Ok(())
-}
-
-fn prom_block_limit<O: Write>(data: &[Entry], output: &mut O) -> Result<()> {
-    writeln!(
-        output,
-        "# HELP gpfs_quota_block_limit_kbytes GPFS quota block limit in kilobytes."
-    )?;
-    writeln!(output, "# TYPE gpfs_quota_block_limit_kbytes gauge")?;
-
-    for data in data {
-        writeln!(
-            output,
-            "gpfs_quota_block_limit_kbytes{{fs=\"{}\",type=\"{}\",id=\"{}\",name=\"{}\",fileset=\"{}\"}} {}",
-            data.fs_name,
-            data.quota_type,
-            data.id,
-            data.name,
-            data.fileset_name,
-            data.block.limit,
-        )?;
-    }
-
-    Ok(())
-}
-
-fn prom_block_in_doubt<O: Write>(
-    data: &[Entry],
-    output: &mut O,
-) -> Result<()> {
-    writeln!(
-        output,
-        "# HELP gpfs_quota_block_in_doubt_kbytes GPFS quota block in doubt in kilobytes."
-    )?;
-    writeln!(output, "# TYPE gpfs_quota_block_in_doubt_kbytes gauge")?;
-
-    for data in data {
-        writeln!(
-            output,
-            "gpfs_quota_block_in_doubt_kbytes{{fs=\"{}\",type=\"{}\",id=\"{}\",name=\"{}\",fileset=\"{}\"}} {}",
-            data.fs_name,
-            data.quota_type,
-            data.id,
-            data.name,
-            data.fileset_name,
-            data.block.in_doubt,
-        )?;
-    }
-
-    Ok(())
-}
-
-fn prom_files_usage<O: Write>(data: &[Entry], output: &mut O) -> Result<()> {
-    writeln!(
-        output,
-        "# HELP gpfs_quota_files_usage_kbytes GPFS quota block usage in kilobytes."
-    )?;
-    writeln!(output, "# TYPE gpfs_quota_files_usage_kbytes gauge")?;
-
-    for data in data {
-        writeln!(
-            output,
-            "gpfs_quota_files_usage_kbytes{{fs=\"{}\",type=\"{}\",id=\"{}\",name=\"{}\",fileset=\"{}\"}} {}",
-            data.fs_name,
-            data.quota_type,
-            data.id,
-            data.name,
-            data.fileset_name,
-            data.files.usage,
-        )?;
-    }
-
-    Ok(())
-}
-
-fn prom_files_quota<O: Write>(data: &[Entry], output: &mut O) -> Result<()> {
-    writeln!(
-        output,
-        "# HELP gpfs_quota_files_quota_kbytes GPFS quota block quota in kilobytes."
-    )?;
-    writeln!(output, "# TYPE gpfs_quota_files_quota_kbytes gauge")?;
-
-    for data in data {
-        writeln!(
-            output,
-            "gpfs_quota_files_quota_kbytes{{fs=\"{}\",type=\"{}\",id=\"{}\",name=\"{}\",fileset=\"{}\"}} {}",
-            data.fs_name,
-            data.quota_type,
-            data.id,
-            data.name,
-            data.fileset_name,
-            data.files.quota,
-        )?;
-    }
-
-    Ok(())
-}
-
-fn prom_files_limit<O: Write>(data: &[Entry], output: &mut O) -> Result<()> {
-    writeln!(
-        output,
-        "# HELP gpfs_quota_files_limit_kbytes GPFS quota block limit in kilobytes."
-    )?;
-    writeln!(output, "# TYPE gpfs_quota_files_limit_kbytes gauge")?;
-
-    for data in data {
-        writeln!(
-            output,
-            "gpfs_quota_files_limit_kbytes{{fs=\"{}\",type=\"{}\",id=\"{}\",name=\"{}\",fileset=\"{}\"}} {}",
-            data.fs_name,
-            data.quota_type,
-            data.id,
-            data.name,
-            data.fileset_name,
-            data.files.limit,
-        )?;
-    }
-
-    Ok(())
-}
-
-fn prom_files_in_doubt<O: Write>(
-    data: &[Entry],
-    output: &mut O,
-) -> Result<()> {
-    writeln!(
-        output,
-        "# HELP gpfs_quota_files_in_doubt_kbytes GPFS quota block in doubt in kilobytes."
-    )?;
-    writeln!(output, "# TYPE gpfs_quota_files_in_doubt_kbytes gauge")?;
-
-    for data in data {
-        writeln!(
-            output,
-            "gpfs_quota_files_in_doubt_kbytes{{fs=\"{}\",type=\"{}\",id=\"{}\",name=\"{}\",fileset=\"{}\"}} {}",
-            data.fs_name,
-            data.quota_type,
-            data.id,
-            data.name,
-            data.fileset_name,
-            data.files.in_doubt,
-        )?;
-    }
-
-    Ok(())
-}
-
 // ----------------------------------------------------------------------------
 // tests
 // ----------------------------------------------------------------------------
@@ -574,6 +315,7 @@ fn prom_files_in_doubt<O: Write>(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::prom::ToText;
 
     #[test]
     fn parse() {
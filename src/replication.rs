@@ -0,0 +1,316 @@
+//! Failure group and replication analysis.
+//!
+//! Storage Scale places the replicas of a block in different failure groups.
+//! With the default number of replicas from `mmlsfs` and the failure groups of
+//! the disks from `mmlsdisk`, this module figures out per pool how many more
+//! failure groups can be lost before data or metadata becomes unavailable.
+//!
+//! The analysis is conservative: blocks are assumed to be spread across all
+//! combinations of failure groups, so a failure group with any disk that is
+//! not up counts as lost.
+
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+
+use crate::Result;
+use crate::disk::{Availability, Disks, Status};
+use crate::fs::Replication;
+use crate::prom::{Family, ToMetrics};
+use crate::run::Runner;
+
+/// Runs `mmlsdisk` and `mmlsfs` and analyzes the failure groups of all pools
+/// of a file system.
+///
+/// # Errors
+///
+/// Returns an error if running `mmlsdisk` or `mmlsfs` fails or if parsing
+/// their output fails.
+pub fn run(runner: &dyn Runner, fs_name: &str) -> Result<Vec<Pool>> {
+    let disks = crate::disk::disks(runner, fs_name)?;
+    let replication = crate::fs::replication(runner, fs_name)?;
+
+    Ok(analyze(fs_name, &disks, replication))
+}
+
+/// Analyzes the failure groups of all pools of a file system.
+#[must_use]
+pub fn analyze(
+    fs_name: &str,
+    disks: &Disks,
+    replication: Replication,
+) -> Vec<Pool> {
+    let mut pools: BTreeMap<&str, (Groups, Groups)> = BTreeMap::new();
+
+    // emptied disks don't hold any data
+    for disk in disks
+        .iter()
+        .filter(|disk| disk.status() != &Status::Emptied)
+    {
+        let (data, meta) = pools.entry(disk.pool()).or_default();
+        let up = disk.availability() == &Availability::Up;
+
+        if disk.is_objectdata() {
+            data.add(disk.failure_group(), up);
+        }
+
+        if disk.is_metadata() {
+            meta.add(disk.failure_group(), up);
+        }
+    }
+
+    pools
+        .into_iter()
+        .map(|(name, (data, meta))| Pool {
+            fs_name: fs_name.into(),
+            name: name.into(),
+            data: data.into_redundancy(replication.default_data()),
+            meta: meta.into_redundancy(replication.default_metadata()),
+        })
+        .collect()
+}
+
+#[derive(Default)]
+struct Groups {
+    all: BTreeSet<String>,
+    degraded: BTreeSet<String>,
+}
+
+impl Groups {
+    fn add(&mut self, failure_group: &str, up: bool) {
+        self.all.insert(failure_group.into());
+
+        if !up {
+            self.degraded.insert(failure_group.into());
+        }
+    }
+
+    fn into_redundancy(self, replicas: u64) -> Option<Redundancy> {
+        if self.all.is_empty() {
+            None
+        } else {
+            Some(Redundancy {
+                replicas,
+                failure_groups: self.all.into_iter().collect(),
+                degraded: self.degraded.into_iter().collect(),
+            })
+        }
+    }
+}
+
+/// Failure group analysis of a storage pool.
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Pool {
+    fs_name: String,
+    name: String,
+    data: Option<Redundancy>,
+    meta: Option<Redundancy>,
+}
+
+impl Pool {
+    /// Returns the file system name.
+    #[must_use]
+    pub fn fs_name(&self) -> &str {
+        &self.fs_name
+    }
+
+    /// Returns the pool name.
+    #[must_use]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the object data redundancy, if the pool holds data.
+    #[must_use]
+    pub const fn data(&self) -> Option<&Redundancy> {
+        self.data.as_ref()
+    }
+
+    /// Returns the metadata redundancy, if the pool holds metadata.
+    #[must_use]
+    pub const fn meta(&self) -> Option<&Redundancy> {
+        self.meta.as_ref()
+    }
+}
+
+/// Failure group redundancy of either data or metadata of a pool.
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Redundancy {
+    replicas: u64,
+    failure_groups: Vec<String>,
+    degraded: Vec<String>,
+}
+
+impl Redundancy {
+    /// Returns the default number of replicas.
+    #[must_use]
+    pub const fn replicas(&self) -> u64 {
+        self.replicas
+    }
+
+    /// Returns the failure groups with disks.
+    #[must_use]
+    pub fn failure_groups(&self) -> &[String] {
+        &self.failure_groups
+    }
+
+    /// Returns the failure groups with disks that are not up.
+    #[must_use]
+    pub fn degraded(&self) -> &[String] {
+        &self.degraded
+    }
+
+    /// Returns how many more failure groups can be lost before some blocks
+    /// become unavailable.
+    ///
+    /// Replicas can't be placed in more failure groups than there are, so
+    /// with fewer failure groups than replicas, the failure groups count.
+    /// Negative values mean that some blocks may already be unavailable.
+    #[must_use]
+    pub fn redundancy(&self) -> i64 {
+        let failure_groups = self.failure_groups.len() as u64;
+        let copies = self.replicas.min(failure_groups);
+
+        let copies = i64::try_from(copies).unwrap_or(i64::MAX);
+        let degraded = i64::try_from(self.degraded.len()).unwrap_or(i64::MAX);
+
+        copies - degraded - 1
+    }
+
+    /// Returns `true` if losing any single failure group keeps all blocks
+    /// available.
+    #[must_use]
+    pub fn survives_failure_group_loss(&self) -> bool {
+        self.redundancy() > 0
+    }
+}
+
+// ----------------------------------------------------------------------------
+// prometheus
+// ----------------------------------------------------------------------------
+
+impl ToMetrics for Vec<Pool> {
+    fn to_metrics(&self) -> Vec<Family> {
+        let mut redundancy = Family::gauge(
+            "gpfs_pool_failure_group_redundancy",
+            "GPFS pool failure groups that can be lost without data becoming \
+             unavailable.",
+        );
+
+        for pool in self {
+            for (kind, r) in [("data", &pool.data), ("metadata", &pool.meta)] {
+                if let Some(r) = r {
+                    redundancy.sample(
+                        &[
+                            ("fs", &pool.fs_name),
+                            ("pool", &pool.name),
+                            ("type", kind),
+                        ],
+                        r.redundancy(),
+                    );
+                }
+            }
+        }
+
+        vec![redundancy]
+    }
+}
+
+// ----------------------------------------------------------------------------
+// tests
+// ----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prom::ToText;
+
+    fn replication(data: u64, metadata: u64) -> Replication {
+        let input = format!(
+            "\
+mmlsfs::HEADER:version:reserved:reserved:deviceName:fieldName:data:remarks:
+mmlsfs::0:1:::gpfs1:defaultMetadataReplicas:{metadata}::
+mmlsfs::0:1:::gpfs1:maxMetadataReplicas:3::
+mmlsfs::0:1:::gpfs1:defaultDataReplicas:{data}::
+mmlsfs::0:1:::gpfs1:maxDataReplicas:3::
+"
+        );
+
+        Replication::from_reader(input.as_bytes()).unwrap()
+    }
+
+    fn disks(lines: &[&str]) -> Disks {
+        let mut input = String::from(
+            "mmlsdisk::HEADER:version:reserved:reserved:nsdName:driverType:\
+             sectorSize:failureGroup:metadata:data:status:availability:\
+             diskID:storagePool:remarks:numQuorumDisks:readQuorumValue:\
+             writeQuorumValue:diskSizeKB:diskUID:thinDiskType:replicaType:\n",
+        );
+
+        for line in lines {
+            input.push_str(line);
+            input.push('\n');
+        }
+
+        Disks::from_reader(input.as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn example() {
+        let input = include_str!("disk-full-example.in");
+        let disks = Disks::from_reader(input.as_bytes()).unwrap();
+
+        let pools = analyze("gpfs1", &disks, replication(1, 2));
+
+        let system = &pools[2];
+        assert_eq!(system.name(), "system");
+        assert_eq!(system.data(), None);
+
+        let meta = system.meta().unwrap();
+        assert_eq!(meta.failure_groups(), ["1"]);
+        assert!(meta.degraded().is_empty());
+        assert_eq!(meta.redundancy(), 0);
+        assert!(!meta.survives_failure_group_loss());
+
+        let nlsas = &pools[0];
+        assert_eq!(nlsas.name(), "nlsas");
+        assert_eq!(nlsas.data().unwrap().degraded(), ["2"]);
+        assert_eq!(nlsas.data().unwrap().redundancy(), -1);
+    }
+
+    #[test]
+    fn replicated() {
+        let disks = disks(&[
+            "mmlsdisk::0:1:::d1:nsd:512:1:Yes:Yes:ready:up:1:system::3:2:2:1024::::",
+            "mmlsdisk::0:1:::d2:nsd:512:2:Yes:Yes:ready:up:2:system::3:2:2:1024::::",
+            "mmlsdisk::0:1:::d3:nsd:512:3:Yes:Yes:ready:down:3:system::3:2:2:1024::::",
+            "mmlsdisk::0:1:::d4:nsd:512:3:Yes:Yes:emptied:down:4:system::3:2:2:1024::::",
+        ]);
+
+        let pools = analyze("gpfs1", &disks, replication(2, 3));
+        assert_eq!(pools.len(), 1);
+
+        let data = pools[0].data().unwrap();
+        assert_eq!(data.failure_groups(), ["1", "2", "3"]);
+        assert_eq!(data.degraded(), ["3"]);
+        assert_eq!(data.redundancy(), 0);
+
+        let meta = pools[0].meta().unwrap();
+        assert_eq!(meta.redundancy(), 1);
+        assert!(meta.survives_failure_group_loss());
+
+        let mut output = vec![];
+        pools.to_prom(&mut output).unwrap();
+
+        assert_eq!(
+            std::str::from_utf8(&output).unwrap(),
+            "\
+# HELP gpfs_pool_failure_group_redundancy GPFS pool failure groups that can be lost without data becoming unavailable.
+# TYPE gpfs_pool_failure_group_redundancy gauge
+gpfs_pool_failure_group_redundancy{fs=\"gpfs1\",pool=\"system\",type=\"data\"} 0
+gpfs_pool_failure_group_redundancy{fs=\"gpfs1\",pool=\"system\",type=\"metadata\"} 1
+"
+        );
+    }
+}
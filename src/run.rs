@@ -0,0 +1,967 @@
+//! Running `mm*` commands.
+//!
+//! All functions in this library that need to run `mm*` commands take a
+//! [`Runner`], which decides how the commands are actually executed:
+//!
+//! - [`Local`] runs them on the local node, optionally from a custom binary
+//!   directory, wrapped with e.g. `sudo` and with a timeout.
+//! - [`Replay`] returns previously captured output instead of running
+//!   anything, which is useful for testing.
+//! - [`Recorder`] wraps another runner and captures all output to a directory
+//!   that can later be used with [`Replay`].
+//!
+//! Unsuccessful commands are reported with a [`CommandError`] by
+//! [`Runner::run_checked`], which is what the library uses for all `mm*`
+//! commands.
+//!
+//! # Examples
+//!
+//! ```
+//! use mmoxi::run::{Cmd, Replay};
+//!
+//! # fn main() -> mmoxi::Result<()> {
+//! let runner = Replay::new().with(
+//!     Cmd::new("mmlsfs").args(["all", "-Y", "-B"]),
+//!     "\
+//! mmlsfs::HEADER:version:reserved:reserved:deviceName:fieldName:data:remarks:
+//! mmlsfs::0:1:::gpfs1:blockSize:8388608::
+//! ",
+//! );
+//!
+//! let names = mmoxi::fs::names(&runner)?;
+//! assert_eq!(names, ["gpfs1"]);
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io::{self, ErrorKind, Read, Write};
+use std::os::unix::process::CommandExt;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use nix::sys::signal::{Signal, killpg};
+use nix::unistd::Pid;
+
+use crate::{Error, Result};
+
+/// Runs commands.
+pub trait Runner {
+    /// Runs the command and returns its output.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the command could not be run at all. A command
+    /// that ran but was not successful is **not** an error, see
+    /// [`Output::success`].
+    fn run(&self, cmd: &Cmd) -> Result<Output>;
+
+    /// Runs the command and returns its output if it was successful.
+    ///
+    /// A command is considered unsuccessful if its exit code is non-zero or
+    /// if it wrote nothing to stdout but something to stderr. The latter is
+    /// how some `mm*` commands report errors.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the command could not be run. Returns
+    /// [`Error::Command`] if the command was not successful.
+    fn run_checked(&self, cmd: &Cmd) -> Result<Output> {
+        let output = self.run(cmd)?;
+
+        let error_without_output =
+            output.stdout.is_empty() && !output.stderr.is_empty();
+
+        if output.success() && !error_without_output {
+            Ok(output)
+        } else {
+            Err(CommandError::new(cmd, &output).into())
+        }
+    }
+}
+
+/// A command to run.
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+pub struct Cmd {
+    program: String,
+    args: Vec<String>,
+    stdin: Option<Vec<u8>>,
+}
+
+impl Cmd {
+    /// Creates a new command for `program`, which should be a bare program
+    /// name, e.g. `mmlsdisk`.
+    #[must_use]
+    pub fn new(program: impl Into<String>) -> Self {
+        Self {
+            program: program.into(),
+            args: vec![],
+            stdin: None,
+        }
+    }
+
+    /// Adds an argument.
+    #[must_use]
+    pub fn arg(mut self, arg: impl Into<String>) -> Self {
+        self.args.push(arg.into());
+        self
+    }
+
+    /// Adds multiple arguments.
+    #[must_use]
+    pub fn args<I, S>(mut self, args: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.args.extend(args.into_iter().map(Into::into));
+        self
+    }
+
+    /// Writes `input` to the standard input of the command, e.g. the
+    /// requests for `mmpmon`. Without input, the standard input is empty.
+    #[must_use]
+    pub fn stdin(mut self, input: impl Into<Vec<u8>>) -> Self {
+        self.stdin = Some(input.into());
+        self
+    }
+
+    /// Returns the program.
+    #[must_use]
+    pub fn program(&self) -> &str {
+        &self.program
+    }
+
+    /// Returns the arguments.
+    #[must_use]
+    pub fn get_args(&self) -> &[String] {
+        &self.args
+    }
+
+    /// Returns the input written to the standard input, if any.
+    #[must_use]
+    pub fn get_stdin(&self) -> Option<&[u8]> {
+        self.stdin.as_deref()
+    }
+
+    /// Returns the file name stem used for fixtures of this command.
+    ///
+    /// Program and arguments are joined with `_`. Any `%` and `/` are
+    /// percent-encoded, so the name is a valid file name. If there is input
+    /// (see [`Cmd::stdin`]), a hash of it is appended as `_stdin-<hash>`, so
+    /// the same command with different input gets a different name.
+    #[must_use]
+    pub fn fixture_name(&self) -> String {
+        let name = std::iter::once(&self.program)
+            .chain(&self.args)
+            .map(|s| s.replace('%', "%25").replace('/', "%2F"))
+            .collect::<Vec<_>>()
+            .join("_");
+
+        match &self.stdin {
+            Some(input) => format!("{name}_stdin-{:016x}", fnv1a(input)),
+            None => name,
+        }
+    }
+}
+
+/// Returns the 64-bit FNV-1a hash of `data`.
+///
+/// Unlike the hashers of the standard library, the result is stable across
+/// releases, which matters for the names of fixtures on disk.
+fn fnv1a(data: &[u8]) -> u64 {
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0100_0000_01b3;
+
+    data.iter().fold(OFFSET_BASIS, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(PRIME)
+    })
+}
+
+impl fmt::Display for Cmd {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.program)?;
+
+        for arg in &self.args {
+            write!(f, " {arg}")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Output of a command.
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Default)]
+pub struct Output {
+    /// The exit code, `None` if the process was terminated by a signal.
+    pub code: Option<i32>,
+
+    /// The data the process wrote to stdout.
+    pub stdout: Vec<u8>,
+
+    /// The data the process wrote to stderr.
+    pub stderr: Vec<u8>,
+}
+
+impl Output {
+    /// Returns successful output with the given `stdout`.
+    #[must_use]
+    pub fn from_stdout(stdout: impl Into<Vec<u8>>) -> Self {
+        Self {
+            code: Some(0),
+            stdout: stdout.into(),
+            stderr: vec![],
+        }
+    }
+
+    /// Returns `true` if the process exited with code zero.
+    #[must_use]
+    pub const fn success(&self) -> bool {
+        matches!(self.code, Some(0))
+    }
+}
+
+impl From<std::process::Output> for Output {
+    fn from(output: std::process::Output) -> Self {
+        Self {
+            code: output.status.code(),
+            stdout: output.stdout,
+            stderr: output.stderr,
+        }
+    }
+}
+
+// ----------------------------------------------------------------------------
+// errors
+// ----------------------------------------------------------------------------
+
+/// Messages of `mm*` commands that indicate a transient error, i.e. one that
+/// may go away if the command is retried later.
+const TRANSIENT_ERRORS: &[&str] = &[
+    "daemon is not running",
+    "mmfsd is not running",
+    "gpfs is not running",
+    "not mounted",
+    "quorum",
+    "recovery",
+    "is being expelled",
+    "timed out",
+    "try again",
+    "temporarily unavailable",
+];
+
+/// Error of an unsuccessful command.
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+pub struct CommandError {
+    command: String,
+    code: Option<i32>,
+    stderr: String,
+}
+
+impl CommandError {
+    /// Returns a new error for the output of `cmd`.
+    #[must_use]
+    pub fn new(cmd: &Cmd, output: &Output) -> Self {
+        Self {
+            command: cmd.to_string(),
+            code: output.code,
+            stderr: String::from_utf8_lossy(&output.stderr).trim().into(),
+        }
+    }
+
+    /// Returns the command line.
+    #[must_use]
+    pub fn command(&self) -> &str {
+        &self.command
+    }
+
+    /// Returns the exit code, `None` if the process was terminated by a
+    /// signal.
+    #[must_use]
+    pub const fn code(&self) -> Option<i32> {
+        self.code
+    }
+
+    /// Returns the captured stderr.
+    #[must_use]
+    pub fn stderr(&self) -> &str {
+        &self.stderr
+    }
+
+    /// Returns `true` if the error is likely transient, e.g. because the
+    /// daemon is down or a file system is not mounted at the moment, or
+    /// because the command was terminated by a signal.
+    #[must_use]
+    pub fn is_transient(&self) -> bool {
+        let stderr = self.stderr.to_lowercase();
+
+        self.code.is_none()
+            || TRANSIENT_ERRORS.iter().any(|error| stderr.contains(error))
+    }
+}
+
+impl fmt::Display for CommandError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} failed", self.command)?;
+
+        match self.code {
+            Some(code) => write!(f, " with exit code {code}")?,
+            None => write!(f, " by signal")?,
+        }
+
+        if !self.stderr.is_empty() {
+            write!(f, ": {}", self.stderr)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl std::error::Error for CommandError {}
+
+// ----------------------------------------------------------------------------
+// local
+// ----------------------------------------------------------------------------
+
+/// How often a command with a timeout is polled for completion.
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Runs commands on the local node.
+///
+/// Commands with a timeout (see [`Local::timeout`]) run in their own process
+/// group. If the timeout expires, the whole process group is killed and
+/// [`Error::Timeout`] is returned. With a wrapper like `sudo`, the group
+/// belongs to another user and is killed with `kill` through the wrapper, so
+/// the wrapper needs to permit that, too.
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+pub struct Local {
+    bin_dir: Option<PathBuf>,
+    wrapper: Vec<String>,
+    timeout: Option<Duration>,
+    program_timeouts: HashMap<String, Duration>,
+}
+
+impl Local {
+    /// Returns a runner that finds programs via `PATH`.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Runs programs from `dir` instead of finding them via `PATH`, e.g.
+    /// `/usr/lpp/mmfs/bin`.
+    #[must_use]
+    pub fn bin_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.bin_dir = Some(dir.into());
+        self
+    }
+
+    /// Wraps all commands with the given command, e.g. `["sudo", "-n"]`.
+    #[must_use]
+    pub fn wrapper<I, S>(mut self, wrapper: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.wrapper = wrapper.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Kills commands that run longer than `timeout`.
+    #[must_use]
+    pub const fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Kills `program` if it runs longer than `timeout`, e.g. to allow
+    /// `mmapplypolicy` to run longer than other commands. This overrides
+    /// [`Local::timeout`] for this program.
+    #[must_use]
+    pub fn program_timeout(
+        mut self,
+        program: impl Into<String>,
+        timeout: Duration,
+    ) -> Self {
+        self.program_timeouts.insert(program.into(), timeout);
+        self
+    }
+
+    fn timeout_for(&self, cmd: &Cmd) -> Option<Duration> {
+        self.program_timeouts
+            .get(&cmd.program)
+            .copied()
+            .or(self.timeout)
+    }
+
+    fn command(&self, cmd: &Cmd) -> Command {
+        let program = self.bin_dir.as_ref().map_or_else(
+            || PathBuf::from(&cmd.program),
+            |dir| dir.join(&cmd.program),
+        );
+
+        let mut command =
+            if let Some((wrapper, args)) = self.wrapper.split_first() {
+                let mut command = Command::new(wrapper);
+                command.args(args);
+                command.arg(program);
+                command
+            } else {
+                Command::new(program)
+            };
+
+        command.args(&cmd.args);
+        command
+    }
+}
+
+impl Runner for Local {
+    fn run(&self, cmd: &Cmd) -> Result<Output> {
+        let mut command = self.command(cmd);
+        let program = command.get_program().to_string_lossy().into_owned();
+
+        let spawn_error = |source: io::Error| {
+            if source.kind() == ErrorKind::NotFound {
+                Error::CommandNotFound { command: program }
+            } else {
+                Error::Spawn {
+                    command: cmd.to_string(),
+                    source,
+                }
+            }
+        };
+
+        let stdin = if cmd.stdin.is_some() {
+            Stdio::piped()
+        } else {
+            Stdio::null()
+        };
+
+        let Some(timeout) = self.timeout_for(cmd) else {
+            let mut child = command
+                .stdin(stdin)
+                .stdout(Stdio::piped())
+                .stderr(Stdio::piped())
+                .spawn()
+                .map_err(spawn_error)?;
+
+            write_stdin(&mut child, cmd);
+
+            let output =
+                child.wait_with_output().map_err(|source| Error::Spawn {
+                    command: cmd.to_string(),
+                    source,
+                })?;
+
+            return Ok(output.into());
+        };
+
+        let mut child = command
+            .process_group(0)
+            .stdin(stdin)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(spawn_error)?;
+
+        write_stdin(&mut child, cmd);
+
+        wait_timeout(child, cmd, timeout, &self.wrapper)
+    }
+}
+
+/// Writes the input of `cmd` to the stdin of `child` in the background and
+/// closes it afterwards.
+///
+/// Write errors are ignored: a child that exits without reading all of its
+/// input reports that with its exit code.
+fn write_stdin(child: &mut Child, cmd: &Cmd) {
+    if let (Some(mut pipe), Some(input)) = (child.stdin.take(), &cmd.stdin) {
+        let input = input.clone();
+
+        thread::spawn(move || {
+            let _ = pipe.write_all(&input);
+        });
+    }
+}
+
+/// Waits for `child` to finish, killing its process group after `timeout`.
+///
+/// The timeout also covers reading its output, because processes the child
+/// started in the background may keep the pipes open after it exited.
+fn wait_timeout(
+    mut child: Child,
+    cmd: &Cmd,
+    timeout: Duration,
+    wrapper: &[String],
+) -> Result<Output> {
+    let wait_error = |source| Error::Spawn {
+        command: cmd.to_string(),
+        source,
+    };
+
+    let timeout_error = || Error::Timeout {
+        command: cmd.to_string(),
+        timeout,
+    };
+
+    // drain pipes concurrently, otherwise a child filling a pipe would block
+    let stdout = read_to_end(child.stdout.take());
+    let stderr = read_to_end(child.stderr.take());
+
+    let deadline = Instant::now() + timeout;
+
+    let status = loop {
+        if let Some(status) = child.try_wait().map_err(wait_error)? {
+            break status;
+        }
+
+        if Instant::now() >= deadline {
+            kill_process_group(&mut child, wrapper);
+            child.wait().map_err(wait_error)?;
+
+            return Err(timeout_error());
+        }
+
+        thread::sleep(POLL_INTERVAL);
+    };
+
+    let mut join = |reader: Receiver<io::Result<Vec<u8>>>| {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+
+        match reader.recv_timeout(remaining) {
+            Ok(result) => result.map_err(wait_error),
+            Err(RecvTimeoutError::Timeout) => {
+                kill_process_group(&mut child, wrapper);
+                Err(timeout_error())
+            }
+            Err(RecvTimeoutError::Disconnected) => {
+                Err(wait_error(io::Error::other("reader panicked")))
+            }
+        }
+    };
+
+    Ok(Output {
+        code: status.code(),
+        stdout: join(stdout)?,
+        stderr: join(stderr)?,
+    })
+}
+
+fn read_to_end(
+    pipe: Option<impl Read + Send + 'static>,
+) -> Receiver<io::Result<Vec<u8>>> {
+    let (sender, receiver) = mpsc::channel();
+
+    thread::spawn(move || {
+        let mut buf = Vec::new();
+
+        let result = match pipe {
+            Some(mut pipe) => pipe.read_to_end(&mut buf).map(|_| buf),
+            None => Ok(buf),
+        };
+
+        // the receiver is gone if the command timed out
+        let _ = sender.send(result);
+    });
+
+    receiver
+}
+
+/// Kills the process group of `child`.
+///
+/// With a wrapper like `sudo`, the group belongs to another user, so it is
+/// killed through the wrapper if killing it directly is not permitted.
+fn kill_process_group(child: &mut Child, wrapper: &[String]) {
+    let group = i32::try_from(child.id()).ok();
+
+    let killed = group.is_some_and(|group| {
+        killpg(Pid::from_raw(group), Signal::SIGKILL).is_ok()
+            || kill_command(wrapper, group).is_some_and(|mut command| {
+                command.status().is_ok_and(|status| status.success())
+            })
+    });
+
+    // fall back to at least killing the child itself
+    if !killed {
+        let _ = child.kill();
+    }
+}
+
+/// Returns the command that kills process `group` through `wrapper`, or
+/// [`None`] without a wrapper.
+fn kill_command(wrapper: &[String], group: i32) -> Option<Command> {
+    let (program, args) = wrapper.split_first()?;
+
+    let mut command = Command::new(program);
+    command
+        .args(args)
+        .args(["kill", "-KILL", "--", &format!("-{group}")])
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+
+    Some(command)
+}
+
+// ----------------------------------------------------------------------------
+// replay
+// ----------------------------------------------------------------------------
+
+/// Replays previously captured command output.
+///
+/// Output is looked up in memory first (see [`Replay::with`]) and then in the
+/// fixture directory (see [`Replay::from_dir`]). Commands without any
+/// fixture result in an error.
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+pub struct Replay {
+    outputs: HashMap<Cmd, Output>,
+    dir: Option<PathBuf>,
+}
+
+impl Replay {
+    /// Returns a runner without any fixtures.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns a runner reading fixtures from `dir`.
+    ///
+    /// For a command, the files `<name>.stdout`, `<name>.stderr` and
+    /// `<name>.code` are read, where `<name>` is the
+    /// [`Cmd::fixture_name`]. Only the `stdout` file is mandatory. A missing
+    /// `code` file means exit code zero. This is the layout written by
+    /// [`Recorder`].
+    #[must_use]
+    pub fn from_dir(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            outputs: HashMap::default(),
+            dir: Some(dir.into()),
+        }
+    }
+
+    /// Adds a successful command with the given `stdout`.
+    #[must_use]
+    pub fn with(self, cmd: Cmd, stdout: impl Into<Vec<u8>>) -> Self {
+        self.with_output(cmd, Output::from_stdout(stdout))
+    }
+
+    /// Adds a command with the given output.
+    #[must_use]
+    pub fn with_output(mut self, cmd: Cmd, output: Output) -> Self {
+        self.outputs.insert(cmd, output);
+        self
+    }
+
+    fn read_dir(dir: &Path, cmd: &Cmd) -> Result<Output> {
+        let name = cmd.fixture_name();
+
+        let stdout = read_optional(&dir.join(format!("{name}.stdout")))?
+            .ok_or_else(|| Error::NotFound(format!("fixture for {cmd}")))?;
+
+        let stderr = read_optional(&dir.join(format!("{name}.stderr")))?
+            .unwrap_or_default();
+
+        let code = read_optional(&dir.join(format!("{name}.code")))?;
+        let code = match code {
+            Some(code) => {
+                let code = String::from_utf8_lossy(&code);
+                let code = code.trim();
+
+                if code.is_empty() {
+                    None
+                } else {
+                    let code = code.parse().map_err(
+                        |error: std::num::ParseIntError| Error::Parse {
+                            line: 1,
+                            field: format!("exit code of {cmd}"),
+                            value: code.into(),
+                            message: error.to_string(),
+                        },
+                    )?;
+
+                    Some(code)
+                }
+            }
+            None => Some(0),
+        };
+
+        Ok(Output {
+            code,
+            stdout,
+            stderr,
+        })
+    }
+}
+
+impl Runner for Replay {
+    fn run(&self, cmd: &Cmd) -> Result<Output> {
+        if let Some(output) = self.outputs.get(cmd) {
+            return Ok(output.clone());
+        }
+
+        self.dir.as_ref().map_or_else(
+            || Err(Error::NotFound(format!("fixture for {cmd}"))),
+            |dir| Self::read_dir(dir, cmd),
+        )
+    }
+}
+
+fn read_optional(path: &Path) -> Result<Option<Vec<u8>>> {
+    match fs::read(path) {
+        Ok(content) => Ok(Some(content)),
+        Err(error) if error.kind() == ErrorKind::NotFound => Ok(None),
+        Err(error) => Err(Error::file(path)(error)),
+    }
+}
+
+// ----------------------------------------------------------------------------
+// recorder
+// ----------------------------------------------------------------------------
+
+/// Records the output of another runner to a fixture directory.
+///
+/// The written fixtures can be replayed with [`Replay::from_dir`].
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct Recorder<R> {
+    inner: R,
+    dir: PathBuf,
+}
+
+impl<R: Runner> Recorder<R> {
+    /// Returns a runner that records all output of `inner` to `dir`.
+    pub fn new(inner: R, dir: impl Into<PathBuf>) -> Self {
+        Self {
+            inner,
+            dir: dir.into(),
+        }
+    }
+
+    fn write(&self, cmd: &Cmd, output: &Output) -> Result<()> {
+        let name = cmd.fixture_name();
+
+        fs::create_dir_all(&self.dir).map_err(Error::file(&self.dir))?;
+
+        let code = output.code.map(|code| code.to_string());
+
+        for (extension, content) in [
+            ("stdout", output.stdout.as_slice()),
+            ("stderr", output.stderr.as_slice()),
+            ("code", code.as_deref().unwrap_or_default().as_bytes()),
+        ] {
+            let path = self.dir.join(format!("{name}.{extension}"));
+
+            fs::write(&path, content).map_err(Error::file(&path))?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<R: Runner> Runner for Recorder<R> {
+    fn run(&self, cmd: &Cmd) -> Result<Output> {
+        let output = self.inner.run(cmd)?;
+        self.write(cmd, &output)?;
+        Ok(output)
+    }
+}
+
+// ----------------------------------------------------------------------------
+// tests
+// ----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn display() {
+        let cmd = Cmd::new("mmlsdisk").arg("gpfs1").arg("-Y");
+        assert_eq!(cmd.to_string(), "mmlsdisk gpfs1 -Y");
+    }
+
+    #[test]
+    fn fixture_name() {
+        let cmd = Cmd::new("mmlsfileset").args(["gpfs1", "a/b%c", "-Y"]);
+        assert_eq!(cmd.fixture_name(), "mmlsfileset_gpfs1_a%2Fb%25c_-Y");
+    }
+
+    #[test]
+    fn fixture_name_stdin() {
+        let io = Cmd::new("mmpmon").args(["-p", "-s"]).stdin("io_s\n");
+        let rhist = Cmd::new("mmpmon").args(["-p", "-s"]).stdin("rhist s\n");
+
+        assert_eq!(io.fixture_name(), "mmpmon_-p_-s_stdin-eed59f961644d543");
+        assert_ne!(io.fixture_name(), rhist.fixture_name());
+
+        assert_eq!(fnv1a(b""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(fnv1a(b"a"), 0xaf63_dc4c_8601_ec8c);
+    }
+
+    #[test]
+    fn local_command() {
+        let runner = Local::new()
+            .bin_dir("/usr/lpp/mmfs/bin")
+            .wrapper(["sudo", "-n"]);
+
+        let command = runner.command(&Cmd::new("mmlsmgr").arg("-Y"));
+
+        assert_eq!(command.get_program(), "sudo");
+        assert_eq!(
+            command.get_args().collect::<Vec<_>>(),
+            ["-n", "/usr/lpp/mmfs/bin/mmlsmgr", "-Y"]
+        );
+    }
+
+    #[test]
+    fn local_timeout() {
+        let runner = Local::new()
+            .timeout(Duration::from_secs(10))
+            .program_timeout("sh", Duration::from_millis(100));
+
+        let output = runner.run(&Cmd::new("echo").arg("hello")).unwrap();
+        assert_eq!(output, Output::from_stdout("hello\n"));
+
+        // the grandchild keeps stdout open, so it must be killed, too
+        let cmd = Cmd::new("sh").args(["-c", "sleep 10 & sleep 10"]);
+
+        let start = Instant::now();
+        let error = runner.run(&cmd).unwrap_err();
+
+        assert!(start.elapsed() < Duration::from_secs(5));
+        assert!(matches!(error, Error::Timeout { .. }));
+        assert!(error.is_transient());
+    }
+
+    #[test]
+    fn local_timeout_background() {
+        let runner = Local::new().timeout(Duration::from_millis(200));
+
+        // the shell exits at once, but its background job keeps stdout open
+        let cmd = Cmd::new("sh").args(["-c", "sleep 10 &"]);
+
+        let start = Instant::now();
+        let error = runner.run(&cmd).unwrap_err();
+
+        assert!(start.elapsed() < Duration::from_secs(5));
+        assert!(matches!(error, Error::Timeout { .. }));
+    }
+
+    #[test]
+    fn kill_through_wrapper() {
+        assert!(kill_command(&[], 42).is_none());
+
+        let wrapper = ["sudo".to_owned(), "-n".to_owned()];
+        let command = kill_command(&wrapper, 42).unwrap();
+
+        assert_eq!(command.get_program(), "sudo");
+        assert_eq!(
+            command.get_args().collect::<Vec<_>>(),
+            ["-n", "kill", "-KILL", "--", "-42"]
+        );
+    }
+
+    #[test]
+    fn local_stdin() {
+        let cmd = Cmd::new("cat").stdin("hello\n");
+
+        let output = Local::new().run(&cmd).unwrap();
+        assert_eq!(output, Output::from_stdout("hello\n"));
+
+        let runner = Local::new().timeout(Duration::from_secs(10));
+        let output = runner.run(&cmd).unwrap();
+        assert_eq!(output, Output::from_stdout("hello\n"));
+
+        // without input, stdin is empty instead of inherited
+        let output = runner.run(&Cmd::new("cat")).unwrap();
+        assert_eq!(output, Output::from_stdout(""));
+    }
+
+    #[test]
+    fn checked() {
+        let ok = Cmd::new("ok");
+        let failed = Cmd::new("failed");
+        let silent = Cmd::new("silent");
+
+        let runner = Replay::new()
+            .with(ok.clone(), "out")
+            .with_output(
+                failed.clone(),
+                Output {
+                    code: Some(1),
+                    stdout: vec![],
+                    stderr: b"mmlsdisk: The GPFS daemon is not running.\n"
+                        .to_vec(),
+                },
+            )
+            .with_output(
+                silent.clone(),
+                Output {
+                    code: Some(0),
+                    stdout: vec![],
+                    stderr: b"mmdf: File system gpfs1 is invalid.".to_vec(),
+                },
+            );
+
+        assert_eq!(runner.run_checked(&ok).unwrap().stdout, b"out");
+
+        let error = runner.run_checked(&failed).unwrap_err();
+        let Error::Command(error) = error else {
+            panic!("not a command error: {error}");
+        };
+        assert_eq!(error.command(), "failed");
+        assert_eq!(error.code(), Some(1));
+        assert_eq!(
+            error.stderr(),
+            "mmlsdisk: The GPFS daemon is not running."
+        );
+        assert!(error.is_transient());
+        assert_eq!(
+            error.to_string(),
+            "failed failed with exit code 1: \
+             mmlsdisk: The GPFS daemon is not running."
+        );
+
+        let error = runner.run_checked(&silent).unwrap_err();
+        let Error::Command(error) = error else {
+            panic!("not a command error: {error}");
+        };
+        assert_eq!(error.code(), Some(0));
+        assert!(!error.is_transient());
+    }
+
+    #[test]
+    fn replay_missing() {
+        let runner = Replay::new();
+        assert!(matches!(
+            runner.run(&Cmd::new("mmlsmgr")),
+            Err(Error::NotFound(_))
+        ));
+    }
+
+    #[test]
+    fn record_and_replay() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let cmd = Cmd::new("mmlsmgr").arg("-Y");
+
+        let output = Output {
+            code: Some(1),
+            stdout: b"out".to_vec(),
+            stderr: b"err".to_vec(),
+        };
+
+        let inner = Replay::new().with_output(cmd.clone(), output.clone());
+        let recorder = Recorder::new(inner, dir.path());
+        assert_eq!(recorder.run(&cmd).unwrap(), output);
+
+        let replay = Replay::from_dir(dir.path());
+        assert_eq!(replay.run(&cmd).unwrap(), output);
+    }
+}
@@ -1,22 +1,26 @@
 //! `mmgetstate` parsing.
 
+use std::fmt::Display;
 use std::io::BufRead;
-use std::process::Command;
+use std::num::ParseIntError;
+use std::str::FromStr;
 
-use anyhow::{Context, Result, anyhow};
+use crate::{Error, Result};
+
+use crate::parse::{FromRecord, Record};
+use crate::prom::{Family, ToMetrics};
+use crate::run::{Cmd, Runner};
+use crate::util::serde_via_str;
 
 /// Returns the local node name.
 ///
 /// # Errors
 ///
 /// Returns an error if running `mmgetstate` fails or if parsing its output fails.
-pub fn local_node_name() -> Result<String> {
-    let mut cmd = Command::new("mmgetstate");
-    cmd.arg("-Y");
+pub fn local_node_name(runner: &dyn Runner) -> Result<String> {
+    let cmd = Cmd::new("mmgetstate").arg("-Y");
 
-    let output = cmd
-        .output()
-        .with_context(|| format!("error running: {cmd:?}"))?;
+    let output = runner.run_checked(&cmd)?;
 
     let states = States::from_reader(output.stdout.as_slice())?;
 
@@ -25,47 +29,99 @@ pub fn local_node_name() -> Result<String> {
         .into_iter()
         .next()
         .map(State::into_name)
-        .ok_or_else(|| anyhow!("no local state"))
+        .ok_or_else(|| Error::NotFound("local node state".into()))
+}
+
+/// Returns the states of all nodes in the cluster.
+///
+/// # Errors
+///
+/// Returns an error if running `mmgetstate` fails or if parsing its output fails.
+pub fn all(runner: &dyn Runner) -> Result<States> {
+    let cmd = Cmd::new("mmgetstate").args(["-a", "-Y"]);
+
+    let output = runner.run_checked(&cmd)?;
+
+    States::from_reader(output.stdout.as_slice())
 }
 
 /// Parsed states.
 #[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct States(Vec<State>);
 
 impl States {
     fn from_reader<Input: BufRead>(input: Input) -> Result<Self> {
-        let mut index = Index::default();
-        let mut nsds = Self::default();
+        crate::parse::from_reader(input).map(Self)
+    }
 
-        for line in input.lines() {
-            let line = line?;
+    /// Returns the states.
+    #[must_use]
+    pub fn states(&self) -> &[State] {
+        &self.0
+    }
 
-            let tokens = line.split(':').collect::<Vec<_>>();
+    /// Returns the number of quorum nodes required for quorum.
+    ///
+    /// Only nodes that are up report this, so this is [`None`] if no node is.
+    #[must_use]
+    pub fn quorum(&self) -> Option<u64> {
+        self.0.iter().find_map(State::quorum)
+    }
 
-            if tokens[2] == "HEADER" {
-                index = Index::default();
-                header_to_index(&tokens, &mut index);
-            } else {
-                let entry = State::from_tokens(&tokens, &index)?;
-                nsds.0.push(entry);
-            }
-        }
+    /// Returns `true` if the cluster uses tiebreaker disks for quorum.
+    ///
+    /// Like [`States::quorum`], this is only reported by nodes that are up.
+    #[must_use]
+    pub fn tiebreaker(&self) -> Option<bool> {
+        self.0
+            .iter()
+            .find(|state| state.quorum.is_some())
+            .map(State::tiebreaker)
+    }
 
-        Ok(nsds)
+    /// Returns the number of nodes that are up.
+    #[must_use]
+    pub fn nodes_up(&self) -> Option<u64> {
+        self.0.iter().find_map(State::nodes_up)
     }
 
-    /// Returns the states.
+    /// Returns the number of nodes in the cluster.
     #[must_use]
-    pub fn states(&self) -> &[State] {
-        &self.0
+    pub fn total_nodes(&self) -> Option<u64> {
+        self.0.iter().find_map(State::total_nodes)
+    }
+
+    /// Returns the number of quorum nodes that are active.
+    #[must_use]
+    pub fn quorum_nodes_active(&self) -> usize {
+        self.0
+            .iter()
+            .filter(|state| state.is_quorum_node())
+            .filter(|state| state.daemon == NodeState::Active)
+            .count()
+    }
+
+    /// Returns `true` if enough quorum nodes are active to maintain quorum.
+    #[must_use]
+    pub fn has_quorum(&self) -> bool {
+        self.quorum()
+            .is_some_and(|quorum| self.quorum_nodes_active() as u64 >= quorum)
     }
 }
 
 /// State data.
 #[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct State {
     name: String,
-    state: String,
+    number: u64,
+    daemon: NodeState,
+    quorum: Option<u64>,
+    tiebreaker: bool,
+    nodes_up: Option<u64>,
+    total_nodes: Option<u64>,
+    remarks: String,
 }
 
 impl State {
@@ -81,10 +137,205 @@ impl State {
         self.name
     }
 
+    /// Returns the node number.
+    #[must_use]
+    pub const fn number(&self) -> u64 {
+        self.number
+    }
+
     /// Returns the state.
     #[must_use]
-    pub fn state(&self) -> &str {
-        &self.state
+    pub const fn state(&self) -> &NodeState {
+        &self.daemon
+    }
+
+    /// Returns the number of quorum nodes required for quorum, as seen by
+    /// this node.
+    #[must_use]
+    pub const fn quorum(&self) -> Option<u64> {
+        self.quorum
+    }
+
+    /// Returns `true` if the cluster uses tiebreaker disks for quorum, as
+    /// seen by this node.
+    ///
+    /// `mmgetstate` marks this with a `*` after the quorum, e.g. `1*`.
+    #[must_use]
+    pub const fn tiebreaker(&self) -> bool {
+        self.tiebreaker
+    }
+
+    /// Returns the number of nodes that are up, as seen by this node.
+    #[must_use]
+    pub const fn nodes_up(&self) -> Option<u64> {
+        self.nodes_up
+    }
+
+    /// Returns the number of nodes in the cluster, as seen by this node.
+    #[must_use]
+    pub const fn total_nodes(&self) -> Option<u64> {
+        self.total_nodes
+    }
+
+    /// Returns the remarks, e.g. `quorum node`.
+    #[must_use]
+    pub fn remarks(&self) -> &str {
+        &self.remarks
+    }
+
+    /// Returns `true` if this is a quorum node.
+    #[must_use]
+    pub fn is_quorum_node(&self) -> bool {
+        self.remarks.contains("quorum node")
+    }
+}
+
+/// GPFS daemon state of a node.
+///
+/// Displayed and serialized as `mmgetstate` reports it, e.g. `active`.
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+#[non_exhaustive]
+pub enum NodeState {
+    /// The daemon is up and the node has joined the cluster.
+    Active,
+
+    /// The daemon is up and trying to form a quorum.
+    Arbitrating,
+
+    /// The daemon is not running.
+    Down,
+
+    /// The literal `unknown` state of `mmgetstate`, e.g. because the node
+    /// cannot be reached. It is displayed and serialized as `unknown`.
+    ///
+    /// This is not [`NodeState::Unknown`], which holds any state this library
+    /// doesn't know about.
+    Unreachable,
+
+    /// Any other state, displayed and serialized as reported.
+    Unknown(String),
+}
+
+impl NodeState {
+    const ALL: [&'static str; 4] =
+        ["active", "arbitrating", "down", "unknown"];
+
+    /// Returns the state name, with all unknown states named `unknown`.
+    const fn name(&self) -> &'static str {
+        match self {
+            Self::Active => "active",
+            Self::Arbitrating => "arbitrating",
+            Self::Down => "down",
+            Self::Unreachable | Self::Unknown(_) => "unknown",
+        }
+    }
+}
+
+impl Display for NodeState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            Self::Unknown(s) => s.as_str(),
+            state => state.name(),
+        };
+
+        write!(f, "{s}")
+    }
+}
+
+impl FromStr for NodeState {
+    type Err = crate::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "active" => Ok(Self::Active),
+            "arbitrating" => Ok(Self::Arbitrating),
+            "down" => Ok(Self::Down),
+            "unknown" => Ok(Self::Unreachable),
+            unknown => Ok(Self::Unknown(unknown.into())),
+        }
+    }
+}
+
+serde_via_str!(NodeState);
+
+// ----------------------------------------------------------------------------
+// prometheus
+// ----------------------------------------------------------------------------
+
+impl ToMetrics for States {
+    fn to_metrics(&self) -> Vec<Family> {
+        let mut state =
+            Family::gauge("gpfs_node_state", "GPFS node daemon state.");
+        let mut quorum_node = Family::gauge(
+            "gpfs_node_quorum_node",
+            "GPFS node is quorum node.",
+        );
+
+        for node in &self.0 {
+            for name in NodeState::ALL {
+                state.sample(
+                    &[("node", &node.name), ("state", name)],
+                    node.daemon.name() == name,
+                );
+            }
+
+            quorum_node.sample(&[("node", &node.name)], node.is_quorum_node());
+        }
+
+        let mut quorum = Family::gauge(
+            "gpfs_cluster_quorum",
+            "GPFS cluster number of quorum nodes required for quorum.",
+        );
+        if let Some(value) = self.quorum() {
+            quorum.sample(&[], value);
+        }
+
+        let mut tiebreaker = Family::gauge(
+            "gpfs_cluster_quorum_tiebreaker",
+            "GPFS cluster uses tiebreaker disks for quorum.",
+        );
+        if let Some(value) = self.tiebreaker() {
+            tiebreaker.sample(&[], value);
+        }
+
+        let mut quorum_nodes_active = Family::gauge(
+            "gpfs_cluster_quorum_nodes_active",
+            "GPFS cluster number of active quorum nodes.",
+        );
+        quorum_nodes_active.sample(&[], self.quorum_nodes_active());
+
+        let mut has_quorum = Family::gauge(
+            "gpfs_cluster_has_quorum",
+            "GPFS cluster has quorum.",
+        );
+        has_quorum.sample(&[], self.has_quorum());
+
+        let mut nodes_up = Family::gauge(
+            "gpfs_cluster_nodes_up",
+            "GPFS cluster number of nodes that are up.",
+        );
+        if let Some(value) = self.nodes_up() {
+            nodes_up.sample(&[], value);
+        }
+
+        let mut total_nodes = Family::gauge(
+            "gpfs_cluster_nodes",
+            "GPFS cluster number of nodes.",
+        );
+        if let Some(value) = self.total_nodes() {
+            total_nodes.sample(&[], value);
+        }
+
+        vec![
+            state,
+            quorum_node,
+            quorum,
+            tiebreaker,
+            quorum_nodes_active,
+            has_quorum,
+            nodes_up,
+            total_nodes,
+        ]
     }
 }
 
@@ -92,34 +343,58 @@ impl State {
 // boiler-platy parsing
 // ----------------------------------------------------------------------------
 
-impl State {
-    fn from_tokens(tokens: &[&str], index: &Index) -> Result<Self> {
-        let name_index = index
-            .node_name
-            .ok_or_else(|| anyhow!("no node name index"))?;
-        let name = tokens[name_index].into();
+impl FromRecord for State {
+    fn from_record(record: &Record) -> Result<Self> {
+        let name = record.get("nodeName")?.into();
+        let number = record.parse("nodeNumber")?;
+        let daemon = record.parse("state")?;
+
+        // nodes that are not up don't report the cluster view
+        let (quorum, tiebreaker) = parse_quorum(record)?;
+        let nodes_up = parse_opt(record, "nodesUp")?;
+        let total_nodes = parse_opt(record, "totalNodes")?;
 
-        let state_index =
-            index.state.ok_or_else(|| anyhow!("no state index"))?;
-        let state = tokens[state_index].into();
+        let remarks = record.get("remarks")?.into();
 
-        Ok(Self { name, state })
+        Ok(Self {
+            name,
+            number,
+            daemon,
+            quorum,
+            tiebreaker,
+            nodes_up,
+            total_nodes,
+            remarks,
+        })
     }
 }
 
-#[derive(Debug, Default)]
-struct Index {
-    node_name: Option<usize>,
-    state: Option<usize>,
+/// Parses the quorum and whether it is followed by `*` for tiebreaker disks.
+fn parse_quorum(record: &Record) -> Result<(Option<u64>, bool)> {
+    let value = record.get("quorum")?;
+
+    let Some(quorum) = value.strip_suffix('*') else {
+        return Ok((parse_opt(record, "quorum")?, false));
+    };
+
+    let quorum =
+        quorum
+            .parse()
+            .map_err(|error: ParseIntError| Error::Parse {
+                line: record.line(),
+                field: "quorum".into(),
+                value: value.into(),
+                message: error.to_string(),
+            })?;
+
+    Ok((Some(quorum), true))
 }
 
-fn header_to_index(tokens: &[&str], index: &mut Index) {
-    for (i, token) in tokens.iter().enumerate() {
-        match *token {
-            "nodeName" => index.node_name = Some(i),
-            "state" => index.state = Some(i),
-            _ => {}
-        }
+fn parse_opt(record: &Record, field: &str) -> Result<Option<u64>> {
+    if record.get(field)?.is_empty() {
+        Ok(None)
+    } else {
+        record.parse(field).map(Some)
     }
 }
 
@@ -130,6 +405,7 @@ fn header_to_index(tokens: &[&str], index: &mut Index) {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::prom::ToText;
 
     #[test]
     fn parse() {
@@ -142,10 +418,109 @@ mod tests {
             fs.next(),
             Some(State {
                 name: "filer1".into(),
-                state: "active".into(),
+                number: 1,
+                daemon: NodeState::Active,
+                quorum: Some(2),
+                tiebreaker: false,
+                nodes_up: Some(3),
+                total_nodes: Some(88),
+                remarks: "quorum node".into(),
             })
         );
 
         assert_eq!(fs.next(), None);
     }
+
+    #[test]
+    fn parse_all() {
+        let input = include_str!("state-all-example.in");
+
+        let states = States::from_reader(input.as_bytes()).unwrap();
+        assert_eq!(states.states().len(), 6);
+
+        let filer3 = &states.states()[2];
+        assert_eq!(filer3.state(), &NodeState::Down);
+        assert_eq!(filer3.quorum(), None);
+        assert!(filer3.is_quorum_node());
+
+        let node003 = &states.states()[5];
+        assert_eq!(node003.state(), &NodeState::Unreachable);
+        assert_eq!(node003.state().to_string(), "unknown");
+        assert!(!node003.is_quorum_node());
+
+        assert_eq!(states.quorum(), Some(2));
+        assert_eq!(states.tiebreaker(), Some(false));
+        assert_eq!(states.nodes_up(), Some(4));
+        assert_eq!(states.total_nodes(), Some(6));
+        assert_eq!(states.quorum_nodes_active(), 2);
+        assert!(states.has_quorum());
+    }
+
+    #[test]
+    fn parse_tiebreaker() {
+        let input = include_str!("state-tiebreaker-example.in");
+
+        let states = States::from_reader(input.as_bytes()).unwrap();
+        assert_eq!(states.states().len(), 3);
+
+        let filer1 = &states.states()[0];
+        assert_eq!(filer1.quorum(), Some(1));
+        assert!(filer1.tiebreaker());
+
+        let filer2 = &states.states()[1];
+        assert_eq!(filer2.quorum(), None);
+        assert!(!filer2.tiebreaker());
+
+        assert_eq!(states.quorum(), Some(1));
+        assert_eq!(states.tiebreaker(), Some(true));
+        assert_eq!(states.quorum_nodes_active(), 1);
+        assert!(states.has_quorum());
+    }
+
+    #[test]
+    fn parse_invalid_quorum() {
+        let input = "\
+mmgetstate::HEADER:version:reserved:reserved:nodeName:nodeNumber:state:quorum:nodesUp:totalNodes:remarks:cnfsState:daemonShortName:
+mmgetstate::0:1:::filer1:1:active:x*:1:2:quorum node:(undefined):filer1:
+";
+
+        let error = States::from_reader(input.as_bytes()).unwrap_err();
+
+        assert!(matches!(
+            error,
+            Error::Parse { line: 2, ref value, .. } if value == "x*"
+        ));
+    }
+
+    #[test]
+    fn prometheus() {
+        let input = include_str!("state-all-example.in");
+        let states = States::from_reader(input.as_bytes()).unwrap();
+
+        let mut output = vec![];
+        states.to_prom(&mut output).unwrap();
+
+        let metrics = std::str::from_utf8(output.as_slice()).unwrap();
+
+        let expected = include_str!("state-example.prom");
+        assert_eq!(metrics, expected);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde() {
+        for (state, name) in [
+            (NodeState::Active, "active"),
+            (NodeState::Unreachable, "unknown"),
+            (NodeState::Unknown("foo".into()), "foo"),
+        ] {
+            assert_eq!(state.to_string(), name);
+
+            let json = serde_json::to_string(&state).unwrap();
+            assert_eq!(json, format!("\"{name}\""));
+
+            let parsed: NodeState = serde_json::from_str(&json).unwrap();
+            assert_eq!(parsed, state);
+        }
+    }
 }
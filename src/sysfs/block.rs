@@ -2,9 +2,10 @@
 
 use std::collections::HashMap;
 use std::fs::DirEntry;
+use std::num::ParseIntError;
 use std::str::FromStr;
 
-use anyhow::{Context, Result};
+use crate::{Error, Result};
 
 /// Returns `sysfs` statistics for all block devices.
 ///
@@ -14,11 +15,12 @@ use anyhow::{Context, Result};
 pub fn stat_all() -> Result<HashMap<String, Stat>> {
     let mut ret = HashMap::default();
 
-    let devices =
-        std::fs::read_dir("/sys/block").context("read_dir call failed")?;
+    let dir = "/sys/block";
+
+    let devices = std::fs::read_dir(dir).map_err(Error::file(dir))?;
 
     for device in devices {
-        let device = device.context("reading dir entry failed")?;
+        let device = device.map_err(Error::file(dir))?;
         let (device_name, stat) = stat(&device)?;
         ret.insert(device_name, stat);
     }
@@ -32,9 +34,7 @@ fn stat(device: &DirEntry) -> Result<(String, Stat)> {
     let stat_file = device.path().join("stat");
 
     let stat = std::fs::read_to_string(&stat_file)
-        .with_context(|| {
-            format!("reading stat file: {}", stat_file.display())
-        })?
+        .map_err(Error::file(&stat_file))?
         .parse()?;
 
     Ok((device_name, stat))
@@ -44,6 +44,7 @@ fn stat(device: &DirEntry) -> Result<(String, Stat)> {
 #[derive(
     Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Default,
 )]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Stat {
     /// Returns the number of read I/Os processed.
     pub read_ios: u64,
@@ -94,7 +95,7 @@ impl Stat {
 }
 
 impl FromStr for Stat {
-    type Err = anyhow::Error;
+    type Err = Error;
 
     fn from_str(s: &str) -> Result<Self> {
         let mut stat = Self::default();
@@ -102,71 +103,41 @@ impl FromStr for Stat {
         let mut tokens =
             s.trim().split(' ').filter(|token| !token.trim().is_empty());
 
-        stat.read_ios = tokens
-            .next()
-            .context("no read I/Os value")?
-            .parse()
-            .context("parsing read I/Os value")?;
-
-        stat.read_merges = tokens
-            .next()
-            .context("no read merges value")?
-            .parse()
-            .context("parsing read merges value")?;
-
-        stat.read_sectors = tokens
-            .next()
-            .context("no read sectors value")?
-            .parse()
-            .context("parsing read sectors value")?;
-
-        stat.read_ticks = tokens
-            .next()
-            .context("no read ticks value")?
-            .parse()
-            .context("parsing read ticks value")?;
-
-        stat.write_ios = tokens
-            .next()
-            .context("no write I/Os value")?
-            .parse()
-            .context("parsing write I/Os value")?;
-
-        stat.write_merges = tokens
-            .next()
-            .context("no write merges value")?
-            .parse()
-            .context("parsing write merges value")?;
-
-        stat.write_sectors = tokens
-            .next()
-            .context("no write sectors value")?
-            .parse()
-            .context("parsing write sectors value")?;
-
-        stat.write_ticks = tokens
-            .next()
-            .context("no write ticks value")?
-            .parse()
-            .context("parsing write ticks value")?;
-
-        stat.in_flight = tokens
-            .next()
-            .context("no in flight value")?
-            .parse()
-            .context("parsing in flight value")?;
-
-        stat.io_ticks = tokens
-            .next()
-            .context("no I/O ticks value")?
-            .parse()
-            .context("parsing I/O ticks value")?;
-
-        stat.time_in_queue = tokens
-            .next()
-            .context("no time in queue value")?
-            .parse()
-            .context("parsing time in queue value")?;
+        let mut next = |field: &str| {
+            let value = tokens.next().ok_or_else(|| Error::MissingField {
+                line: 1,
+                field: field.into(),
+            })?;
+
+            value.parse().map_err(|error: ParseIntError| Error::Parse {
+                line: 1,
+                field: field.into(),
+                value: value.into(),
+                message: error.to_string(),
+            })
+        };
+
+        stat.read_ios = next("read I/Os")?;
+
+        stat.read_merges = next("read merges")?;
+
+        stat.read_sectors = next("read sectors")?;
+
+        stat.read_ticks = next("read ticks")?;
+
+        stat.write_ios = next("write I/Os")?;
+
+        stat.write_merges = next("write merges")?;
+
+        stat.write_sectors = next("write sectors")?;
+
+        stat.write_ticks = next("write ticks")?;
+
+        stat.in_flight = next("in flight")?;
+
+        stat.io_ticks = next("I/O ticks")?;
+
+        stat.time_in_queue = next("time in queue")?;
 
         Ok(stat)
     }
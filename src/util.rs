@@ -2,10 +2,12 @@
 
 use std::str::FromStr;
 
-use anyhow::{Error, Result, anyhow};
+use crate::{Error, Result};
 
 /// Boolean type as used by various `mm* -Y` output.
 #[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
 pub enum MMBool {
     /// False.
     No,
@@ -32,7 +34,50 @@ impl FromStr for MMBool {
         match s.to_lowercase().as_str() {
             "no" | "0" => Ok(Self::No),
             "yes" | "1" => Ok(Self::Yes),
-            unknown => Err(anyhow!("unknown boolean value: {unknown}")),
+            unknown => Err(Error::InvalidValue {
+                kind: "boolean value",
+                value: unknown.into(),
+            }),
         }
     }
 }
+
+/// Implements `serde` for a type through its `Display` and [`FromStr`]
+/// implementations.
+///
+/// This is meant for status enums with an `Unknown(String)` fallback, whose
+/// derived implementations would turn unknown values into a map instead of a
+/// string. This way, every value is the same string as displayed.
+macro_rules! serde_via_str {
+    ($type:ty) => {
+        #[cfg(feature = "serde")]
+        impl serde::Serialize for $type {
+            fn serialize<S>(
+                &self,
+                serializer: S,
+            ) -> std::result::Result<S::Ok, S::Error>
+            where
+                S: serde::Serializer,
+            {
+                serializer.collect_str(self)
+            }
+        }
+
+        #[cfg(feature = "serde")]
+        impl<'de> serde::Deserialize<'de> for $type {
+            fn deserialize<D>(
+                deserializer: D,
+            ) -> std::result::Result<Self, D::Error>
+            where
+                D: serde::Deserializer<'de>,
+            {
+                let s =
+                    <String as serde::Deserialize>::deserialize(deserializer)?;
+
+                s.parse().map_err(serde::de::Error::custom)
+            }
+        }
+    };
+}
+
+pub(crate) use serde_via_str;